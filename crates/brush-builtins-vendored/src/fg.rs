@@ -21,11 +21,15 @@ impl builtins::Command for FgCommand {
 
         if let Some(job_spec) = &self.job_spec {
             if let Some(job) = context.shell.jobs.resolve_job_spec(job_spec) {
-                job.move_to_foreground()?;
+                if context.shell.options.manage_terminal {
+                    job.move_to_foreground()?;
+                } else {
+                    job.resume()?;
+                }
                 writeln!(stderr, "{}", job.command_line)?;
 
                 let result = job.wait().await?;
-                if context.shell.options.interactive {
+                if context.shell.options.manage_terminal {
                     sys::terminal::move_self_to_foreground()?;
                 }
 
@@ -46,11 +50,15 @@ impl builtins::Command for FgCommand {
             }
         } else {
             if let Some(job) = context.shell.jobs.current_job_mut() {
-                job.move_to_foreground()?;
+                if context.shell.options.manage_terminal {
+                    job.move_to_foreground()?;
+                } else {
+                    job.resume()?;
+                }
                 writeln!(stderr, "{}", job.command_line)?;
 
                 let result = job.wait().await?;
-                if context.shell.options.interactive {
+                if context.shell.options.manage_terminal {
                     sys::terminal::move_self_to_foreground()?;
                 }
 
@@ -3,11 +3,14 @@ use clap::{
     builder::{IntoResettable, StyledStr},
 };
 use std::{
-    io::{self, ErrorKind, Write},
+    io::{self, Write},
     str::FromStr,
 };
 
-use brush_core::{ExecutionResult, builtins};
+use brush_core::{
+    ExecutionResult, builtins,
+    sys::resource::{Resource, Virtual},
+};
 
 #[derive(Clone, Copy)]
 enum Unit {
@@ -30,69 +33,6 @@ impl Unit {
     }
 }
 
-#[derive(Clone, Copy)]
-enum Virtual {
-    Pipe,
-    VMem,
-}
-
-impl Virtual {
-    fn get(self) -> std::io::Result<(u64, u64)> {
-        match self {
-            Self::Pipe => {
-                let lim = nix::unistd::PathconfVar::PIPE_BUF as u64 * 512;
-                Ok((lim, lim))
-            }
-            Self::VMem => rlimit::Resource::AS
-                .get()
-                .or_else(|_| rlimit::Resource::VMEM.get()),
-        }
-    }
-    fn set(self, soft: u64, hard: u64) -> std::io::Result<()> {
-        match self {
-            Self::Pipe => Err(std::io::Error::from(ErrorKind::Unsupported)),
-            Self::VMem => rlimit::Resource::AS
-                .set(soft, hard)
-                .or_else(|_| rlimit::Resource::VMEM.set(soft, hard)),
-        }
-    }
-    const fn is_supported(self) -> bool {
-        match self {
-            Self::Pipe => true,
-            Self::VMem => {
-                rlimit::Resource::AS.is_supported() || rlimit::Resource::VMEM.is_supported()
-            }
-        }
-    }
-}
-
-#[derive(Clone, Copy)]
-enum Resource {
-    Phy(rlimit::Resource),
-    Virt(Virtual),
-}
-
-impl Resource {
-    fn get(self) -> std::io::Result<(u64, u64)> {
-        match self {
-            Self::Phy(res) => res.get(),
-            Self::Virt(res) => res.get(),
-        }
-    }
-    fn set(self, soft: u64, hard: u64) -> std::io::Result<()> {
-        match self {
-            Self::Phy(res) => res.set(soft, hard),
-            Self::Virt(res) => res.set(soft, hard),
-        }
-    }
-    const fn is_supported(self) -> bool {
-        match self {
-            Self::Phy(res) => res.is_supported(),
-            Self::Virt(res) => res.is_supported(),
-        }
-    }
-}
-
 #[derive(Clone, Copy)]
 struct ResourceDescription {
     resource: Resource,
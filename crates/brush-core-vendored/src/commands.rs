@@ -1,14 +1,22 @@
 //! Command execution
 
-use std::{borrow::Cow, ffi::OsStr, fmt::Display, process::Stdio, sync::Arc};
+use std::{
+    borrow::Cow, ffi::OsStr, fmt::Display, io::Write, path::{Path, PathBuf}, process::Stdio, sync::Arc,
+    time::SystemTime,
+};
 
 use brush_parser::ast;
 use itertools::Itertools;
-use sys::commands::{CommandExt, CommandFdInjectionExt, CommandFgControlExt};
+use sys::commands::{
+    CommandExt, CommandFdInjectionExt, CommandFgControlExt, CommandResourceLimitsExt,
+};
 
 use crate::{
     ErrorKind, ExecutionControlFlow, ExecutionParameters, ExecutionResult, Shell, ShellFd,
-    builtins, env, error, escape,
+    builtins,
+    commandtrace::TracedCommand,
+    env, error, escape,
+    interfaces::{CommandKind, CommandPolicyDecision, CommandPolicyRequest},
     interp::{self, Execute, ProcessGroupPolicy},
     openfiles::{self, OpenFile, OpenFiles},
     pathsearch, processes,
@@ -299,85 +307,179 @@ async fn invoke_debug_trap_handler_if_registered(
 ///   external commands; if not provided, the default search logic will
 ///   be used.
 pub async fn execute(
-    cmd_context: ExecutionContext<'_>,
+    mut cmd_context: ExecutionContext<'_>,
     process_group_id: &mut Option<i32>,
-    args: Vec<CommandArg>,
+    mut args: Vec<CommandArg>,
     use_functions: bool,
     path_dirs: Option<Vec<String>>,
 ) -> Result<ExecutionSpawnResult, error::Error> {
-    // First see if it's the name of a builtin.
+    let mut resolved = resolve_command(&mut cmd_context, use_functions, path_dirs.as_deref());
+
+    // Give any registered command policy a chance to block or rewrite the command.
+    if let Some(policy) = cmd_context.shell.command_policy().cloned() {
+        let policy_args = args.iter().map(ToString::to_string).collect_vec();
+        let request = CommandPolicyRequest {
+            args: policy_args.as_slice(),
+            kind: resolved.kind(),
+            working_dir: cmd_context.shell.working_dir(),
+        };
+
+        match policy.check(request).await {
+            CommandPolicyDecision::Allow => (),
+            CommandPolicyDecision::Rewrite(new_args) => {
+                let Some(command_name) = new_args.first() else {
+                    return Ok(ExecutionResult::success().into());
+                };
+                cmd_context.command_name.clone_from(command_name);
+                args = new_args.into_iter().map(CommandArg::String).collect();
+                resolved = resolve_command(&mut cmd_context, use_functions, path_dirs.as_deref());
+            }
+            CommandPolicyDecision::Deny { exit_code, message } => {
+                if let Some(message) = message {
+                    writeln!(cmd_context.stderr(), "{message}")?;
+                }
+                return Ok(ExecutionResult::new(exit_code).into());
+            }
+        }
+    }
+
+    let Some(trace) = cmd_context.params.trace() else {
+        return dispatch(cmd_context, process_group_id, args, resolved).await;
+    };
+
+    // Record the command before dispatching it, so that commands it runs in turn
+    // (e.g. in a function body) are listed after it.
+    let (pipeline_index, pipeline_len) = cmd_context.params.pipeline_position;
+    let index = trace.begin(TracedCommand {
+        args: args.iter().map(ToString::to_string).collect(),
+        kind: resolved.kind(),
+        path: resolved.path().map(Path::to_path_buf),
+        pid: None,
+        pipeline_index,
+        pipeline_len: pipeline_len.max(1),
+        start: SystemTime::now(),
+        end: None,
+        exit_code: None,
+    });
+
+    let mut result = dispatch(cmd_context, process_group_id, args, resolved).await;
+    match &mut result {
+        Ok(ExecutionSpawnResult::Completed(result)) => {
+            trace.finish(index, Some(result.exit_code.into()));
+        }
+        Ok(ExecutionSpawnResult::StartedProcess(child)) => {
+            trace.set_pid(index, child.pid());
+            child.set_trace(trace, index);
+        }
+        Err(_) => trace.finish(index, None),
+    }
+
+    result
+}
+
+/// What a simple command's name resolved to, as found by [`resolve_command`].
+enum ResolvedCommand {
+    Builtin(builtins::Registration),
+    Function(Arc<ast::FunctionDefinition>),
+    External(PathBuf),
+    NotFound,
+}
+
+impl ResolvedCommand {
+    const fn kind(&self) -> CommandKind {
+        match self {
+            Self::Builtin(_) => CommandKind::Builtin,
+            Self::Function(_) => CommandKind::Function,
+            Self::External(_) => CommandKind::External,
+            Self::NotFound => CommandKind::NotFound,
+        }
+    }
+
+    fn path(&self) -> Option<&Path> {
+        match self {
+            Self::External(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves the command's name in lookup order: special builtins, then shell
+/// functions, then other builtins, then external executables. Policy checks,
+/// tracing and dispatch all use the result, so they always agree.
+fn resolve_command(
+    cmd_context: &mut ExecutionContext<'_>,
+    use_functions: bool,
+    path_dirs: Option<&[String]>,
+) -> ResolvedCommand {
+    let name = cmd_context.command_name.as_str();
     let builtin = cmd_context
         .shell
         .builtins()
-        .get(&cmd_context.command_name)
+        .get(name)
+        .filter(|r| !r.disabled)
         .cloned();
 
-    // If we found a special builtin (that's not disabled), then invoke it.
-    if builtin
-        .as_ref()
-        .is_some_and(|r| !r.disabled && r.special_builtin)
-    {
-        return execute_builtin_command(&builtin.unwrap(), cmd_context, args).await;
+    // Special builtins take precedence over functions.
+    if let Some(builtin) = builtin.as_ref().filter(|r| r.special_builtin) {
+        return ResolvedCommand::Builtin(builtin.clone());
     }
 
     // Assuming we weren't requested not to do so, check if it's the name of
     // a shell function.
     if use_functions {
-        if let Some(func_reg) = cmd_context
-            .shell
-            .funcs()
-            .get(cmd_context.command_name.as_str())
-        {
-            // Strip the function name off args.
-            return invoke_shell_function(func_reg.definition.clone(), cmd_context, &args[1..])
-                .await;
+        if let Some(func_reg) = cmd_context.shell.funcs().get(name) {
+            return ResolvedCommand::Function(func_reg.definition.clone());
         }
     }
 
-    // If we found a (non-special) builtin and it's not disabled, then invoke it.
     if let Some(builtin) = builtin {
-        if !builtin.disabled {
-            return execute_builtin_command(&builtin, cmd_context, args).await;
-        }
+        return ResolvedCommand::Builtin(builtin);
     }
 
-    // We still haven't found a command to invoke. We'll need to look for an external command.
-    if !cmd_context.command_name.contains(std::path::MAIN_SEPARATOR) {
-        // All else failed; if we were given path directories to search, try to look through them
-        // for a matching executable. Otherwise, use our default search logic.
-        let path = if let Some(path_dirs) = path_dirs {
-            pathsearch::search_for_executable(
-                path_dirs.iter().map(String::as_str),
-                cmd_context.command_name.as_str(),
-            )
-            .next()
-        } else {
-            cmd_context
-                .shell
-                .find_first_executable_in_path_using_cache(&cmd_context.command_name)
-        };
+    // Names containing a path separator are run as-is.
+    if name.contains(std::path::MAIN_SEPARATOR) {
+        return ResolvedCommand::External(PathBuf::from(name));
+    }
 
-        if let Some(path) = path {
-            let resolved_path = path.to_string_lossy();
-            execute_external_command(
-                cmd_context,
-                resolved_path.as_ref(),
-                process_group_id,
-                &args[1..],
-            )
-        } else {
-            Err(ErrorKind::CommandNotFound(cmd_context.command_name).into())
-        }
+    // If we were given path directories to search, try to look through them
+    // for a matching executable. Otherwise, use our default search logic.
+    let path = if let Some(path_dirs) = path_dirs {
+        pathsearch::search_for_executable(path_dirs.iter().map(String::as_str), name).next()
     } else {
-        let resolved_path = cmd_context.command_name.clone();
+        cmd_context
+            .shell
+            .find_first_executable_in_path_using_cache(&cmd_context.command_name)
+    };
+
+    path.map_or(ResolvedCommand::NotFound, ResolvedCommand::External)
+}
 
+/// Dispatches a simple command to the builtin, function, or external command
+/// it resolved to.
+async fn dispatch(
+    cmd_context: ExecutionContext<'_>,
+    process_group_id: &mut Option<i32>,
+    args: Vec<CommandArg>,
+    resolved: ResolvedCommand,
+) -> Result<ExecutionSpawnResult, error::Error> {
+    match resolved {
+        ResolvedCommand::Builtin(builtin) => {
+            execute_builtin_command(&builtin, cmd_context, args).await
+        }
+        // Strip the function name off args.
+        ResolvedCommand::Function(definition) => {
+            invoke_shell_function(definition, cmd_context, &args[1..]).await
+        }
         // Strip the command name off args.
-        execute_external_command(
+        ResolvedCommand::External(path) => execute_external_command(
             cmd_context,
-            resolved_path.as_str(),
+            path.to_string_lossy().as_ref(),
             process_group_id,
             &args[1..],
-        )
+        ),
+        ResolvedCommand::NotFound => {
+            Err(ErrorKind::CommandNotFound(cmd_context.command_name).into())
+        }
     }
 }
 
@@ -427,10 +529,14 @@ pub(crate) fn execute_external_command(
     // If we're to lead our own process group and stdin is a terminal,
     // then we need to arrange for the new process to move itself
     // to the foreground.
-    if new_pg && child_stdin_is_terminal {
+    if new_pg && child_stdin_is_terminal && context.shell.options.manage_terminal {
         cmd.take_foreground();
     }
 
+    if let Some(limits) = context.params.resource_limits {
+        cmd.apply_resource_limits(limits);
+    }
+
     // When tracing is enabled, report.
     tracing::debug!(
         target: trace_categories::COMMANDS,
@@ -454,12 +560,16 @@ pub(crate) fn execute_external_command(
                 tracing::warn!("could not retrieve pid for child process");
             }
 
-            Ok(ExecutionSpawnResult::StartedProcess(
-                processes::ChildProcess::new(pid, child),
-            ))
+            let mut child = processes::ChildProcess::new(pid, child);
+            if let (Some(job_processes), Some(pid)) = (&context.params.job_processes, pid) {
+                job_processes.record(pid, *process_group_id);
+                child.set_job_processes(job_processes.clone());
+            }
+
+            Ok(ExecutionSpawnResult::StartedProcess(child))
         }
         Err(spawn_err) => {
-            if context.shell.options.interactive {
+            if context.shell.options.manage_terminal {
                 sys::terminal::move_self_to_foreground()?;
             }
 
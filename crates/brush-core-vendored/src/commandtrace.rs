@@ -0,0 +1,79 @@
+//! Structured records of the simple commands a shell executes.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::interfaces::CommandKind;
+
+/// A simple command recorded by a [`CommandTrace`].
+#[derive(Clone, Debug)]
+pub struct TracedCommand {
+    /// The expanded arguments, starting with the command name.
+    pub args: Vec<String>,
+    /// How the command name resolved.
+    pub kind: CommandKind,
+    /// The resolved executable path, for external commands.
+    pub path: Option<PathBuf>,
+    /// The process ID, for external commands.
+    pub pid: Option<i32>,
+    /// Zero-based position of the command within its pipeline.
+    pub pipeline_index: usize,
+    /// Number of commands in the pipeline.
+    pub pipeline_len: usize,
+    /// When the command started.
+    pub start: SystemTime,
+    /// When the command finished; `None` if it never completed.
+    pub end: Option<SystemTime>,
+    /// The exit code; `None` if the command failed to run or never completed.
+    pub exit_code: Option<u8>,
+}
+
+/// Shared, append-only log of executed commands; clones record into the
+/// same log, so one trace covers subshells and background jobs.
+#[derive(Clone, Default)]
+pub struct CommandTrace {
+    commands: Arc<Mutex<Vec<TracedCommand>>>,
+}
+
+impl CommandTrace {
+    /// Returns a new, empty trace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the commands recorded so far, in start order.
+    pub fn commands(&self) -> Vec<TracedCommand> {
+        self.lock().clone()
+    }
+
+    /// Records a command that just started, returning its index.
+    pub(crate) fn begin(&self, command: TracedCommand) -> usize {
+        let mut commands = self.lock();
+        commands.push(command);
+        commands.len() - 1
+    }
+
+    /// Records the process ID of a started command.
+    pub(crate) fn set_pid(&self, index: usize, pid: Option<i32>) {
+        if let Some(command) = self.lock().get_mut(index) {
+            command.pid = pid;
+        }
+    }
+
+    /// Records the completion of a command.
+    pub(crate) fn finish(&self, index: usize, exit_code: Option<u8>) {
+        if let Some(command) = self.lock().get_mut(index) {
+            command.end = Some(SystemTime::now());
+            command.exit_code = exit_code;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<TracedCommand>> {
+        self.commands
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
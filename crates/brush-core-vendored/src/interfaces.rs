@@ -1,5 +1,9 @@
 //! Exports traits for shell interfaces implemented by callers.
 
+mod commandpolicy;
+mod joboutput;
 mod keybindings;
 
+pub use commandpolicy::{CommandKind, CommandPolicy, CommandPolicyDecision, CommandPolicyRequest};
+pub use joboutput::JobOutputCapture;
 pub use keybindings::{InputFunction, Key, KeyAction, KeyBindings, KeySequence, KeyStroke};
@@ -0,0 +1,52 @@
+use std::{future::Future, path::Path, pin::Pin};
+
+/// Describes how a simple command's name resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandKind {
+    /// A builtin command.
+    Builtin,
+    /// A shell function.
+    Function,
+    /// An external executable.
+    External,
+    /// No builtin, function, or executable matched the name.
+    NotFound,
+}
+
+/// A simple command about to be executed.
+#[derive(Debug)]
+pub struct CommandPolicyRequest<'a> {
+    /// The expanded arguments, starting with the command name.
+    pub args: &'a [String],
+    /// How the command name resolved.
+    pub kind: CommandKind,
+    /// The shell's working directory.
+    pub working_dir: &'a Path,
+}
+
+/// A policy's verdict on a simple command.
+#[derive(Clone, Debug)]
+pub enum CommandPolicyDecision {
+    /// Run the command as-is.
+    Allow,
+    /// Run these arguments instead; the rewritten command is not re-checked.
+    Rewrite(Vec<String>),
+    /// Skip the command, completing with the given exit code.
+    Deny {
+        /// Exit code reported for the blocked command.
+        exit_code: u8,
+        /// Optional message written to the command's stderr.
+        message: Option<String>,
+    },
+}
+
+/// Consulted before every simple command the shell dispatches, including
+/// those run from functions, `eval`, and subshells.
+pub trait CommandPolicy: Send + Sync {
+    /// Decides whether the command may run.
+    // NOTE: we use a boxed future here so the trait stays object-safe.
+    fn check<'a>(
+        &'a self,
+        request: CommandPolicyRequest<'a>,
+    ) -> Pin<Box<dyn Future<Output = CommandPolicyDecision> + Send + 'a>>;
+}
@@ -0,0 +1,14 @@
+use crate::openfiles::OpenFile;
+
+/// Supplies the files a background job writes to, in place of the stdout
+/// and stderr it would otherwise inherit from the command that started it.
+pub trait JobOutputCapture: Send + Sync {
+    /// Opens the `(stdout, stderr)` files for a job about to start. Returning
+    /// `None` leaves the job's output unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `job_id` - The ID the job will be assigned.
+    /// * `command_line` - The command line of the job.
+    fn open(&self, job_id: usize, command_line: &str) -> Option<(OpenFile, OpenFile)>;
+}
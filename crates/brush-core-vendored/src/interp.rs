@@ -3,12 +3,15 @@ use itertools::Itertools;
 use std::collections::VecDeque;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 use sys::commands::ExitStatusExt;
 
 use crate::arithmetic::{self, ExpandAndEvaluate};
 use crate::commands::{self, CommandArg};
+use crate::commandtrace::CommandTrace;
 use crate::env::{EnvironmentLookup, EnvironmentScope};
+use crate::interfaces::JobOutputCapture;
 use crate::openfiles::{OpenFile, OpenFiles};
 use crate::results::{
     self, ExecutionExitCode, ExecutionResult, ExecutionSpawnResult, ExecutionWaitResult,
@@ -65,8 +68,18 @@ pub struct ExecutionParameters {
     open_files: openfiles::OpenFiles,
     /// Policy for how to manage spawned external processes.
     pub process_group_policy: ProcessGroupPolicy,
+    /// Resource limits for spawned external processes.
+    pub resource_limits: Option<ResourceLimits>,
     /// Optional cancellation token shared with callers.
     cancel_token: Option<CancellationToken>,
+    /// Optional trace recording each simple command executed.
+    trace: Option<CommandTrace>,
+    /// Index and length of the pipeline containing the current command.
+    pub(crate) pipeline_position: (usize, usize),
+    /// Optional capture for the output of background jobs started here.
+    job_output: Option<Arc<dyn JobOutputCapture>>,
+    /// Running processes started on behalf of the enclosing background job.
+    pub(crate) job_processes: Option<jobs::JobProcesses>,
 }
 
 impl ExecutionParameters {
@@ -80,6 +93,21 @@ impl ExecutionParameters {
         self.cancel_token.clone()
     }
 
+    /// Assigns a trace that records each simple command executed.
+    pub fn set_trace(&mut self, trace: CommandTrace) {
+        self.trace = Some(trace);
+    }
+
+    /// Returns the command trace, if present.
+    pub fn trace(&self) -> Option<CommandTrace> {
+        self.trace.clone()
+    }
+
+    /// Assigns a capture that receives the output of background jobs.
+    pub fn set_job_output(&mut self, capture: Arc<dyn JobOutputCapture>) {
+        self.job_output = Some(capture);
+    }
+
 	/// Returns true when cancellation has been requested.
 	pub fn is_cancelled(&self) -> bool {
 		self
@@ -210,6 +238,23 @@ pub enum ProcessGroupPolicy {
     SameProcessGroup,
 }
 
+/// Resource limits applied to external processes spawned during execution.
+///
+/// Limits are installed in the child before it executes, so they never
+/// constrain the shell itself. Only soft limits are set, clamped to the
+/// current hard limit, which stays unchanged; a process may raise its soft
+/// limit again up to the hard limit, so these guard against runaway commands
+/// rather than sandbox them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResourceLimits {
+    /// Maximum CPU time, in seconds.
+    pub cpu_seconds: Option<u64>,
+    /// Maximum size of the virtual address space, in bytes.
+    pub address_space_bytes: Option<u64>,
+    /// Maximum number of open file descriptors.
+    pub open_files: Option<u64>,
+}
+
 #[async_trait::async_trait]
 pub trait Execute {
     async fn execute(
@@ -298,8 +343,22 @@ fn spawn_ao_list_in_task<'a>(
 ) -> &'a jobs::Job {
     // Clone the inputs.
     let mut cloned_shell = shell.clone();
-    let cloned_params = params.clone();
+    let mut cloned_params = params.clone();
     let cloned_ao_list = ao_list.clone();
+    let command_line = ao_list.to_string();
+
+    // Route the job's output to the capture, if any; jobs it starts in turn
+    // share the same files.
+    if let Some(capture) = cloned_params.job_output.take() {
+        if let Some((stdout, stderr)) = capture.open(shell.jobs.next_id(), &command_line) {
+            cloned_params.set_fd(OpenFiles::STDOUT_FD, stdout);
+            cloned_params.set_fd(OpenFiles::STDERR_FD, stderr);
+        }
+    }
+
+    // Track the processes the job starts so it can be signaled.
+    let processes = jobs::JobProcesses::default();
+    cloned_params.job_processes = Some(processes.clone());
 
     // Mark the child shell as not interactive; we don't want it messing with the terminal too much.
     cloned_shell.options.interactive = false;
@@ -310,11 +369,14 @@ fn spawn_ao_list_in_task<'a>(
             .await
     });
 
-    shell.jobs.add_as_current(jobs::Job::new(
-        [jobs::JobTask::Internal(join_handle)],
-        ao_list.to_string(),
-        jobs::JobState::Running,
-    ))
+    shell.jobs.add_as_current(
+        jobs::Job::new(
+            [jobs::JobTask::Internal(join_handle)],
+            command_line,
+            jobs::JobState::Running,
+        )
+        .with_processes(processes),
+    )
 }
 
 #[async_trait::async_trait]
@@ -523,7 +585,7 @@ async fn wait_for_pipeline_processes_and_update_status(
         }
     }
 
-    if shell.options.interactive {
+    if shell.options.manage_terminal {
         sys::terminal::move_self_to_foreground()?;
     }
 
@@ -1107,7 +1169,7 @@ impl ExecuteInPipeline for ast::SimpleCommand {
 
 async fn execute_command(
     context: &mut PipelineExecutionContext<'_>,
-    params: ExecutionParameters,
+    mut params: ExecutionParameters,
     cmd_name: String,
     assignments: Vec<&ast::Assignment>,
     args: Vec<CommandArg>,
@@ -1139,6 +1201,8 @@ async fn execute_command(
             .await?;
     }
 
+    params.pipeline_position = (context.current_pipeline_index, context.pipeline_len);
+
     let mut cmd_context = commands::ExecutionContext {
         shell: context.shell,
         command_name: cmd_name,
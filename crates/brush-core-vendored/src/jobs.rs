@@ -2,6 +2,7 @@
 
 use std::collections::VecDeque;
 use std::fmt::Display;
+use std::sync::{Arc, Mutex};
 
 use futures::FutureExt;
 
@@ -60,17 +61,16 @@ impl JobTask {
         }
     }
 
-    #[allow(clippy::unwrap_in_result)]
     fn poll(&mut self) -> Option<Result<ExecutionResult, error::Error>> {
         match self {
             Self::External(process) => {
                 let check_result = process.poll();
                 check_result.map(|polled_result| polled_result.map(|output| output.into()))
             }
-            Self::Internal(handle) => {
-                let checkable_handle = handle;
-                checkable_handle.now_or_never().map(|r| r.unwrap())
-            }
+            // Aborted tasks complete with a join error rather than a result.
+            Self::Internal(handle) => handle
+                .now_or_never()
+                .map(|r| r.unwrap_or_else(|err| Err(err.into()))),
         }
     }
 }
@@ -95,13 +95,18 @@ impl JobManager {
             }
         }
 
-        let id = self.jobs.len() + 1;
-        job.id = id;
+        job.id = self.next_id();
         job.annotation = JobAnnotation::Current;
         self.jobs.push(job);
         self.jobs.last().unwrap()
     }
 
+    /// Returns the ID the next added job will be assigned: one more than
+    /// the highest ID in use.
+    pub fn next_id(&self) -> usize {
+        self.jobs.iter().map(|j| j.id).max().unwrap_or(0) + 1
+    }
+
     /// Returns the current job, if there is one.
     pub fn current_job(&self) -> Option<&Job> {
         self.jobs
@@ -243,6 +248,48 @@ impl Display for JobAnnotation {
     }
 }
 
+/// A running process started on behalf of a job.
+#[derive(Clone, Copy)]
+struct JobProcess {
+    pid: sys::process::ProcessId,
+    /// The process group it belongs to, if the shell created one.
+    pgid: Option<sys::process::ProcessId>,
+}
+
+/// Shared list of the running processes started by a job that executes in a
+/// task; clones record into the same list.
+#[derive(Clone, Default)]
+pub(crate) struct JobProcesses {
+    processes: Arc<Mutex<Vec<JobProcess>>>,
+}
+
+impl JobProcesses {
+    /// Records a process started by the job.
+    pub(crate) fn record(
+        &self,
+        pid: sys::process::ProcessId,
+        pgid: Option<sys::process::ProcessId>,
+    ) {
+        self.lock().push(JobProcess { pid, pgid });
+    }
+
+    /// Forgets a process once it has been reaped, so its ID is never
+    /// signaled after being reused.
+    pub(crate) fn forget(&self, pid: sys::process::ProcessId) {
+        self.lock().retain(|p| p.pid != pid);
+    }
+
+    fn list(&self) -> Vec<JobProcess> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<JobProcess>> {
+        self.processes
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
 /// Encapsulates a set of processes managed by the shell as a single unit.
 pub struct Job {
     /// The tasks that make up the job.
@@ -251,6 +298,9 @@ pub struct Job {
     /// If available, the process group ID of the job's processes.
     pgid: Option<sys::process::ProcessId>,
 
+    /// Running processes started by the job's tasks, if tracked.
+    processes: Option<JobProcesses>,
+
     /// The annotation of the job (e.g., current, previous).
     annotation: JobAnnotation,
 
@@ -293,12 +343,20 @@ impl Job {
             id: 0,
             tasks: tasks.into_iter().collect(),
             pgid: None,
+            processes: None,
             annotation: JobAnnotation::None,
             command_line,
             state,
         }
     }
 
+    /// Tracks the processes the job's tasks start, so the job can be
+    /// signaled even though it has no child process of its own.
+    pub(crate) fn with_processes(mut self, processes: JobProcesses) -> Self {
+        self.processes = Some(processes);
+        self
+    }
+
     /// Returns a pid-style string for the job.
     pub fn to_pid_style_string(&self) -> String {
         let display_pid = self
@@ -397,6 +455,17 @@ impl Job {
 
     /// Moves the job to execute in the foreground.
     pub fn move_to_foreground(&mut self) -> Result<(), error::Error> {
+        self.resume()?;
+
+        if let Some(pgid) = self.process_group_id() {
+            sys::terminal::move_to_foreground(pgid)?;
+        }
+
+        Ok(())
+    }
+
+    /// Continues the job if it is stopped, without taking the terminal.
+    pub fn resume(&mut self) -> Result<(), error::Error> {
         if matches!(self.state, JobState::Stopped) {
             if let Some(pgid) = self.process_group_id() {
                 sys::signal::continue_process(pgid)?;
@@ -406,10 +475,6 @@ impl Job {
             }
         }
 
-        if let Some(pgid) = self.process_group_id() {
-            sys::terminal::move_to_foreground(pgid)?;
-        }
-
         Ok(())
     }
 
@@ -419,10 +484,23 @@ impl Job {
     ///
     /// * `signal` - The signal to send to the job.
     pub fn kill(&self, signal: traps::TrapSignal) -> Result<(), error::Error> {
-        if let Some(pid) = self.process_group_id() {
-            sys::signal::kill_process(pid, signal)
-        } else {
-            Err(error::ErrorKind::FailedToSendSignal.into())
+        self.signaler().kill(signal)
+    }
+
+    /// Returns a handle that can signal the job without access to it, e.g.
+    /// while the shell that owns it is busy running another command.
+    pub fn signaler(&self) -> JobSignaler {
+        JobSignaler {
+            processes: self.processes.clone(),
+            pid: self.pgid.or_else(|| self.representative_pid()),
+            tasks: self
+                .tasks
+                .iter()
+                .filter_map(|task| match task {
+                    JobTask::Internal(handle) => Some(handle.abort_handle()),
+                    JobTask::External(_) => None,
+                })
+                .collect(),
         }
     }
 
@@ -441,10 +519,200 @@ impl Job {
         None
     }
 
+    /// Returns the IDs of the job's running processes, as far as they are known.
+    pub fn process_ids(&self) -> Vec<sys::process::ProcessId> {
+        let tracked = self
+            .processes
+            .as_ref()
+            .map(JobProcesses::list)
+            .unwrap_or_default();
+        self.tasks
+            .iter()
+            .filter_map(|task| match task {
+                JobTask::External(p) => p.pid(),
+                JobTask::Internal(_) => None,
+            })
+            .chain(tracked.into_iter().map(|p| p.pid))
+            .collect()
+    }
+
     /// Tries to retrieve the process group ID (PGID) of the job.
     pub fn process_group_id(&self) -> Option<sys::process::ProcessId> {
         // TODO: Don't assume that the first PID is the PGID.
-        self.pgid.or_else(|| self.representative_pid())
+        self.pgid.or_else(|| self.representative_pid()).or_else(|| {
+            self.processes
+                .as_ref()
+                .and_then(|processes| processes.list().iter().rev().find_map(|p| p.pgid))
+        })
+    }
+
+}
+
+/// Signals the processes of a job independently of the job itself.
+#[derive(Clone)]
+pub struct JobSignaler {
+    /// Running processes started by the job's tasks, if tracked.
+    processes: Option<JobProcesses>,
+    /// The process to signal when no processes are tracked.
+    pid: Option<sys::process::ProcessId>,
+    /// Abort handles of the job's internal tasks.
+    tasks: Vec<tokio::task::AbortHandle>,
+}
+
+impl JobSignaler {
+    /// Sends a signal to the job.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - The signal to send to the job.
+    pub fn kill(&self, signal: traps::TrapSignal) -> Result<(), error::Error> {
+        // Jobs running in a task signal each running process they started, or
+        // its whole process group when the shell created one.
+        let tracked = self
+            .processes
+            .as_ref()
+            .map(JobProcesses::list)
+            .unwrap_or_default();
+        if !tracked.is_empty() {
+            let mut signaled_groups = vec![];
+            let mut signaled = false;
+            for process in tracked {
+                let sent = match process.pgid {
+                    Some(pgid) if signaled_groups.contains(&pgid) => continue,
+                    Some(pgid) => {
+                        signaled_groups.push(pgid);
+                        sys::signal::kill_process_group(pgid, signal)
+                    }
+                    None => sys::signal::kill_process(process.pid, signal),
+                };
+                signaled |= sent.is_ok();
+            }
+            return if signaled {
+                Ok(())
+            } else {
+                Err(error::ErrorKind::FailedToSendSignal.into())
+            };
+        }
+
+        if let Some(pid) = self.pid {
+            return sys::signal::kill_process(pid, signal);
+        }
+
+        // With no process to signal (e.g. a job running only builtins),
+        // terminating signals abort the job's tasks instead.
+        let terminates = matches!(signal.as_str(), "SIGHUP" | "SIGINT" | "SIGKILL" | "SIGTERM");
+        if terminates && !self.tasks.is_empty() {
+            self.tasks.iter().for_each(tokio::task::AbortHandle::abort);
+            Ok(())
+        } else {
+            Err(error::ErrorKind::FailedToSendSignal.into())
+        }
+    }
+}
+
+#[expect(clippy::panic_in_result_fn)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn job(command_line: &str) -> Job {
+        Job::new([], command_line.into(), JobState::Running)
+    }
+
+    #[test]
+    fn test_job_ids_follow_highest_in_use() {
+        let mut manager = JobManager::new();
+        for command_line in ["a", "b", "c"] {
+            manager.add_as_current(job(command_line));
+        }
+        assert_eq!(manager.jobs.iter().map(|j| j.id).collect::<Vec<_>>(), [1, 2, 3]);
+
+        // IDs freed below the highest one are not reused.
+        manager.jobs.retain(|j| j.id != 2);
+        assert_eq!(manager.next_id(), 4);
+
+        manager.jobs.retain(|j| j.id != 3);
+        assert_eq!(manager.add_as_current(job("d")).id, 2);
+
+        manager.jobs.clear();
+        assert_eq!(manager.next_id(), 1);
+    }
+
+    #[cfg(unix)]
+    fn spawn_sleep(new_group: bool) -> Result<std::process::Child> {
+        use std::os::unix::process::CommandExt;
+
+        let mut command = std::process::Command::new("sleep");
+        command.arg("30");
+        if new_group {
+            command.process_group(0);
+        }
+        Ok(command.spawn()?)
     }
 
+    #[cfg(unix)]
+    fn signal_of(mut child: std::process::Child) -> Result<Option<i32>> {
+        use std::os::unix::process::ExitStatusExt;
+
+        Ok(child.wait()?.signal())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_signals_tracked_processes_and_groups() -> Result<()> {
+        let term = traps::TrapSignal::Signal(sys::signal::Signal::SIGTERM);
+        let grouped = spawn_sleep(true)?;
+        let ungrouped = spawn_sleep(false)?;
+        let grouped_pid = i32::try_from(grouped.id())?;
+        let ungrouped_pid = i32::try_from(ungrouped.id())?;
+
+        let processes = JobProcesses::default();
+        processes.record(grouped_pid, Some(grouped_pid));
+        processes.record(ungrouped_pid, None);
+        let job = job("sleep 30").with_processes(processes.clone());
+        assert_eq!(job.process_ids(), [grouped_pid, ungrouped_pid]);
+
+        job.kill(term)?;
+        assert_eq!(signal_of(grouped)?, Some(nix::libc::SIGTERM));
+        assert_eq!(signal_of(ungrouped)?, Some(nix::libc::SIGTERM));
+
+        // Reaped processes are forgotten and never signaled again.
+        processes.forget(grouped_pid);
+        processes.forget(ungrouped_pid);
+        assert_eq!(job.process_ids(), [] as [i32; 0]);
+        assert!(job.kill(term).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_aborts_jobs_without_processes() -> Result<()> {
+        let spawn_pending = || {
+            Job::new(
+                [JobTask::Internal(tokio::spawn(std::future::pending()))],
+                "sleep 30".into(),
+                JobState::Running,
+            )
+            .with_processes(JobProcesses::default())
+        };
+
+        let job = spawn_pending();
+        let usr1 = traps::TrapSignal::Signal(sys::signal::Signal::SIGUSR1);
+        assert!(job.kill(usr1).is_err());
+
+        let mut job = spawn_pending();
+        job.kill(traps::TrapSignal::Signal(sys::signal::Signal::SIGKILL))?;
+        let result = loop {
+            if let Some(result) = job.poll_done()? {
+                break result;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert!(result.is_err());
+        assert!(matches!(job.state, JobState::Done));
+
+        Ok(())
+    }
 }
@@ -6,6 +6,7 @@ pub mod arithmetic;
 mod braceexpansion;
 pub mod builtins;
 pub mod commands;
+pub mod commandtrace;
 pub mod completion;
 pub mod env;
 pub mod error;
@@ -41,7 +42,7 @@ mod wellknownvars;
 
 pub use commands::{CommandArg, ExecutionContext};
 pub use error::{BuiltinError, Error, ErrorKind};
-pub use interp::{ExecutionParameters, ProcessGroupPolicy};
+pub use interp::{ExecutionParameters, ProcessGroupPolicy, ResourceLimits};
 pub use results::{ExecutionControlFlow, ExecutionExitCode, ExecutionResult, ExecutionSpawnResult};
 pub use shell::{CreateOptions, Shell, ShellBuilder, ShellBuilderState, ShellFd};
 pub use variables::{ShellValue, ShellVariable};
@@ -177,6 +177,9 @@ pub struct RuntimeOptions {
     // Options set by the shell.
     /// Whether or not the shell is interactive.
     pub interactive: bool,
+    /// Whether the shell controls the process's terminal, moving itself and
+    /// its jobs to the terminal's foreground.
+    pub manage_terminal: bool,
     /// Whether or not the shell is reading commands from standard input.
     pub read_commands_from_stdin: bool,
     /// Whether or not the shell is in maximal `sh` compatibility mode.    
@@ -195,6 +198,7 @@ impl RuntimeOptions {
         // There's a set of options enabled by default for all shells.
         let mut options = Self {
             interactive: create_options.interactive,
+            manage_terminal: create_options.interactive,
             disallow_overwriting_regular_files_via_output_redirection: create_options
                 .disallow_overwriting_regular_files_via_output_redirection,
             do_not_execute_commands: create_options.do_not_execute_commands,
@@ -2,7 +2,7 @@
 
 use tokio_util::sync::CancellationToken;
 
-use crate::{error, sys};
+use crate::{ExecutionResult, commandtrace::CommandTrace, error, jobs::JobProcesses, sys};
 
 /// Tracks a child process being awaited.
 pub struct ChildProcess {
@@ -12,12 +12,47 @@ pub struct ChildProcess {
     child: sys::process::Child,
     /// Tracks whether this process has already been reaped.
     reaped: bool,
+    /// Trace entry to complete once the process exits.
+    trace: Option<(CommandTrace, usize)>,
+    /// Background job process list to leave once reaped.
+    job_processes: Option<JobProcesses>,
 }
 
 impl ChildProcess {
     /// Wraps a child process and its future.
     pub fn new(pid: Option<sys::process::ProcessId>, child: sys::process::Child) -> Self {
-        Self { pid, child, reaped: false }
+        Self {
+            pid,
+            child,
+            reaped: false,
+            trace: None,
+            job_processes: None,
+        }
+    }
+
+    /// Arranges for the given trace entry to be completed when the process exits.
+    pub(crate) fn set_trace(&mut self, trace: CommandTrace, index: usize) {
+        self.trace = Some((trace, index));
+    }
+
+    /// Arranges for the process to be removed from the job's process list once reaped.
+    pub(crate) fn set_job_processes(&mut self, job_processes: JobProcesses) {
+        self.job_processes = Some(job_processes);
+    }
+
+    /// Records that the process has been reaped.
+    fn mark_reaped(&mut self) {
+        self.reaped = true;
+        if let (Some(job_processes), Some(pid)) = (&self.job_processes, self.pid) {
+            job_processes.forget(pid);
+        }
+    }
+
+    fn finish_trace(&self, output: &std::process::Output) {
+        if let Some((trace, index)) = &self.trace {
+            let result = ExecutionResult::from(output.clone());
+            trace.finish(*index, Some(result.exit_code.into()));
+        }
     }
 
     /// Returns the process's ID.
@@ -72,14 +107,19 @@ impl ChildProcess {
             return match status {
                 Some(status) => {
                     let status = status?;
-                    self.reaped = true;
-                    Ok(ProcessWaitResult::Completed(output_from_status(status)))
+                    self.mark_reaped();
+                    let output = output_from_status(status);
+                    self.finish_trace(&output);
+                    Ok(ProcessWaitResult::Completed(output))
                 }
                 None => {
                     if self.child.kill().await.is_ok() {
-                        self.reaped = true;
+                        self.mark_reaped();
                     } else if let Ok(Some(_)) = self.child.try_wait() {
-                        self.reaped = true;
+                        self.mark_reaped();
+                    }
+                    if let Some((trace, index)) = &self.trace {
+                        trace.finish(*index, None);
                     }
                     Ok(ProcessWaitResult::Cancelled)
                 }
@@ -94,20 +134,22 @@ impl ChildProcess {
         }
 
         if let Ok(Some(_)) = self.child.try_wait() {
-            self.reaped = true;
+            self.mark_reaped();
             return;
         }
         let _ = self.child.start_kill();
         if let Ok(Some(_)) = self.child.try_wait() {
-            self.reaped = true;
+            self.mark_reaped();
     }
     }
 
     pub(crate) fn poll(&mut self) -> Option<Result<std::process::Output, error::Error>> {
         match self.child.try_wait() {
             Ok(Some(status)) => {
-                self.reaped = true;
-                Some(Ok(output_from_status(status)))
+                self.mark_reaped();
+                let output = output_from_status(status);
+                self.finish_trace(&output);
+                Some(Ok(output))
             }
             Ok(None) => None,
             Err(err) => Some(Err(err.into())),
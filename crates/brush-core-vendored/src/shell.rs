@@ -29,6 +29,9 @@ pub type KeyBindingsHelper = Arc<Mutex<dyn interfaces::KeyBindings>>;
 /// Type for storing an error formatter.
 pub type ErrorFormatterHelper = Arc<Mutex<dyn error::ErrorFormatter>>;
 
+/// Type for storing a command policy.
+pub type CommandPolicyHelper = Arc<dyn interfaces::CommandPolicy>;
+
 /// Type alias for shell file descriptors.
 pub type ShellFd = i32;
 
@@ -114,6 +117,9 @@ pub struct Shell {
 
     /// Error formatter for customizing error display.
     error_formatter: ErrorFormatterHelper,
+
+    /// Policy consulted before executing simple commands.
+    command_policy: Option<CommandPolicyHelper>,
 }
 
 impl Clone for Shell {
@@ -145,6 +151,7 @@ impl Clone for Shell {
             key_bindings: self.key_bindings.clone(),
             history: self.history.clone(),
             error_formatter: self.error_formatter.clone(),
+            command_policy: self.command_policy.clone(),
             depth: self.depth + 1,
         }
     }
@@ -330,6 +337,8 @@ pub struct CreateOptions {
     pub key_bindings: Option<KeyBindingsHelper>,
     /// Error formatter helper for the shell to use.
     pub error_formatter: Option<ErrorFormatterHelper>,
+    /// Policy consulted before executing simple commands.
+    pub command_policy: Option<CommandPolicyHelper>,
     /// Brush implementation version.
     pub shell_version: Option<String>,
 }
@@ -377,6 +386,7 @@ impl Shell {
             error_formatter: options
                 .error_formatter
                 .unwrap_or_else(|| Arc::new(Mutex::new(error::DefaultErrorFormatter::new()))),
+            command_policy: options.command_policy,
             depth: 0,
         };
 
@@ -457,6 +467,16 @@ impl Shell {
         &self.key_bindings
     }
 
+    /// Returns the command policy for the shell, if any.
+    pub const fn command_policy(&self) -> Option<&CommandPolicyHelper> {
+        self.command_policy.as_ref()
+    }
+
+    /// Sets or clears the command policy for the shell.
+    pub fn set_command_policy(&mut self, policy: Option<CommandPolicyHelper>) {
+        self.command_policy = policy;
+    }
+
     /// Returns the registered builtins for the shell.
     pub const fn builtins(&self) -> &HashMap<String, builtins::Registration> {
         &self.builtins
@@ -1173,8 +1193,13 @@ impl Shell {
         Ok(())
     }
 
-    /// Adds a command to history.
+    /// Adds a command to history. History is started on first use when the
+    /// `history` option was enabled after the shell was created.
     pub fn add_to_history(&mut self, command: &str) -> Result<(), error::Error> {
+        if self.history.is_none() && self.options.enable_command_history {
+            self.history = Some(history::History::default());
+        }
+
         if let Some(history) = &mut self.history {
             // Trim.
             let command = command.trim();
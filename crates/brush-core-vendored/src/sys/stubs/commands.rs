@@ -2,7 +2,7 @@
 
 use std::ffi::OsStr;
 
-use crate::{ShellFd, error, openfiles};
+use crate::{ShellFd, error, interp::ResourceLimits, openfiles};
 
 /// Extension trait for Unix-like command extensions.
 pub trait CommandExt {
@@ -87,3 +87,20 @@ impl CommandFgControlExt for std::process::Command {
         // NOTE: This is a no-op.
     }
 }
+
+/// Extension trait for applying resource limits to commands.
+pub trait CommandResourceLimitsExt {
+    /// Arranges for the given limits to be installed in the child process
+    /// before it executes.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The limits to apply.
+    fn apply_resource_limits(&mut self, limits: ResourceLimits);
+}
+
+impl CommandResourceLimitsExt for std::process::Command {
+    fn apply_resource_limits(&mut self, _limits: ResourceLimits) {
+        // NOTE: Resource limits are not supported on this platform.
+    }
+}
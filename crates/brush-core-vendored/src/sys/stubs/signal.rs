@@ -102,6 +102,17 @@ pub fn kill_process(
     Err(error::ErrorKind::NotSupportedOnThisPlatform("killing process").into())
 }
 
+/// Sends a signal to every process in a process group.
+///
+/// Process groups are not tracked on this platform; only the group leader
+/// is signaled.
+pub fn kill_process_group(
+    pgid: sys::process::ProcessId,
+    signal: traps::TrapSignal,
+) -> Result<(), error::Error> {
+    kill_process(pgid, signal)
+}
+
 pub(crate) fn lead_new_process_group() -> Result<(), error::Error> {
     Ok(())
 }
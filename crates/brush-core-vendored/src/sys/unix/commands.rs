@@ -7,6 +7,7 @@ use command_fds::{CommandFdExt, FdMapping};
 
 use crate::ShellFd;
 use crate::error;
+use crate::interp::ResourceLimits;
 use crate::openfiles;
 
 /// Extension trait for injecting file descriptors into commands.
@@ -58,6 +59,46 @@ impl CommandFgControlExt for std::process::Command {
     }
 }
 
+/// Extension trait for applying resource limits to commands.
+pub trait CommandResourceLimitsExt {
+    /// Arranges for the given limits to be installed in the child process
+    /// before it executes.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The limits to apply.
+    fn apply_resource_limits(&mut self, limits: ResourceLimits);
+}
+
+impl CommandResourceLimitsExt for std::process::Command {
+    fn apply_resource_limits(&mut self, limits: ResourceLimits) {
+        // SAFETY:
+        // The closure runs in the forked child before exec and only issues
+        // getrlimit/setrlimit syscalls (see `Resource::set_soft`), which are
+        // async-signal-safe.
+        unsafe {
+            self.pre_exec(move || apply_resource_limits_before_exec(limits));
+        }
+    }
+}
+
+fn apply_resource_limits_before_exec(limits: ResourceLimits) -> Result<(), std::io::Error> {
+    use crate::sys::resource::{Resource, Virtual};
+
+    let requested = [
+        (Resource::Phy(rlimit::Resource::CPU), limits.cpu_seconds),
+        (Resource::Virt(Virtual::VMem), limits.address_space_bytes),
+        (Resource::Phy(rlimit::Resource::NOFILE), limits.open_files),
+    ];
+    for (resource, value) in requested {
+        if let Some(value) = value {
+            resource.set_soft(value)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn setup_process_before_exec() -> Result<(), std::io::Error> {
     use crate::sys;
 
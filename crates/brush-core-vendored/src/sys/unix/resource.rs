@@ -1,5 +1,7 @@
 //! Resource utilities
 
+use std::io;
+
 use crate::error;
 
 /// Returns the user and system CPU time used by the current process;
@@ -29,3 +31,99 @@ const fn convert_rusage_time(time: nix::sys::time::TimeVal) -> std::time::Durati
     #[allow(clippy::cast_possible_truncation)]
     std::time::Duration::new(time.tv_sec() as u64, time.tv_usec() as u32 * 1000)
 }
+
+/// A virtual resource limit: one not set directly through `setrlimit`.
+#[derive(Clone, Copy, Debug)]
+pub enum Virtual {
+    /// Pipe buffer size; read-only.
+    Pipe,
+    /// Virtual memory: `RLIMIT_AS`, falling back to `RLIMIT_VMEM` where the
+    /// former is unavailable.
+    VMem,
+}
+
+impl Virtual {
+    fn get(self) -> io::Result<(u64, u64)> {
+        match self {
+            Self::Pipe => {
+                let lim = nix::unistd::PathconfVar::PIPE_BUF as u64 * 512;
+                Ok((lim, lim))
+            }
+            Self::VMem => rlimit::Resource::AS
+                .get()
+                .or_else(|_| rlimit::Resource::VMEM.get()),
+        }
+    }
+
+    fn set(self, soft: u64, hard: u64) -> io::Result<()> {
+        match self {
+            Self::Pipe => Err(io::Error::from(io::ErrorKind::Unsupported)),
+            Self::VMem => rlimit::Resource::AS
+                .set(soft, hard)
+                .or_else(|_| rlimit::Resource::VMEM.set(soft, hard)),
+        }
+    }
+
+    const fn is_supported(self) -> bool {
+        match self {
+            Self::Pipe => true,
+            Self::VMem => {
+                rlimit::Resource::AS.is_supported() || rlimit::Resource::VMEM.is_supported()
+            }
+        }
+    }
+}
+
+/// A process resource limit, as managed by `ulimit`.
+#[derive(Clone, Copy, Debug)]
+pub enum Resource {
+    /// A limit backed directly by `getrlimit`/`setrlimit`.
+    Phy(rlimit::Resource),
+    /// A limit with platform-specific handling.
+    Virt(Virtual),
+}
+
+impl Resource {
+    /// Returns the current soft and hard limits, in that order.
+    pub fn get(self) -> io::Result<(u64, u64)> {
+        match self {
+            Self::Phy(res) => res.get(),
+            Self::Virt(res) => res.get(),
+        }
+    }
+
+    /// Sets the soft and hard limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `soft` - The new soft limit.
+    /// * `hard` - The new hard limit.
+    pub fn set(self, soft: u64, hard: u64) -> io::Result<()> {
+        match self {
+            Self::Phy(res) => res.set(soft, hard),
+            Self::Virt(res) => res.set(soft, hard),
+        }
+    }
+
+    /// Sets the soft limit, clamped to the current hard limit, leaving the
+    /// hard limit unchanged.
+    ///
+    /// Only issues `getrlimit`/`setrlimit` syscalls, so it is safe to call
+    /// between `fork` and `exec`.
+    ///
+    /// # Arguments
+    ///
+    /// * `soft` - The new soft limit.
+    pub fn set_soft(self, soft: u64) -> io::Result<()> {
+        let (_, hard) = self.get()?;
+        self.set(soft.min(hard), hard)
+    }
+
+    /// Returns whether the limit exists on this platform.
+    pub const fn is_supported(self) -> bool {
+        match self {
+            Self::Phy(res) => res.is_supported(),
+            Self::Virt(res) => res.is_supported(),
+        }
+    }
+}
@@ -35,6 +35,25 @@ pub fn kill_process(
     Ok(())
 }
 
+/// Sends a signal to every process in a process group.
+///
+/// # Arguments
+/// * `pgid` - The process group ID to send the signal to
+/// * `signal` - The signal to send (must be a real signal, not a trap signal)
+pub fn kill_process_group(
+    pgid: sys::process::ProcessId,
+    signal: traps::TrapSignal,
+) -> Result<(), error::Error> {
+    let traps::TrapSignal::Signal(translated_signal) = signal else {
+        return Err(error::ErrorKind::InvalidSignal(signal.to_string()).into());
+    };
+
+    nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pgid), translated_signal)
+        .map_err(|_errno| error::ErrorKind::FailedToSendSignal)?;
+
+    Ok(())
+}
+
 pub(crate) fn lead_new_process_group() -> Result<(), error::Error> {
     nix::unistd::setpgid(nix::unistd::Pid::from_raw(0), nix::unistd::Pid::from_raw(0))?;
     Ok(())
@@ -3,7 +3,7 @@
 use std::ffi::OsStr;
 use std::os::windows::process::CommandExt as WindowsCommandExt;
 
-use crate::{ShellFd, error, openfiles};
+use crate::{ShellFd, error, interp::ResourceLimits, openfiles};
 use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
 
 /// Extension trait for Windows command extensions.
@@ -96,3 +96,20 @@ impl CommandFgControlExt for std::process::Command {
 		self.creation_flags(CREATE_NEW_PROCESS_GROUP);
 	}
 }
+
+/// Extension trait for applying resource limits to commands.
+pub trait CommandResourceLimitsExt {
+	/// Arranges for the given limits to be installed in the child process
+	/// before it executes.
+	///
+	/// # Arguments
+	///
+	/// * `limits` - The limits to apply.
+	fn apply_resource_limits(&mut self, limits: ResourceLimits);
+}
+
+impl CommandResourceLimitsExt for std::process::Command {
+	fn apply_resource_limits(&mut self, _limits: ResourceLimits) {
+		// NOTE: Resource limits are not supported on this platform.
+	}
+}
@@ -1,14 +1,15 @@
 //! Fuzzy file path discovery for autocomplete and @-mention resolution.
 //!
-//! Searches for files and directories whose paths match a query string via
-//! subsequence scoring. Uses the shared [`fs_cache`] for directory scanning.
+//! Searches for files and directories whose paths match a query string,
+//! ranked by the [`fuzzy`](crate::fuzzy) scorer. Uses the shared [`fs_cache`]
+//! for directory scanning.
 
 use std::path::Path;
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
-use crate::{fs_cache, task};
+use crate::{file_types::TypeFilter, frecency::Frecency, fs_cache, fuzzy::Scorer, task};
 
 // ═══════════════════════════════════════════════════════════════════════════
 // Public types
@@ -17,7 +18,8 @@ use crate::{fs_cache, task};
 /// Options for fuzzy file path search.
 #[napi(object)]
 pub struct FuzzyFindOptions<'env> {
-	/// Fuzzy query to match against file paths (case-insensitive).
+	/// Fuzzy query to match against file paths (case-insensitive);
+	/// whitespace-separated words must all match.
 	pub query:       String,
 	/// Directory to search.
 	pub path:        String,
@@ -25,8 +27,21 @@ pub struct FuzzyFindOptions<'env> {
 	pub hidden:      Option<bool>,
 	/// Respect .gitignore (default: true).
 	pub gitignore:   Option<bool>,
+	/// Only match files of this ripgrep file type (e.g., "rust", "ts");
+	/// directories are kept.
+	#[napi(js_name = "type")]
+	pub type_filter: Option<String>,
+	/// Extra type definitions, as `name:glob` or `name:include:a,b`.
+	#[napi(js_name = "typeAdd")]
+	pub type_add:    Option<Vec<String>>,
+	/// Exclude files of these types.
+	#[napi(js_name = "typeNot")]
+	pub type_not:    Option<Vec<String>>,
 	/// Enable shared filesystem scan cache (default: false).
 	pub cache:       Option<bool>,
+	/// Boost entries recorded with `recordFileAccess()` and, slightly,
+	/// recently modified files (default: false).
+	pub frecency:    Option<bool>,
 	/// Maximum number of matches to return (default: 100).
 	#[napi(js_name = "maxResults")]
 	pub max_results: Option<u32>,
@@ -47,6 +62,9 @@ pub struct FuzzyFindMatch {
 	pub is_directory: bool,
 	/// Match quality score (higher is better).
 	pub score:        u32,
+	/// Matched character positions in `path`, as sorted UTF-16 offsets (for
+	/// highlighting).
+	pub positions:    Vec<u32>,
 }
 
 /// Result of fuzzy file path search.
@@ -59,94 +77,6 @@ pub struct FuzzyFindResult {
 	pub total_matches: u32,
 }
 
-// ═══════════════════════════════════════════════════════════════════════════
-// Scoring
-// ═══════════════════════════════════════════════════════════════════════════
-
-/// Strips separators, whitespace, and punctuation for normalized fuzzy
-/// comparison.
-fn normalize_fuzzy_text(value: &str) -> String {
-	value
-		.chars()
-		.filter(|ch| !ch.is_whitespace() && !matches!(ch, '/' | '\\' | '.' | '_' | '-'))
-		.flat_map(|ch| ch.to_lowercase())
-		.collect()
-}
-
-/// Scores a query as a subsequence of `target`. Returns 0 if not a subsequence.
-fn fuzzy_subsequence_score(query: &str, target: &str) -> u32 {
-	let query_chars: Vec<char> = query.chars().collect();
-	if query_chars.is_empty() {
-		return 1;
-	}
-	let mut query_index = 0usize;
-	let mut gaps = 0u32;
-	let mut last_match_index: Option<usize> = None;
-	for (target_index, target_ch) in target.chars().enumerate() {
-		if query_index >= query_chars.len() {
-			break;
-		}
-		if query_chars[query_index] == target_ch {
-			if let Some(last_index) = last_match_index
-				&& target_index > last_index + 1
-			{
-				gaps = gaps.saturating_add(1);
-			}
-			last_match_index = Some(target_index);
-			query_index += 1;
-		}
-	}
-	if query_index != query_chars.len() {
-		return 0;
-	}
-	let gap_penalty = gaps.saturating_mul(5);
-	40u32.saturating_sub(gap_penalty).max(1)
-}
-
-/// Composite path scoring: exact > starts-with > contains > fuzzy subsequence.
-fn score_fuzzy_path(
-	path: &str,
-	is_directory: bool,
-	query_lower: &str,
-	normalized_query: &str,
-) -> u32 {
-	let lower_path = path.to_lowercase();
-	let normalized_path = normalize_fuzzy_text(path);
-	let file_name_source = path.trim_end_matches('/');
-	let file_name = Path::new(file_name_source)
-		.file_name()
-		.and_then(|name| name.to_str())
-		.unwrap_or(file_name_source);
-	let lower_file_name = file_name.to_lowercase();
-	let normalized_file_name = normalize_fuzzy_text(file_name);
-
-	let mut score = if query_lower.is_empty() {
-		1
-	} else if lower_file_name == query_lower {
-		120
-	} else if lower_file_name.starts_with(query_lower) {
-		100
-	} else if lower_file_name.contains(query_lower) {
-		80
-	} else if lower_path.contains(query_lower) {
-		60
-	} else {
-		let file_name_fuzzy = fuzzy_subsequence_score(normalized_query, &normalized_file_name);
-		if file_name_fuzzy > 0 {
-			50 + file_name_fuzzy
-		} else {
-			let path_fuzzy = fuzzy_subsequence_score(normalized_query, &normalized_path);
-			if path_fuzzy > 0 { 30 + path_fuzzy } else { 0 }
-		}
-	};
-
-	if is_directory && score > 0 {
-		score += 10;
-	}
-
-	score
-}
-
 // ═══════════════════════════════════════════════════════════════════════════
 // Execution
 // ═══════════════════════════════════════════════════════════════════════════
@@ -157,8 +87,12 @@ struct FuzzyFindConfig {
 	path:        String,
 	hidden:      Option<bool>,
 	gitignore:   Option<bool>,
+	type_filter: Option<String>,
+	type_add:    Option<Vec<String>>,
+	type_not:    Option<Vec<String>>,
 	max_results: Option<u32>,
 	cache:       Option<bool>,
+	frecency:    Option<bool>,
 }
 
 fn clamp_u32(value: u64) -> u32 {
@@ -174,42 +108,63 @@ fn fuzzy_find_sync(config: FuzzyFindConfig, ct: task::CancelToken) -> Result<Fuz
 		return Ok(FuzzyFindResult { matches: Vec::new(), total_matches: 0 });
 	}
 
-	let query_lower = config.query.trim().to_lowercase();
-	let normalized_query = normalize_fuzzy_text(&query_lower);
-	if !query_lower.is_empty() && normalized_query.is_empty() {
-		return Ok(FuzzyFindResult { matches: Vec::new(), total_matches: 0 });
-	}
+	let mut scorer = Scorer::new(&config.query, true);
+
+	let types = TypeFilter::new(
+		config.type_filter.as_deref(),
+		config.type_add.as_deref(),
+		config.type_not.as_deref(),
+	)?;
+	let types = types.as_ref();
+
+	let frecency = config
+		.frecency
+		.unwrap_or(false)
+		.then(|| Frecency::load(&root));
+	let frecency = frecency.as_ref();
 
 	let use_cache = config.cache.unwrap_or(false);
 	let mut scored = if use_cache {
 		let scan = fs_cache::get_or_scan(&root, include_hidden, respect_gitignore, &ct)?;
-		let mut scored = score_entries(&scan.entries, &query_lower, &normalized_query, &ct)?;
+		let mut scored = score_entries(&scan.entries, &mut scorer, types, frecency, &ct)?;
 		// Empty-result recheck: if the query was non-trivial but produced zero matches
 		// from a cached scan that's old enough, force one rescan before giving up.
 		if scored.is_empty()
-			&& !query_lower.is_empty()
+			&& !scorer.is_empty()
 			&& scan.cache_age_ms >= fs_cache::empty_recheck_ms()
 		{
 			let fresh = fs_cache::force_rescan(&root, include_hidden, respect_gitignore, true, &ct)?;
-			scored = score_entries(&fresh, &query_lower, &normalized_query, &ct)?;
+			scored = score_entries(&fresh, &mut scorer, types, frecency, &ct)?;
 		}
 		scored
 	} else {
 		let fresh = fs_cache::force_rescan(&root, include_hidden, respect_gitignore, false, &ct)?;
-		score_entries(&fresh, &query_lower, &normalized_query, &ct)?
+		score_entries(&fresh, &mut scorer, types, frecency, &ct)?
 	};
 
-	scored.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+	// Among equal scores, shorter paths first.
+	scored.sort_by(|a, b| {
+		b.score
+			.cmp(&a.score)
+			.then_with(|| a.path.len().cmp(&b.path.len()))
+			.then_with(|| a.path.cmp(&b.path))
+	});
 	let total_matches = clamp_u32(scored.len() as u64);
-	let matches = scored.into_iter().take(max_results).collect();
-	Ok(FuzzyFindResult { matches, total_matches })
+	scored.truncate(max_results);
+	if !scorer.is_empty() {
+		for entry in &mut scored {
+			entry.positions = scorer.positions(&entry.path);
+		}
+	}
+	Ok(FuzzyFindResult { matches: scored, total_matches })
 }
 
-/// Score all entries against the query, returning only those with score > 0.
+/// Score all entries against the query, returning only those that match.
 fn score_entries(
 	entries: &[fs_cache::GlobMatch],
-	query_lower: &str,
-	normalized_query: &str,
+	scorer: &mut Scorer,
+	types: Option<&TypeFilter>,
+	frecency: Option<&Frecency>,
 	ct: &task::CancelToken,
 ) -> Result<Vec<FuzzyFindMatch>> {
 	let mut scored = Vec::new();
@@ -220,17 +175,22 @@ fn score_entries(
 		}
 
 		let is_directory = entry.file_type == fs_cache::FileType::Dir;
+		if types.is_some_and(|types| !types.is_match(Path::new(&entry.path), is_directory)) {
+			continue;
+		}
 		let path = if is_directory {
 			format!("{}/", entry.path)
 		} else {
 			entry.path.clone()
 		};
-		let score = score_fuzzy_path(&path, is_directory, query_lower, normalized_query);
-		if score == 0 {
+		let Some(mut score) = scorer.score(&path) else {
 			continue;
+		};
+		if let Some(frecency) = frecency {
+			score += frecency.boost(&entry.path, entry.mtime);
 		}
 
-		scored.push(FuzzyFindMatch { path, is_directory, score });
+		scored.push(FuzzyFindMatch { path, is_directory, score, positions: Vec::new() });
 	}
 	Ok(scored)
 }
@@ -244,9 +204,32 @@ fn score_entries(
 /// Matching file and directory entries sorted by match quality.
 #[napi(js_name = "fuzzyFind")]
 pub fn fuzzy_find(options: FuzzyFindOptions<'_>) -> task::Async<FuzzyFindResult> {
-	let FuzzyFindOptions { query, path, hidden, gitignore, cache, max_results, timeout_ms, signal } =
-		options;
+	let FuzzyFindOptions {
+		query,
+		path,
+		hidden,
+		gitignore,
+		type_filter,
+		type_add,
+		type_not,
+		cache,
+		frecency,
+		max_results,
+		timeout_ms,
+		signal,
+	} = options;
 	let ct = task::CancelToken::new(timeout_ms, signal);
-	let config = FuzzyFindConfig { query, path, hidden, gitignore, max_results, cache };
+	let config = FuzzyFindConfig {
+		query,
+		path,
+		hidden,
+		gitignore,
+		type_filter,
+		type_add,
+		type_not,
+		max_results,
+		cache,
+		frecency,
+	};
 	task::blocking("fuzzy_find", ct, move |ct| fuzzy_find_sync(config, ct))
 }
@@ -0,0 +1,128 @@
+//! File type filters shared by `grep()`, `glob()` and `fuzzyFind()`.
+//!
+//! Types come from ripgrep's database (`ignore::types`), extended with
+//! `typeAdd` definitions (`name:glob` or `name:include:other,...`, as in
+//! `rg --type-add`). `type` selects a type and `typeNot` excludes types.
+//! Like ripgrep, types match file names only and never filter directories.
+//!
+//! For compatibility with the previous hard-coded list, a few extra aliases
+//! are accepted, and an unknown `type` name is matched as a file extension.
+//! Unknown `typeNot` names are rejected, since excluding an extension that
+//! happens to be misspelled would silently exclude nothing.
+
+use std::path::Path;
+
+use ignore::types::{Types, TypesBuilder};
+use napi::{Error, Result};
+
+/// Names from the previous type list that ripgrep spells differently.
+const ALIASES: &[(&str, &str)] = &[
+	("bash", "sh"),
+	("cxx", "cpp"),
+	("dockerfile", "docker"),
+	("javascript", "js"),
+	("kt", "kotlin"),
+	("makefile", "make"),
+	("rb", "ruby"),
+	("rs", "rust"),
+	("scss", "sass"),
+	("yml", "yaml"),
+];
+
+/// A compiled type selection.
+pub struct TypeFilter {
+	types: Types,
+}
+
+impl TypeFilter {
+	/// Build a filter, or `None` when no type is selected or excluded.
+	pub fn new(
+		select: Option<&str>,
+		add: Option<&[String]>,
+		negate: Option<&[String]>,
+	) -> Result<Option<Self>> {
+		let select = select.map(normalize_name).filter(|name| !name.is_empty());
+		let negate: Vec<String> = negate
+			.unwrap_or_default()
+			.iter()
+			.map(|name| normalize_name(name))
+			.filter(|name| !name.is_empty())
+			.collect();
+		if select.is_none() && negate.is_empty() {
+			return Ok(None);
+		}
+
+		let mut builder = TypesBuilder::new();
+		builder.add_defaults();
+		for def in add.unwrap_or_default() {
+			builder
+				.add_def(&normalize_def(def))
+				.map_err(|err| Error::from_reason(format!("Invalid type definition {def:?}: {err}")))?;
+		}
+		let known: Vec<String> = builder
+			.definitions()
+			.into_iter()
+			.map(|def| def.name().to_string())
+			.collect();
+		// Known type names and aliases; other names are only accepted by
+		// `type`, as an extension.
+		let resolve = |name: &str| -> Option<String> {
+			if name == "all" || known.iter().any(|known| known == name) {
+				return Some(name.to_string());
+			}
+			ALIASES
+				.iter()
+				.find(|(from, _)| *from == name)
+				.map(|&(_, alias)| alias.to_string())
+		};
+		let unknown = |name: &str| Error::from_reason(format!("Unknown file type: {name}"));
+		let negate = negate
+			.iter()
+			.map(|name| resolve(name).ok_or_else(|| unknown(name)))
+			.collect::<Result<Vec<_>>>()?;
+		if let Some(name) = select {
+			let name = if let Some(known) = resolve(&name) {
+				known
+			} else {
+				builder
+					.add(&name, &format!("*.{name}"))
+					.map_err(|_| unknown(&name))?;
+				name
+			};
+			builder.select(&name);
+		}
+		for name in negate {
+			builder.negate(&name);
+		}
+		let types = builder
+			.build()
+			.map_err(|err| Error::from_reason(format!("Invalid file type filter: {err}")))?;
+		Ok(Some(Self { types }))
+	}
+
+	/// Whether a file at `path` passes the filter. Directories always do.
+	pub fn is_match(&self, path: &Path, is_dir: bool) -> bool {
+		!self.types.matched(path, is_dir).is_ignore()
+	}
+}
+
+/// Trim, drop a leading `.`, and lowercase, so `.TS` selects `ts`.
+fn normalize_name(name: &str) -> String {
+	name.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Normalize the type names in a `typeAdd` definition (`name:glob` or
+/// `name:include:a,b`), leaving the glob as written.
+fn normalize_def(def: &str) -> String {
+	let Some((name, rest)) = def.split_once(':') else {
+		return def.to_string();
+	};
+	let name = normalize_name(name);
+	match rest.split_once(':') {
+		Some(("include", names)) => {
+			let names: Vec<String> = names.split(',').map(normalize_name).collect();
+			format!("{name}:include:{}", names.join(","))
+		},
+		_ => format!("{name}:{rest}"),
+	}
+}
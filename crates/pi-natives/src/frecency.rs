@@ -0,0 +1,365 @@
+//! Frecency (frequency + recency) signal for ranking `fuzzyFind()` and
+//! `glob()` results.
+//!
+//! `recordFileAccess(path)` bumps a path each time the agent or user reads,
+//! edits or mentions it. Each access is worth 1 and halves every
+//! [`ACCESS_HALF_LIFE`], so a file touched often this session outranks one
+//! touched once last week. A recent mtime adds a much smaller amount, so files
+//! regenerated by a build do not outrank files someone actually worked on.
+//!
+//! Scores are kept per project root: the nearest ancestor containing `.git`,
+//! else the working directory when the path is inside it, else the directory
+//! itself. Each root is loaded on first use and written to `FRECENCY_DIR`
+//! (default: `<data dir>/omp/frecency`) in the background after it changes.
+//!
+//! Accesses are queued and applied on a worker thread, since finding the
+//! project root touches the filesystem. Loading scores applies the queue
+//! first, so a search always sees the accesses recorded before it.
+
+use std::{
+	collections::HashMap,
+	fmt::Write as _,
+	fs,
+	io::{self, Write},
+	mem,
+	path::{Path, PathBuf},
+	sync::{Arc, LazyLock},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use dashmap::DashMap;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::fs_cache;
+
+/// Time for an access to lose half its weight.
+const ACCESS_HALF_LIFE: Duration = Duration::from_hours(72);
+/// Time for the mtime signal to lose half its weight.
+const MTIME_HALF_LIFE: Duration = Duration::from_hours(24);
+/// Weight of a file modified just now, relative to one access.
+const MTIME_WEIGHT: f64 = 0.25;
+/// Entries kept per root; the lowest scores are dropped first.
+const MAX_ENTRIES: usize = 1000;
+/// Entries decayed below this are dropped when saving.
+const MIN_SCORE: f64 = 0.01;
+
+const HEADER: &str = "omp-frecency 1";
+
+static STORES: LazyLock<DashMap<PathBuf, Arc<Store>>> = LazyLock::new(DashMap::new);
+
+/// Absolute paths passed to `recordFileAccess()` and not yet applied.
+static QUEUED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+/// Held while applying [`QUEUED`], so a load waits for accesses being applied.
+static APPLYING: Mutex<()> = Mutex::new(());
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Scoring
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy)]
+struct Entry {
+	/// Access score as of `last_ms`.
+	score:   f64,
+	/// Last access, in milliseconds since Unix epoch.
+	last_ms: f64,
+}
+
+impl Entry {
+	fn score_at(self, now_ms: f64) -> f64 {
+		self.score * decay(now_ms - self.last_ms, ACCESS_HALF_LIFE)
+	}
+}
+
+/// Weight left after `age_ms`: 1 now, 0.5 after `half_life`.
+fn decay(age_ms: f64, half_life: Duration) -> f64 {
+	0.5f64.powf(age_ms.max(0.0) / half_life.as_secs_f64() / 1000.0)
+}
+
+fn now_ms() -> f64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0.0, |since| since.as_secs_f64() * 1000.0)
+}
+
+/// Frecency of the entries under a search root.
+pub struct Frecency {
+	/// Current access scores, keyed relative to the search root.
+	scores: HashMap<String, f64>,
+	now_ms: f64,
+}
+
+impl Frecency {
+	/// Load the scores recorded under `search_root` (an absolute, canonical
+	/// directory).
+	pub fn load(search_root: &Path) -> Self {
+		apply_queued();
+		let root = project_root(search_root);
+		let store = store(&root);
+		let prefix = fs_cache::normalize_relative_path(&root, search_root);
+		let now_ms = now_ms();
+		let entries = store.entries.lock();
+		let scores = entries
+			.iter()
+			.filter_map(|(key, entry)| {
+				let relative = if prefix.is_empty() {
+					key.as_str()
+				} else {
+					key.strip_prefix(prefix.as_ref())?.strip_prefix('/')?
+				};
+				Some((relative.to_string(), entry.score_at(now_ms)))
+			})
+			.collect();
+		Self { scores, now_ms }
+	}
+
+	/// Combined score of an entry: its accesses plus a small boost for a
+	/// recent `mtime`. A trailing `/` on directories is ignored.
+	pub fn score(&self, relative: &str, mtime: Option<f64>) -> f64 {
+		let relative = relative.strip_suffix('/').unwrap_or(relative);
+		let accessed = self.scores.get(relative).copied().unwrap_or(0.0);
+		let modified =
+			mtime.map_or(0.0, |mtime| MTIME_WEIGHT * decay(self.now_ms - mtime, MTIME_HALF_LIFE));
+		accessed + modified
+	}
+
+	/// Score bonus for fuzzy ranking: 16 (about one matched character) per
+	/// doubling of the frecency, capped at 64.
+	pub fn boost(&self, relative: &str, mtime: Option<f64>) -> u32 {
+		let boost = 16.0 * self.score(relative, mtime).ln_1p() / std::f64::consts::LN_2;
+		boost.round().clamp(0.0, 64.0) as u32
+	}
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Storage
+// ═══════════════════════════════════════════════════════════════════════════
+
+struct Store {
+	/// Where the entries are persisted, if anywhere.
+	file:         Option<PathBuf>,
+	entries:      Mutex<HashMap<String, Entry>>,
+	/// A background save is queued.
+	save_pending: Mutex<bool>,
+	/// Serializes writes so an older snapshot never replaces a newer one.
+	write_lock:   Mutex<()>,
+}
+
+impl Store {
+	fn load(root: &Path) -> Self {
+		let file = store_file(root);
+		let entries = file
+			.as_ref()
+			.and_then(|path| fs::read_to_string(path).ok())
+			.map_or_else(HashMap::new, |text| decode(&text));
+		Self {
+			file,
+			entries: Mutex::new(entries),
+			save_pending: Mutex::new(false),
+			write_lock: Mutex::new(()),
+		}
+	}
+
+	fn record(self: &Arc<Self>, key: String) {
+		let now_ms = now_ms();
+		{
+			let mut entries = self.entries.lock();
+			let entry = entries
+				.entry(key)
+				.or_insert(Entry { score: 0.0, last_ms: now_ms });
+			*entry = Entry { score: entry.score_at(now_ms) + 1.0, last_ms: now_ms };
+		}
+
+		let mut pending = self.save_pending.lock();
+		if !*pending {
+			*pending = true;
+			drop(pending);
+			let store = Arc::clone(self);
+			napi::bindgen_prelude::spawn_blocking(move || {
+				// Persistence is best effort; scores stay in memory regardless.
+				let _ = store.save();
+			});
+		}
+	}
+
+	fn save(&self) -> io::Result<()> {
+		let _write = self.write_lock.lock();
+		*self.save_pending.lock() = false;
+		let text = {
+			let mut entries = self.entries.lock();
+			prune(&mut entries, now_ms());
+			encode(&entries)
+		};
+
+		let path = self
+			.file
+			.as_ref()
+			.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+		let dir = path
+			.parent()
+			.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+		fs::create_dir_all(dir)?;
+		let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+		let result = fs::File::create(&temp)
+			.and_then(|mut file| file.write_all(text.as_bytes()))
+			.and_then(|()| fs::rename(&temp, path));
+		if result.is_err() {
+			let _ = fs::remove_file(&temp);
+		}
+		result
+	}
+}
+
+/// Drop faded entries, and the weakest ones beyond [`MAX_ENTRIES`].
+fn prune(entries: &mut HashMap<String, Entry>, now_ms: f64) {
+	entries.retain(|_, entry| entry.score_at(now_ms) >= MIN_SCORE);
+	if entries.len() > MAX_ENTRIES {
+		let mut scores: Vec<f64> = entries
+			.values()
+			.map(|entry| entry.score_at(now_ms))
+			.collect();
+		let cutoff_index = scores.len() - MAX_ENTRIES;
+		let (_, &mut cutoff, _) = scores.select_nth_unstable_by(cutoff_index, f64::total_cmp);
+		entries.retain(|_, entry| entry.score_at(now_ms) >= cutoff);
+	}
+}
+
+/// One `score<TAB>last_ms<TAB>path` line per entry, after a header line.
+fn encode(entries: &HashMap<String, Entry>) -> String {
+	let mut text = format!("{HEADER}\n");
+	for (key, entry) in entries {
+		let _ = writeln!(text, "{}\t{}\t{key}", entry.score, entry.last_ms);
+	}
+	text
+}
+
+fn decode(text: &str) -> HashMap<String, Entry> {
+	let mut lines = text.lines();
+	if lines.next() != Some(HEADER) {
+		return HashMap::new();
+	}
+	lines
+		.filter_map(|line| {
+			let mut fields = line.splitn(3, '\t');
+			let score = fields.next()?.parse().ok()?;
+			let last_ms = fields.next()?.parse().ok()?;
+			let key = fields.next()?;
+			Some((key.to_string(), Entry { score, last_ms }))
+		})
+		.collect()
+}
+
+fn store_dir() -> Option<PathBuf> {
+	if let Some(dir) = std::env::var_os("FRECENCY_DIR") {
+		return Some(PathBuf::from(dir));
+	}
+	let data = if cfg!(windows) {
+		std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+	} else {
+		std::env::var_os("XDG_DATA_HOME")
+			.map(PathBuf::from)
+			.or_else(|| {
+				std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+			})
+	}?;
+	Some(data.join("omp").join("frecency"))
+}
+
+/// Store file for `root`, named by a stable (FNV-1a) hash of the path.
+fn store_file(root: &Path) -> Option<PathBuf> {
+	let hash = root
+		.as_os_str()
+		.as_encoded_bytes()
+		.iter()
+		.fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+			(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+		});
+	Some(store_dir()?.join(format!("{hash:016x}.tsv")))
+}
+
+fn store(root: &Path) -> Arc<Store> {
+	STORES
+		.entry(root.to_path_buf())
+		.or_insert_with(|| Arc::new(Store::load(root)))
+		.clone()
+}
+
+/// Project root for `dir`: the nearest ancestor holding `.git`, else the
+/// working directory when `dir` is inside it, else `dir` itself.
+fn project_root(dir: &Path) -> PathBuf {
+	if let Some(repo) = dir
+		.ancestors()
+		.find(|ancestor| ancestor.join(".git").exists())
+	{
+		return repo.to_path_buf();
+	}
+	std::env::current_dir()
+		.ok()
+		.map(|cwd| fs::canonicalize(&cwd).unwrap_or(cwd))
+		.filter(|cwd| dir.starts_with(cwd))
+		.unwrap_or_else(|| dir.to_path_buf())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// N-API
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Record that a file or directory was read, edited or mentioned, so
+/// `fuzzyFind()` and `glob()` with `frecency` rank it higher.
+///
+/// Returns at once; the access is applied on a worker thread, before any
+/// later search loads scores.
+///
+/// # Arguments
+/// - `path`: File or directory, absolute or relative to the working directory.
+///   It need not exist any more.
+///
+/// # Errors
+/// Returns an error when a relative path cannot be resolved.
+#[napi(js_name = "recordFileAccess")]
+pub fn record_file_access(path: String) -> Result<()> {
+	let path = PathBuf::from(path);
+	let path = if path.is_absolute() {
+		path
+	} else {
+		std::env::current_dir()
+			.map_err(|err| Error::from_reason(format!("Failed to resolve cwd: {err}")))?
+			.join(path)
+	};
+	let mut queued = QUEUED.lock();
+	queued.push(path);
+	if queued.len() == 1 {
+		drop(queued);
+		napi::bindgen_prelude::spawn_blocking(apply_queued);
+	}
+	Ok(())
+}
+
+/// Apply the accesses queued by `recordFileAccess()`.
+fn apply_queued() {
+	let _applying = APPLYING.lock();
+	let paths = mem::take(&mut *QUEUED.lock());
+	for path in paths {
+		record_path(path);
+	}
+}
+
+/// Record an access to the absolute `path` in its project's store.
+fn record_path(path: PathBuf) {
+	// Match the canonical roots searches use.
+	let path = fs::canonicalize(&path).unwrap_or(path);
+	let Some(parent) = path.parent() else {
+		return;
+	};
+	let root = if path.is_dir() {
+		project_root(&path)
+	} else {
+		project_root(parent)
+	};
+	let key = fs_cache::normalize_relative_path(&root, &path);
+	if key.is_empty() || key.contains(['\n', '\t']) {
+		return;
+	}
+	store(&root).record(key.into_owned());
+}
@@ -1,7 +1,9 @@
 //! Shared filesystem scan cache for discovery tools (glob, fd).
 //!
-//! Provides a TTL-based cache of scanned directory entries, with:
-//! - Global policy (no per-call TTL tuning)
+//! Provides a cache of scanned directory entries, with:
+//! - A filesystem watcher per cached root that patches entries as files change
+//!   (see [`crate::fs_watch`])
+//! - A TTL for roots that are not watched (global policy, no per-call tuning)
 //! - Explicit invalidation for agent file mutations
 //! - Empty-result fast recheck to avoid stale negatives
 //!
@@ -9,11 +11,16 @@
 //! - `FS_SCAN_CACHE_TTL_MS`       – default `1000`
 //! - `FS_SCAN_EMPTY_RECHECK_MS`   – default `200`
 //! - `FS_SCAN_CACHE_MAX_ENTRIES`   – default `16`
+//! - `FS_SCAN_WATCH`               – default `1`; `0` disables watchers
 
 use std::{
 	borrow::Cow,
+	collections::{HashMap, HashSet},
 	path::{Path, PathBuf},
-	sync::LazyLock,
+	sync::{
+		Arc, LazyLock,
+		atomic::{AtomicU64, Ordering},
+	},
 	time::{Duration, Instant},
 };
 
@@ -22,7 +29,10 @@ use ignore::WalkBuilder;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
-use crate::task;
+use crate::{
+	fs_watch::{Change, ChangeKind, Watcher},
+	task,
+};
 
 // ═══════════════════════════════════════════════════════════════════════════
 // Public types (re-exported by glob for backward compatibility)
@@ -89,25 +99,47 @@ fn max_cache_entries() -> usize {
 	env_usize("FS_SCAN_CACHE_MAX_ENTRIES", DEFAULT_MAX_CACHE_ENTRIES)
 }
 
+fn watch_enabled() -> bool {
+	env_u64("FS_SCAN_WATCH", 1) != 0
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // Cache internals
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// A scan root with its visibility rules; also keys the cache.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-struct CacheKey {
-	root:           PathBuf,
-	include_hidden: bool,
-	use_gitignore:  bool,
+pub struct CacheKey {
+	pub root:           PathBuf,
+	pub include_hidden: bool,
+	pub use_gitignore:  bool,
 }
 
-#[derive(Clone)]
 struct CacheEntry {
 	created_at: Instant,
+	/// Last scan or watcher update.
+	updated_at: Instant,
 	entries:    Vec<GlobMatch>,
+	/// Keeps `entries` current while alive; dropping it stops watching.
+	watcher:    Option<Arc<Watcher>>,
+	/// Tags updates from this entry's watcher, so a replaced entry's watcher
+	/// cannot patch its successor.
+	watch_id:   u64,
+}
+
+impl CacheEntry {
+	fn is_watched(&self) -> bool {
+		self
+			.watcher
+			.as_ref()
+			.is_some_and(|watcher| watcher.is_alive())
+	}
 }
 
 static FS_CACHE: LazyLock<DashMap<CacheKey, CacheEntry>> = LazyLock::new(DashMap::new);
 
+static NEXT_WATCH_ID: AtomicU64 = AtomicU64::new(1);
+
 /// Result of a cache-aware scan, including the age of the cached data.
 pub struct ScanResult {
 	/// Scanned filesystem entries.
@@ -242,51 +274,132 @@ pub fn build_walker(root: &Path, include_hidden: bool, use_gitignore: bool) -> W
 ///
 /// Always stores `node_modules` entries; caller-side filtering handles
 /// exclusion.
-fn collect_entries(
-	root: &Path,
-	include_hidden: bool,
-	use_gitignore: bool,
-	ct: &task::CancelToken,
-) -> Result<Vec<GlobMatch>> {
-	let builder = build_walker(root, include_hidden, use_gitignore);
+fn collect_entries(key: &CacheKey, ct: &task::CancelToken) -> Result<Vec<GlobMatch>> {
 	let mut entries = Vec::new();
+	walk_entries(key, &key.root, None, ct, |entry| entries.push(entry))?;
+	Ok(entries)
+}
+
+/// Walks `start` (the root or a directory under it) with the scan rules,
+/// calling `visit` with each entry relative to `key.root`, parents first.
+///
+/// With `only`, just that child of `start` and its descendants are visited,
+/// so a single path can be rescanned under the same ignore rules.
+pub fn walk_entries(
+	key: &CacheKey,
+	start: &Path,
+	only: Option<&Path>,
+	ct: &task::CancelToken,
+	mut visit: impl FnMut(GlobMatch),
+) -> Result<()> {
+	let mut builder = build_walker(start, key.include_hidden, key.use_gitignore);
+	if let Some(only) = only {
+		let only = only.to_path_buf();
+		builder.filter_entry(move |entry| entry.depth() == 0 || entry.path().starts_with(&only));
+	}
 
 	for entry in builder.build() {
 		ct.heartbeat()?;
 
 		let Ok(entry) = entry else { continue };
+		if entry.depth() == 0 {
+			// Ignore the starting directory itself.
+			continue;
+		}
 		let path = entry.path();
 		if should_skip_path(path, true) {
 			// The cache always stores node_modules; caller-side filtering is applied later.
 			continue;
 		}
 
-		let relative = normalize_relative_path(root, path);
-		if relative.is_empty() {
-			// Ignore the synthetic root entry ("" relative path).
-			continue;
-		}
-
+		let relative = normalize_relative_path(&key.root, path);
 		let Some((file_type, mtime)) = classify_file_type(path) else {
 			continue;
 		};
 
-		entries.push(GlobMatch { path: relative.into_owned(), file_type, mtime });
+		visit(GlobMatch { path: relative.into_owned(), file_type, mtime });
 	}
 
-	Ok(entries)
+	Ok(())
+}
+
+/// Scan `key.root` into a cache entry, watching it for changes when enabled
+/// and supported.
+fn scan(key: &CacheKey, ct: &task::CancelToken) -> Result<CacheEntry> {
+	let now = Instant::now();
+	if watch_enabled() {
+		let watch_id = NEXT_WATCH_ID.fetch_add(1, Ordering::Relaxed);
+		let cache_key = key.clone();
+		let started = Watcher::start(key.clone(), ct, move |changes| {
+			apply_changes(&cache_key, watch_id, changes);
+		})?;
+		if let Some((watcher, entries)) = started {
+			return Ok(CacheEntry {
+				created_at: now,
+				updated_at: now,
+				entries,
+				watcher: Some(Arc::new(watcher)),
+				watch_id,
+			});
+		}
+	}
+	let entries = collect_entries(key, ct)?;
+	Ok(CacheEntry { created_at: now, updated_at: now, entries, watcher: None, watch_id: 0 })
+}
+
+/// Patch the entries cached for `key` with changes from its watcher.
+fn apply_changes(key: &CacheKey, watch_id: u64, changes: &[Change]) {
+	let Some(mut cached) = FS_CACHE.get_mut(key) else {
+		return;
+	};
+	if cached.watch_id != watch_id {
+		return;
+	}
+	let entries = &mut cached.entries;
+	// Entries are in walk order, which is `Path` order.
+	let position = |entries: &[GlobMatch], path: &str| {
+		entries.binary_search_by(|entry| Path::new(&entry.path).cmp(Path::new(path)))
+	};
+	if changes.len() <= 64 {
+		for change in changes {
+			match (change.kind, position(entries, &change.entry.path)) {
+				(ChangeKind::Remove, Ok(index)) => {
+					entries.remove(index);
+				},
+				(ChangeKind::Remove, Err(_)) => {},
+				(_, Ok(index)) => entries[index] = change.entry.clone(),
+				(_, Err(index)) => entries.insert(index, change.entry.clone()),
+			}
+		}
+	} else {
+		// Rebuild once instead of shifting the list for every change.
+		let touched: HashSet<&str> = changes
+			.iter()
+			.map(|change| change.entry.path.as_str())
+			.collect();
+		let updated: HashMap<&str, &GlobMatch> = changes
+			.iter()
+			.filter(|change| change.kind != ChangeKind::Remove)
+			.map(|change| (change.entry.path.as_str(), &change.entry))
+			.collect();
+		entries.retain(|entry| !touched.contains(entry.path.as_str()));
+		entries.extend(updated.into_values().cloned());
+		entries.sort_by(|a, b| Path::new(&a.path).cmp(Path::new(&b.path)));
+	}
+	cached.updated_at = Instant::now();
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
 // Cache API
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// Returns scanned entries using the global TTL cache policy.
+/// Returns scanned entries using the global cache policy: watched roots stay
+/// cached, others expire after the TTL.
 ///
-/// The returned [`ScanResult::cache_age_ms`] lets callers implement
-/// empty-result fast recheck: if a query produces zero matches and the cache is
-/// older than [`empty_recheck_ms()`], call [`force_rescan`] before returning
-/// empty.
+/// The returned [`ScanResult::cache_age_ms`] (time since the last scan or
+/// watcher update) lets callers implement empty-result fast recheck: if a
+/// query produces zero matches and the cache is older than
+/// [`empty_recheck_ms()`], call [`force_rescan`] before returning empty.
 pub fn get_or_scan(
 	root: &Path,
 	include_hidden: bool,
@@ -294,18 +407,17 @@ pub fn get_or_scan(
 	ct: &task::CancelToken,
 ) -> Result<ScanResult> {
 	let ttl = cache_ttl_ms();
+	let key = CacheKey { root: root.to_path_buf(), include_hidden, use_gitignore };
 	if ttl == 0 {
 		// Caching disabled – always scan fresh.
-		let entries = collect_entries(root, include_hidden, use_gitignore, ct)?;
+		let entries = collect_entries(&key, ct)?;
 		return Ok(ScanResult { entries, cache_age_ms: 0 });
 	}
 
-	let key = CacheKey { root: root.to_path_buf(), include_hidden, use_gitignore };
-
 	let now = Instant::now();
 	if let Some(entry) = FS_CACHE.get(&key) {
-		let age = now.duration_since(entry.created_at);
-		if age < Duration::from_millis(ttl) {
+		let age = now.duration_since(entry.updated_at);
+		if entry.is_watched() || now.duration_since(entry.created_at) < Duration::from_millis(ttl) {
 			return Ok(ScanResult {
 				entries:      entry.entries.clone(),
 				cache_age_ms: age.as_millis() as u64,
@@ -315,8 +427,9 @@ pub fn get_or_scan(
 		FS_CACHE.remove(&key);
 	}
 
-	let entries = collect_entries(root, include_hidden, use_gitignore, ct)?;
-	FS_CACHE.insert(key, CacheEntry { created_at: now, entries: entries.clone() });
+	let entry = scan(&key, ct)?;
+	let entries = entry.entries.clone();
+	FS_CACHE.insert(key, entry);
 	evict_oldest();
 	Ok(ScanResult { entries, cache_age_ms: 0 })
 }
@@ -336,12 +449,13 @@ pub fn force_rescan(
 	let key = CacheKey { root: root.to_path_buf(), include_hidden, use_gitignore };
 	FS_CACHE.remove(&key);
 
-	let entries = collect_entries(root, include_hidden, use_gitignore, ct)?;
-	if store {
-		let now = Instant::now();
-		FS_CACHE.insert(key, CacheEntry { created_at: now, entries: entries.clone() });
-		evict_oldest();
+	if !store {
+		return collect_entries(&key, ct);
 	}
+	let entry = scan(&key, ct)?;
+	let entries = entry.entries.clone();
+	FS_CACHE.insert(key, entry);
+	evict_oldest();
 	Ok(entries)
 }
 
@@ -351,31 +465,51 @@ pub fn force_rescan(
 
 /// Invalidate cache entries whose root contains `target`.
 ///
-/// Removes any cache entry whose root is a prefix of (or equal to) `target`,
-/// because a file mutation under that root makes the scan stale.
+/// A file mutation under a root makes its scan stale. Watched entries are
+/// patched now by rescanning just `target`, keeping their watcher; other
+/// entries are removed. Grep trigram index entries under `target` are
+/// dropped too.
 pub fn invalidate_path(target: &Path) {
-	let keys_to_remove: Vec<CacheKey> = FS_CACHE
-		.iter()
-		.filter(|entry| target.starts_with(&entry.key().root))
-		.map(|entry| entry.key().clone())
-		.collect();
-	for key in keys_to_remove {
-		FS_CACHE.remove(&key);
+	FS_CACHE.retain(|key, entry| entry.is_watched() || !target.starts_with(&key.root));
+	for (_, watcher) in watchers(|key| target.starts_with(&key.root)) {
+		watcher.refresh(target);
 	}
+	crate::grep::trigram::invalidate_path(target);
 }
 
-/// Clear the entire scan cache.
+/// Rescan every watched entry, drop the rest, and clear every grep trigram
+/// index.
 pub fn invalidate_all() {
-	FS_CACHE.clear();
+	FS_CACHE.retain(|_, entry| entry.is_watched());
+	for (root, watcher) in watchers(|_| true) {
+		watcher.refresh(&root);
+	}
+	crate::grep::trigram::invalidate_all();
+}
+
+/// Roots and watchers of the cached entries whose key passes `filter`.
+/// They are refreshed after the cache's locks are released, since their
+/// updates take them again.
+fn watchers(filter: impl Fn(&CacheKey) -> bool) -> Vec<(PathBuf, Arc<Watcher>)> {
+	FS_CACHE
+		.iter()
+		.filter(|entry| filter(entry.key()))
+		.filter_map(|entry| {
+			let watcher = entry.value().watcher.clone()?;
+			Some((entry.key().root.clone(), watcher))
+		})
+		.collect()
 }
 
 /// Invalidate the filesystem scan cache.
 ///
-/// When called with a path, removes entries for roots containing that path.
-/// When called without a path, clears the entire cache.
+/// When called with a path, watched roots containing that path rescan just
+/// that path, and other entries for those roots are removed. When called
+/// without a path, watched roots are rescanned and everything else is
+/// cleared. Grep trigram indexes are invalidated the same way.
 ///
-/// Intended to be called after agent file mutations (write, edit, rename,
-/// delete).
+/// Watched roots pick up changes on their own; call this to see a change
+/// before the watcher's debounce, or when watching is disabled.
 #[napi(js_name = "invalidateFsScanCache")]
 pub fn invalidate_fs_scan_cache(path: Option<String>) {
 	match path {
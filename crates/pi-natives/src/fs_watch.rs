@@ -0,0 +1,597 @@
+//! Filesystem watching for the scan cache and `watch()`.
+//!
+//! A [`Watcher`] scans a root with the scan cache rules (see
+//! [`fs_cache::walk_entries`]) and then reports entries that appear, change
+//! or disappear. Paths are rescanned with the same walker, so ignored and
+//! (unless requested) hidden files never show up, and editing a `.gitignore`
+//! adds or removes the entries it affects.
+//!
+//! Events come from the platform watcher (via `notify`), which watches each
+//! root recursively. Watchers of the same root share it and a thread that
+//! batches events until the tree has been quiet for a moment; each watcher
+//! then rescans the batch under its own visibility rules.
+
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap},
+	mem,
+	path::{Path, PathBuf},
+	sync::{
+		Arc, LazyLock, Weak,
+		atomic::{AtomicBool, Ordering},
+		mpsc::{self, Receiver, RecvTimeoutError},
+	},
+	time::{Duration, Instant},
+};
+
+use napi::{
+	bindgen_prelude::*,
+	threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
+};
+use napi_derive::napi;
+use notify::{
+	Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _,
+	event::{AccessKind, AccessMode, ModifyKind},
+};
+use parking_lot::Mutex;
+
+use crate::{
+	fs_cache::{self, CacheKey, FileType, GlobMatch},
+	task,
+};
+
+/// A burst of changes is applied once no event arrived for this long...
+const QUIET: Duration = Duration::from_millis(50);
+/// ...or once the oldest pending event is this old.
+const MAX_DELAY: Duration = Duration::from_millis(500);
+
+/// How an entry changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+	Create,
+	Modify,
+	Remove,
+}
+
+impl ChangeKind {
+	const fn as_str(self) -> &'static str {
+		match self {
+			Self::Create => "create",
+			Self::Modify => "modify",
+			Self::Remove => "remove",
+		}
+	}
+}
+
+/// A changed entry; removed entries keep their last known type and mtime.
+#[derive(Clone)]
+pub struct Change {
+	pub kind:  ChangeKind,
+	pub entry: GlobMatch,
+}
+
+type OnChanges = Box<dyn FnMut(&[Change]) + Send>;
+
+/// Watches a root until dropped.
+pub struct Watcher {
+	root:       Arc<RootWatch>,
+	subscriber: Arc<Subscriber>,
+}
+
+impl Watcher {
+	/// Scan `key.root`, watch it, and call `on_changes` from a background
+	/// thread after each burst of changes. Returns the initial scan.
+	///
+	/// Returns `None` when the root cannot be watched (for example when the
+	/// platform is out of watches), so the caller can fall back to plain
+	/// scans.
+	pub fn start(
+		key: CacheKey,
+		ct: &task::CancelToken,
+		on_changes: impl FnMut(&[Change]) + Send + 'static,
+	) -> Result<Option<(Self, Vec<GlobMatch>)>> {
+		let Some(root) = RootWatch::get(&key.root)? else {
+			return Ok(None);
+		};
+		// Subscribe before scanning, so changes made during the scan are
+		// queued and applied below.
+		let subscriber = Arc::new(Subscriber {
+			state:      Mutex::new(State {
+				key,
+				known: BTreeMap::new(),
+				pending: Pending::default(),
+				ready: false,
+			}),
+			on_changes: Mutex::new(Box::new(on_changes)),
+		});
+		root.subscribers.lock().push(Arc::clone(&subscriber));
+		let watcher = Self { root, subscriber };
+
+		let key = watcher.subscriber.state.lock().key.clone();
+		let mut entries = Vec::new();
+		fs_cache::walk_entries(&key, &key.root, None, ct, |entry| entries.push(entry))?;
+
+		let mut state = watcher.subscriber.state.lock();
+		state.known = entries
+			.iter()
+			.map(|entry| (entry.path.clone(), entry.clone()))
+			.collect();
+		let pending = mem::take(&mut state.pending);
+		if !pending.is_empty() {
+			state.apply(&pending);
+			// `known` is in string order; the scan cache wants walk order.
+			entries = state.known.values().cloned().collect();
+			entries.sort_by(|a, b| Path::new(&a.path).cmp(Path::new(&b.path)));
+		}
+		state.ready = true;
+		drop(state);
+		Ok(Some((watcher, entries)))
+	}
+
+	/// Rescan `path` (absolute, under the root) now and deliver its changes,
+	/// rather than wait for the platform's events. Known paths are just
+	/// stat'ed.
+	pub fn refresh(&self, path: &Path) {
+		let mut on_changes = self.subscriber.on_changes.lock();
+		let changes = {
+			let mut state = self.subscriber.state.lock();
+			if !state.ready || !path.starts_with(&state.key.root) {
+				return;
+			}
+			let relative = fs_cache::normalize_relative_path(&state.key.root, path).into_owned();
+			let tree = !state.known.contains_key(&relative);
+			state.queue(&Paths { paths: vec![relative], tree });
+			let pending = mem::take(&mut state.pending);
+			state.apply(&pending)
+		};
+		if !changes.is_empty() {
+			on_changes(&changes);
+		}
+	}
+
+	/// Whether changes are still being tracked. A watcher stops when its
+	/// root disappears or the platform watcher fails, such as when it runs
+	/// out of watches.
+	pub fn is_alive(&self) -> bool {
+		self.root.alive.load(Ordering::Acquire)
+	}
+}
+
+impl Drop for Watcher {
+	fn drop(&mut self) {
+		// A batch being delivered may still reach the callback.
+		self
+			.root
+			.subscribers
+			.lock()
+			.retain(|subscriber| !Arc::ptr_eq(subscriber, &self.subscriber));
+	}
+}
+
+/// Live root watchers by canonical root path.
+static ROOTS: LazyLock<Mutex<HashMap<PathBuf, Weak<RootWatch>>>> =
+	LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The platform watcher for one root, shared by every [`Watcher`] of it.
+/// Dropping it stops the platform watcher, which ends the batching thread.
+struct RootWatch {
+	_watcher:    RecommendedWatcher,
+	subscribers: Arc<Mutex<Vec<Arc<Subscriber>>>>,
+	alive:       Arc<AtomicBool>,
+}
+
+impl RootWatch {
+	/// The live watcher of `root`, started if there is none.
+	fn get(root: &Path) -> Result<Option<Arc<Self>>> {
+		if let Some(watch) = Self::lookup(root) {
+			return Ok(Some(watch));
+		}
+		// Started outside the lock: a recursive watch visits every directory.
+		let Some(watch) = Self::start(root)? else {
+			return Ok(None);
+		};
+		let mut roots = ROOTS.lock();
+		roots.retain(|_, watch| watch.strong_count() > 0);
+		if let Some(existing) = roots
+			.get(root)
+			.and_then(Weak::upgrade)
+			.filter(|existing| existing.alive.load(Ordering::Acquire))
+		{
+			// Another scan of this root got there first.
+			return Ok(Some(existing));
+		}
+		roots.insert(root.to_path_buf(), Arc::downgrade(&watch));
+		Ok(Some(watch))
+	}
+
+	fn lookup(root: &Path) -> Option<Arc<Self>> {
+		ROOTS
+			.lock()
+			.get(root)
+			.and_then(Weak::upgrade)
+			.filter(|watch| watch.alive.load(Ordering::Acquire))
+	}
+
+	fn start(root: &Path) -> Result<Option<Arc<Self>>> {
+		let (tx, rx) = mpsc::channel();
+		let Ok(mut watcher) = notify::recommended_watcher(tx) else {
+			return Ok(None);
+		};
+		if watcher.watch(root, RecursiveMode::Recursive).is_err() {
+			return Ok(None);
+		}
+		let subscribers = Arc::new(Mutex::new(Vec::new()));
+		let alive = Arc::new(AtomicBool::new(true));
+		std::thread::Builder::new()
+			.name("pi-fs-watch".to_string())
+			.spawn({
+				let root = root.to_path_buf();
+				let subscribers = Arc::clone(&subscribers);
+				let alive = Arc::clone(&alive);
+				move || {
+					run(&root, &rx, &subscribers);
+					alive.store(false, Ordering::Release);
+				}
+			})
+			.map_err(|err| Error::from_reason(format!("Failed to start watcher: {err}")))?;
+		Ok(Some(Arc::new(Self { _watcher: watcher, subscribers, alive })))
+	}
+}
+
+struct Subscriber {
+	state:      Mutex<State>,
+	/// Held from computing a batch until it is delivered, so batches reach
+	/// the callback in order. Taken before `state`, which must not be held
+	/// while the callback runs: it may take the scan cache's locks.
+	on_changes: Mutex<OnChanges>,
+}
+
+/// Batch events for a root until it is quiet, then let each subscriber
+/// rescan what changed. Returns when the platform watcher stops or fails, or
+/// the root disappears.
+fn run(
+	root: &Path,
+	rx: &Receiver<notify::Result<Event>>,
+	subscribers: &Mutex<Vec<Arc<Subscriber>>>,
+) {
+	let mut since: Option<Instant> = None;
+	loop {
+		let received = match since {
+			Some(since) => rx.recv_timeout(QUIET.min(MAX_DELAY.saturating_sub(since.elapsed()))),
+			None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+		};
+		let timed_out = match received {
+			Ok(Ok(event)) => {
+				let Some(paths) = Paths::of(root, &event) else {
+					continue;
+				};
+				for subscriber in subscribers.lock().iter() {
+					subscriber.state.lock().queue(&paths);
+				}
+				since.get_or_insert_with(Instant::now);
+				false
+			},
+			// Changes may have been missed, e.g. in a directory that could
+			// not be watched.
+			Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => return,
+			Err(RecvTimeoutError::Timeout) => true,
+		};
+		let Some(start) = since else {
+			continue;
+		};
+		if !timed_out && start.elapsed() < MAX_DELAY {
+			continue;
+		}
+		since = None;
+
+		let snapshot = subscribers.lock().clone();
+		for subscriber in snapshot {
+			let mut on_changes = subscriber.on_changes.lock();
+			let changes = {
+				let mut state = subscriber.state.lock();
+				if !state.ready {
+					// Still scanning; it applies its own pending paths.
+					continue;
+				}
+				let pending = mem::take(&mut state.pending);
+				state.apply(&pending)
+			};
+			if !changes.is_empty() {
+				on_changes(&changes);
+			}
+		}
+		if !std::fs::metadata(root).is_ok_and(|metadata| metadata.is_dir()) {
+			return;
+		}
+	}
+}
+
+/// The paths of one event, relative to the root.
+struct Paths {
+	paths: Vec<String>,
+	/// Rescan the paths and everything below them, rather than refresh the
+	/// metadata of known paths.
+	tree:  bool,
+}
+
+impl Paths {
+	fn of(root: &Path, event: &Event) -> Option<Self> {
+		if event.need_rescan() {
+			// Events were dropped; compare the whole tree instead.
+			return Some(Self { paths: vec![String::new()], tree: true });
+		}
+		let tree = match event.kind {
+			EventKind::Access(AccessKind::Close(AccessMode::Write)) => false,
+			// Reads, including the scans of this module.
+			EventKind::Access(_) => return None,
+			EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_)) => false,
+			_ => true,
+		};
+		let paths: Vec<String> = event
+			.paths
+			.iter()
+			.filter(|path| path.starts_with(root))
+			.map(|path| fs_cache::normalize_relative_path(root, path).into_owned())
+			.collect();
+		(!paths.is_empty()).then_some(Self { paths, tree })
+	}
+}
+
+/// Paths to rescan, collected from a burst of events.
+#[derive(Default)]
+struct Pending {
+	/// Rescan the path and everything below it.
+	trees:   BTreeSet<String>,
+	/// Refresh the metadata of a known path.
+	entries: BTreeSet<String>,
+}
+
+impl Pending {
+	fn is_empty(&self) -> bool {
+		self.trees.is_empty() && self.entries.is_empty()
+	}
+}
+
+struct State {
+	key:     CacheKey,
+	/// Visible entries by relative path.
+	known:   BTreeMap<String, GlobMatch>,
+	pending: Pending,
+	/// The initial scan is done; until then everything is queued.
+	ready:   bool,
+}
+
+impl State {
+	fn queue(&mut self, paths: &Paths) {
+		for path in &paths.paths {
+			if path.is_empty() {
+				// The root itself, e.g. removed or moved.
+				if paths.tree {
+					self.pending.trees.insert(String::new());
+				}
+				continue;
+			}
+			let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+			if !self.may_be_visible(dir) {
+				// Inside an ignored or hidden directory.
+				continue;
+			}
+			if matches!(name, ".gitignore" | ".ignore") {
+				// New rules may show or hide anything in the directory.
+				self.pending.trees.insert(dir.to_string());
+			} else if paths.tree {
+				self.pending.trees.insert(path.clone());
+			} else {
+				self.pending.entries.insert(path.clone());
+			}
+		}
+	}
+
+	/// Whether entries in directory `dir` can be visible: it is the root, it
+	/// is known, or it is being rescanned.
+	fn may_be_visible(&self, dir: &str) -> bool {
+		!self.ready
+			|| dir.is_empty()
+			|| self.known.contains_key(dir)
+			|| has_ancestor_in(dir, &self.pending.trees, true)
+	}
+
+	fn absolute(&self, relative: &str) -> PathBuf {
+		if relative.is_empty() {
+			self.key.root.clone()
+		} else {
+			self.key.root.join(relative)
+		}
+	}
+
+	/// Rescan the pending paths and update `known`, returning the changes.
+	fn apply(&mut self, pending: &Pending) -> Vec<Change> {
+		let mut changes = Vec::new();
+		for path in &pending.trees {
+			if !has_ancestor_in(path, &pending.trees, false) {
+				self.refresh_tree(path, &mut changes);
+			}
+		}
+		for path in &pending.entries {
+			if !has_ancestor_in(path, &pending.trees, true) {
+				self.refresh_entry(path, &mut changes);
+			}
+		}
+		changes
+	}
+
+	/// Rescan `relative` and everything below it.
+	fn refresh_tree(&mut self, relative: &str, changes: &mut Vec<Change>) {
+		let path = self.absolute(relative);
+		let mut found = BTreeMap::new();
+		let ct = task::CancelToken::default();
+		let mut collect = |entry: GlobMatch| {
+			found.insert(entry.path.clone(), entry);
+		};
+		// Walk errors leave `found` empty, so the entries are removed.
+		let _ = if relative.is_empty() {
+			fs_cache::walk_entries(&self.key, &path, None, &ct, &mut collect)
+		} else if let Some(parent) = path.parent()
+			&& std::fs::symlink_metadata(&path).is_ok()
+		{
+			fs_cache::walk_entries(&self.key, parent, Some(&path), &ct, &mut collect)
+		} else {
+			Ok(())
+		};
+
+		for old in self.subtree(relative) {
+			if !found.contains_key(&old)
+				&& let Some(entry) = self.known.remove(&old)
+			{
+				changes.push(Change { kind: ChangeKind::Remove, entry });
+			}
+		}
+		for (path, entry) in found {
+			let kind = match self.known.get(&path) {
+				None => ChangeKind::Create,
+				Some(old) if old.file_type != entry.file_type || old.mtime != entry.mtime => {
+					ChangeKind::Modify
+				},
+				Some(_) => continue,
+			};
+			self.known.insert(path, entry.clone());
+			changes.push(Change { kind, entry });
+		}
+	}
+
+	/// Refresh the type and mtime of a known entry.
+	fn refresh_entry(&mut self, relative: &str, changes: &mut Vec<Change>) {
+		let Some(old) = self.known.get(relative) else {
+			// Not visible, or its creation is still to be reported.
+			return;
+		};
+		match fs_cache::classify_file_type(&self.absolute(relative)) {
+			Some((file_type, mtime)) if file_type == old.file_type => {
+				if mtime != old.mtime {
+					let entry = GlobMatch { path: relative.to_string(), file_type, mtime };
+					self.known.insert(relative.to_string(), entry.clone());
+					changes.push(Change { kind: ChangeKind::Modify, entry });
+				}
+			},
+			_ => self.refresh_tree(relative, changes),
+		}
+	}
+
+	/// Known paths at or below `relative`.
+	fn subtree(&self, relative: &str) -> Vec<String> {
+		if relative.is_empty() {
+			return self.known.keys().cloned().collect();
+		}
+		let prefix = format!("{relative}/");
+		let mut paths: Vec<String> = self
+			.known
+			.range(prefix.clone()..)
+			.take_while(|(path, _)| path.starts_with(&prefix))
+			.map(|(path, _)| path.clone())
+			.collect();
+		if self.known.contains_key(relative) {
+			paths.push(relative.to_string());
+		}
+		paths
+	}
+}
+
+/// Whether `set` holds an ancestor of `path` (or `path` itself, if
+/// `inclusive`). The root is the empty path.
+fn has_ancestor_in(path: &str, set: &BTreeSet<String>, inclusive: bool) -> bool {
+	if inclusive && set.contains(path) {
+		return true;
+	}
+	!path.is_empty()
+		&& (set.contains("")
+			|| path
+				.match_indices('/')
+				.any(|(index, _)| set.contains(&path[..index])))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// N-API
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Options for `watch()`.
+#[napi(object)]
+pub struct WatchOptions {
+	/// Include hidden files (default: false).
+	pub hidden:    Option<bool>,
+	/// Respect .gitignore files (default: true).
+	pub gitignore: Option<bool>,
+}
+
+/// A change reported by `watch()`.
+#[napi(object)]
+pub struct WatchEvent {
+	/// `create`, `modify` or `remove`.
+	pub kind:      String,
+	/// Relative path from the watched root, using forward slashes.
+	pub path:      String,
+	/// Entry type; for removals, the last known type.
+	#[napi(js_name = "fileType")]
+	pub file_type: FileType,
+	/// Modification time in milliseconds since Unix epoch.
+	pub mtime:     Option<f64>,
+}
+
+/// Handle returned by `watch()`.
+#[napi]
+pub struct FsWatcher {
+	watcher: Option<Watcher>,
+}
+
+#[napi]
+impl FsWatcher {
+	/// Stop watching. Pending events may still be delivered.
+	#[napi]
+	pub fn close(&mut self) {
+		self.watcher = None;
+	}
+}
+
+/// Watch a directory and report entries that are created, modified or
+/// removed, with the same visibility rules as `glob()`.
+///
+/// # Arguments
+/// - `path`: Directory to watch.
+/// - `on_event`: Called once per change, after each burst settles.
+/// - `options`: Hidden file and gitignore handling.
+///
+/// # Returns
+/// A watcher handle; call `close()` to stop.
+#[napi(js_name = "watch")]
+pub fn watch(
+	path: String,
+	#[napi(ts_arg_type = "(error: Error | null, event: WatchEvent) => void")]
+	on_event: ThreadsafeFunction<WatchEvent>,
+	options: Option<WatchOptions>,
+) -> task::Async<FsWatcher> {
+	let (hidden, gitignore) =
+		options.map_or((None, None), |options| (options.hidden, options.gitignore));
+	task::blocking("watch", (), move |ct| {
+		let root = fs_cache::resolve_search_path(&path)?;
+		let key = CacheKey {
+			root,
+			include_hidden: hidden.unwrap_or(false),
+			use_gitignore: gitignore.unwrap_or(true),
+		};
+		let on_changes = move |changes: &[Change]| {
+			for change in changes {
+				let event = WatchEvent {
+					kind:      change.kind.as_str().to_string(),
+					path:      change.entry.path.clone(),
+					file_type: change.entry.file_type,
+					mtime:     change.entry.mtime,
+				};
+				on_event.call(Ok(event), ThreadsafeFunctionCallMode::NonBlocking);
+			}
+		};
+		match Watcher::start(key, &ct, on_changes)? {
+			Some((watcher, _)) => Ok(FsWatcher { watcher: Some(watcher) }),
+			None => Err(Error::from_reason(
+				"Failed to watch directory: the platform watcher is unavailable or out of watches",
+			)),
+		}
+	})
+}
@@ -0,0 +1,411 @@
+//! Fuzzy ranking shared by `fuzzyFind()` and `fuzzyMatch()`.
+//!
+//! Scoring uses optimal alignment (fzf v2 style, via `nucleo-matcher`):
+//! matches earn bonuses at word boundaries, after path separators, at
+//! camelCase humps and for consecutive runs; gaps are penalized. The query is
+//! split on whitespace and every word must match, ignoring case.
+//!
+//! `fuzzyMatch()` ranks in-memory candidate lists on a worker thread. A
+//! [`FuzzyCandidates`] handle keeps a converted list between keystrokes and,
+//! while the query only grows, rescans just the previous matches.
+
+use std::{cmp::Ordering, sync::Arc};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use nucleo_matcher::{
+	Config, Matcher, Utf32Str, Utf32String,
+	pattern::{AtomKind, CaseMatching, Normalization, Pattern},
+};
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::task;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Scoring
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Extra score for paths when the whole query matches within the file name,
+/// so `config` prefers `src/config.ts` over `src/con/fig.ts`. Worth about one
+/// matched character.
+const FILE_NAME_BONUS: u32 = 16;
+
+/// Scores candidates against one query.
+pub struct Scorer {
+	matcher: Matcher,
+	pattern: Pattern,
+	paths:   bool,
+	chars:   Vec<char>,
+}
+
+impl Scorer {
+	/// Build a scorer for `query`. With `paths`, `/` counts as a word
+	/// boundary and matches within the file name are preferred.
+	pub fn new(query: &str, paths: bool) -> Self {
+		let config = if paths {
+			Config::DEFAULT.match_paths()
+		} else {
+			Config::DEFAULT
+		};
+		Self {
+			matcher: Matcher::new(config),
+			pattern: Pattern::new(query, CaseMatching::Ignore, Normalization::Smart, AtomKind::Fuzzy),
+			paths,
+			chars: Vec::new(),
+		}
+	}
+
+	/// Whether the query has no words, so everything matches with score 0.
+	pub const fn is_empty(&self) -> bool {
+		self.pattern.atoms.is_empty()
+	}
+
+	/// Score `text`, or `None` when it does not match.
+	pub fn score(&mut self, text: &str) -> Option<u32> {
+		let mut chars = std::mem::take(&mut self.chars);
+		let score = self.score_utf32(to_utf32(text, &mut chars));
+		self.chars = chars;
+		score
+	}
+
+	/// Positions of the characters matched in `text`, as sorted UTF-16
+	/// offsets (JavaScript string indices).
+	pub fn positions(&mut self, text: &str) -> Vec<u32> {
+		let mut chars = std::mem::take(&mut self.chars);
+		let positions = self.positions_utf32(text, to_utf32(text, &mut chars));
+		self.chars = chars;
+		positions
+	}
+
+	fn score_utf32(&mut self, haystack: Utf32Str<'_>) -> Option<u32> {
+		if self.is_empty() {
+			return Some(0);
+		}
+		let score = self.pattern.score(haystack, &mut self.matcher)?;
+		if !self.paths {
+			return Some(score);
+		}
+		let name_start = file_name_start(haystack);
+		if name_start == 0 {
+			return Some(score + FILE_NAME_BONUS);
+		}
+		let name = haystack.slice(name_start as usize..);
+		let name_score = self.pattern.score(name, &mut self.matcher);
+		Some(name_score.map_or(score, |name_score| score.max(name_score + FILE_NAME_BONUS)))
+	}
+
+	/// Positions for the alignment [`Self::score_utf32`] picked; `text` must be
+	/// the string `haystack` was built from.
+	fn positions_utf32(&mut self, text: &str, haystack: Utf32Str<'_>) -> Vec<u32> {
+		let mut indices = Vec::new();
+		let Some(score) = self
+			.pattern
+			.indices(haystack, &mut self.matcher, &mut indices)
+		else {
+			return indices;
+		};
+		let name_start = if self.paths {
+			file_name_start(haystack)
+		} else {
+			0
+		};
+		if name_start > 0 {
+			let mut name_indices = Vec::new();
+			let name = haystack.slice(name_start as usize..);
+			if let Some(name_score) = self
+				.pattern
+				.indices(name, &mut self.matcher, &mut name_indices)
+				&& name_score + FILE_NAME_BONUS > score
+			{
+				indices = name_indices
+					.iter()
+					.map(|index| index + name_start)
+					.collect();
+			}
+		}
+
+		indices.sort_unstable();
+		indices.dedup();
+		if text.is_ascii() {
+			indices
+		} else {
+			utf16_positions(text, &indices)
+		}
+	}
+}
+
+/// Convert `text` for the matcher: one char per grapheme, as
+/// [`Utf32String::from`] does.
+fn to_utf32<'a>(text: &'a str, chars: &'a mut Vec<char>) -> Utf32Str<'a> {
+	if text.is_ascii() {
+		return Utf32Str::Ascii(text.as_bytes());
+	}
+	chars.clear();
+	chars.extend(
+		text
+			.graphemes(true)
+			.filter_map(|grapheme| grapheme.chars().next()),
+	);
+	Utf32Str::Unicode(chars)
+}
+
+/// Index of the first character of the last path component, ignoring a
+/// trailing `/` on directories.
+fn file_name_start(path: Utf32Str<'_>) -> u32 {
+	let len = path.len() as u32;
+	let end = if len > 0 && path.get(len - 1) == '/' {
+		len - 1
+	} else {
+		len
+	};
+	(0..end)
+		.rev()
+		.find(|&index| path.get(index) == '/')
+		.map_or(0, |index| index + 1)
+}
+
+/// Convert sorted grapheme indices into UTF-16 offsets of those graphemes.
+fn utf16_positions(text: &str, indices: &[u32]) -> Vec<u32> {
+	let mut positions = Vec::with_capacity(indices.len());
+	let mut wanted = indices.iter().copied().peekable();
+	let mut offset = 0u32;
+	for (index, grapheme) in text.graphemes(true).enumerate() {
+		let Some(&next) = wanted.peek() else { break };
+		if next as usize == index {
+			positions.push(offset);
+			wanted.next();
+		}
+		offset += grapheme.encode_utf16().count() as u32;
+	}
+	positions
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Public types
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A candidate with a caller-defined key.
+#[napi(object)]
+pub struct FuzzyCandidate {
+	/// Text to match against.
+	pub text: String,
+	/// Identifier returned with the match.
+	pub key:  Option<String>,
+}
+
+/// Options for `fuzzyMatch()`.
+#[napi(object)]
+pub struct FuzzyMatchOptions {
+	/// Maximum number of matches to return (default: 100).
+	#[napi(js_name = "maxResults")]
+	pub max_results: Option<u32>,
+	/// Treat candidates as paths: `/` separates words and matches within the
+	/// file name rank higher (default: false).
+	pub paths:       Option<bool>,
+}
+
+/// A ranked candidate.
+#[napi(object)]
+pub struct FuzzyMatch {
+	/// Candidate text.
+	pub text:      String,
+	/// Candidate key, if one was given.
+	pub key:       Option<String>,
+	/// Index of the candidate in the input list.
+	pub index:     u32,
+	/// Match quality score (higher is better).
+	pub score:     u32,
+	/// Matched character positions in `text`, as sorted UTF-16 offsets.
+	pub positions: Vec<u32>,
+}
+
+/// Result of `fuzzyMatch()`.
+#[napi(object)]
+pub struct FuzzyMatchResult {
+	/// Best matches, best first (up to `maxResults`).
+	pub matches:       Vec<FuzzyMatch>,
+	/// Total number of matching candidates.
+	#[napi(js_name = "totalMatches")]
+	pub total_matches: u32,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Candidate lists
+// ═══════════════════════════════════════════════════════════════════════════
+
+struct Candidate {
+	text:     String,
+	key:      Option<String>,
+	haystack: Utf32String,
+}
+
+/// Candidates scored per parallel task.
+const PARALLEL_CHUNK: usize = 4096;
+
+/// The last query and every candidate it matched.
+struct LastQuery {
+	query:   String,
+	paths:   bool,
+	matched: Vec<u32>,
+}
+
+impl LastQuery {
+	/// Whether every match for `query` is among these matches. Appending to
+	/// the query only ever removes matches, unless it completes an escape.
+	fn narrows_to(&self, query: &str, paths: bool) -> bool {
+		self.paths == paths && query.starts_with(&self.query) && !self.query.ends_with('\\')
+	}
+}
+
+/// Converted candidates, shared with the worker threads that rank them.
+struct CandidateList {
+	candidates: Vec<Candidate>,
+	last:       Mutex<Option<LastQuery>>,
+}
+
+impl CandidateList {
+	fn new(candidates: Vec<Either<String, FuzzyCandidate>>) -> Self {
+		let candidates = candidates
+			.into_iter()
+			.map(|candidate| {
+				let (text, key) = match candidate {
+					Either::A(text) => (text, None),
+					Either::B(FuzzyCandidate { text, key }) => (text, key),
+				};
+				let haystack = Utf32String::from(text.as_str());
+				Candidate { text, key, haystack }
+			})
+			.collect();
+		Self { candidates, last: Mutex::new(None) }
+	}
+
+	fn rank(&self, query: String, options: Option<FuzzyMatchOptions>) -> FuzzyMatchResult {
+		let (max_results, paths) =
+			options.map_or((None, None), |options| (options.max_results, options.paths));
+		let max_results = max_results.unwrap_or(100) as usize;
+		let paths = paths.unwrap_or(false);
+		// Appending to the query only narrows the matches, so rescan those.
+		let pool: Vec<u32> = match &*self.last.lock() {
+			Some(last) if last.narrows_to(&query, paths) => last.matched.clone(),
+			_ => (0..self.candidates.len() as u32).collect(),
+		};
+		let candidates = &self.candidates;
+		let mut scored: Vec<(u32, u32)> = pool
+			.par_chunks(PARALLEL_CHUNK)
+			.flat_map_iter(|chunk| {
+				let mut scorer = Scorer::new(&query, paths);
+				chunk
+					.iter()
+					.filter_map(|&index| {
+						let haystack = candidates[index as usize].haystack.slice(..);
+						scorer.score_utf32(haystack).map(|score| (index, score))
+					})
+					.collect::<Vec<_>>()
+			})
+			.collect();
+		let total_matches = scored.len() as u32;
+		let matched = scored.iter().map(|&(index, _)| index).collect();
+
+		// Best first; among equal scores, shorter text, then input order. An
+		// empty query keeps the input order.
+		let mut scorer = Scorer::new(&query, paths);
+		let by_length = !scorer.is_empty();
+		let order = |a: &(u32, u32), b: &(u32, u32)| {
+			b.1.cmp(&a.1)
+				.then_with(|| {
+					let len = |index: u32| candidates[index as usize].text.len();
+					if by_length {
+						len(a.0).cmp(&len(b.0))
+					} else {
+						Ordering::Equal
+					}
+				})
+				.then_with(|| a.0.cmp(&b.0))
+		};
+		if scored.len() > max_results && max_results > 0 {
+			scored.select_nth_unstable_by(max_results - 1, order);
+		}
+		scored.truncate(max_results);
+		scored.sort_unstable_by(order);
+
+		let matches = scored
+			.into_iter()
+			.map(|(index, score)| {
+				let candidate = &candidates[index as usize];
+				let positions = if scorer.is_empty() {
+					Vec::new()
+				} else {
+					scorer.positions_utf32(&candidate.text, candidate.haystack.slice(..))
+				};
+				FuzzyMatch {
+					text: candidate.text.clone(),
+					key: candidate.key.clone(),
+					index,
+					score,
+					positions,
+				}
+			})
+			.collect();
+		*self.last.lock() = Some(LastQuery { query, paths, matched });
+		FuzzyMatchResult { matches, total_matches }
+	}
+}
+
+/// A candidate list prepared for repeated `fuzzyMatch()` calls.
+#[napi]
+pub struct FuzzyCandidates {
+	list: Arc<CandidateList>,
+}
+
+#[napi]
+impl FuzzyCandidates {
+	/// Prepare `candidates`: strings, or `{ text, key }` objects.
+	#[napi(constructor)]
+	pub fn new(candidates: Vec<Either<String, FuzzyCandidate>>) -> Self {
+		Self { list: Arc::new(CandidateList::new(candidates)) }
+	}
+
+	/// Number of candidates.
+	#[napi(getter)]
+	pub fn length(&self) -> u32 {
+		self.list.candidates.len() as u32
+	}
+
+	/// Rank the candidates against `query`.
+	#[napi(js_name = "match")]
+	pub fn match_query(
+		&self,
+		query: String,
+		options: Option<FuzzyMatchOptions>,
+	) -> task::Async<FuzzyMatchResult> {
+		let list = Arc::clone(&self.list);
+		task::blocking("fuzzy_match", (), move |_| Ok(list.rank(query, options)))
+	}
+}
+
+/// Rank a candidate list against a fuzzy query.
+///
+/// # Arguments
+/// - `query`: Whitespace-separated words that must all match.
+/// - `candidates`: A [`FuzzyCandidates`] handle, or strings / `{ text, key }`
+///   objects to rank once.
+/// - `options`: Result limit and path matching.
+///
+/// # Returns
+/// A promise for the best matches with scores and match positions.
+#[napi(js_name = "fuzzyMatch")]
+pub fn fuzzy_match(
+	query: String,
+	#[napi(ts_arg_type = "FuzzyCandidates | Array<string | FuzzyCandidate>")] candidates: Either<
+		ClassInstance<'_, FuzzyCandidates>,
+		Vec<Either<String, FuzzyCandidate>>,
+	>,
+	options: Option<FuzzyMatchOptions>,
+) -> task::Async<FuzzyMatchResult> {
+	match candidates {
+		Either::A(handle) => handle.match_query(query, options),
+		Either::B(list) => FuzzyCandidates::new(list).match_query(query, options),
+	}
+}
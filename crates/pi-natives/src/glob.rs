@@ -3,20 +3,27 @@
 //!
 //! # Overview
 //! Resolves a search root, obtains scanned entries via [`fs_cache`], applies
-//! glob matching plus optional file-type filtering, and optionally streams each
-//! accepted match through a callback.
+//! glob matching plus optional entry-kind and file-type (see
+//! [`crate::file_types`]) filtering, and optionally streams each accepted match
+//! through a callback.
+//!
+//! Include patterns are tried in order and the last one that matches decides,
+//! so a `!` pattern can carve out part of an earlier one. Exclusions are
+//! gitignore rules (from `exclude` and `ignoreFiles`) applied after the scan,
+//! so they do not split the shared scan cache.
 //!
 //! The walker always skips `.git`, and skips `node_modules` unless explicitly
 //! requested.
 //!
 //! # Example
 //! ```ignore
-//! // JS: await native.glob({ pattern: "*.rs", path: "." })
+//! // JS: await native.glob({ patterns: ["*.ts", "*.tsx", "!*.test.ts"], exclude: ["dist/"], path: "." })
 //! ```
 
 use std::path::Path;
 
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use napi::{
 	bindgen_prelude::*,
 	threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
@@ -25,18 +32,39 @@ use napi_derive::napi;
 
 // Re-export entry types so existing `glob::FileType` / `glob::GlobMatch` paths still work.
 pub use crate::fs_cache::{FileType, GlobMatch};
-use crate::{fs_cache, task};
+use crate::{file_types::TypeFilter, frecency::Frecency, fs_cache, task};
 
 /// Input options for `glob`, including traversal, filtering, and cancellation.
 #[napi(object)]
 pub struct GlobOptions<'env> {
 	/// Glob pattern to match (e.g., "*.ts").
-	pub pattern:              String,
+	pub pattern:              Option<String>,
+	/// More glob patterns, tried after `pattern`. A `!` prefix excludes
+	/// matches of earlier patterns; the last matching pattern decides.
+	pub patterns:             Option<Vec<String>>,
+	/// Gitignore-style rules, relative to the search root, for entries to
+	/// leave out (`!` re-includes). Excluding a directory excludes its
+	/// contents.
+	pub exclude:              Option<Vec<String>>,
+	/// Extra gitignore-style files whose rules apply like `exclude`. Relative
+	/// file paths resolve against the working directory.
+	#[napi(js_name = "ignoreFiles")]
+	pub ignore_files:         Option<Vec<String>>,
 	/// Directory to search.
 	pub path:                 String,
 	/// Filter by file type: "file", "dir", or "symlink".
 	#[napi(js_name = "fileType")]
 	pub file_type:            Option<FileType>,
+	/// Filter files by ripgrep file type (e.g., "rust", "ts"); directories are
+	/// kept.
+	#[napi(js_name = "type")]
+	pub type_filter:          Option<String>,
+	/// Extra type definitions, as `name:glob` or `name:include:a,b`.
+	#[napi(js_name = "typeAdd")]
+	pub type_add:             Option<Vec<String>>,
+	/// Exclude files of these types.
+	#[napi(js_name = "typeNot")]
+	pub type_not:             Option<Vec<String>>,
 	/// Include hidden files (default: false).
 	pub hidden:               Option<bool>,
 	/// Maximum number of results to return.
@@ -49,6 +77,9 @@ pub struct GlobOptions<'env> {
 	/// Sort results by mtime (most recent first) before applying limit.
 	#[napi(js_name = "sortByMtime")]
 	pub sort_by_mtime:        Option<bool>,
+	/// Sort results by frecency (entries recorded with `recordFileAccess()`
+	/// first, then recently modified ones) before applying limit.
+	pub frecency:             Option<bool>,
 	/// Include `node_modules` entries when the pattern does not explicitly
 	/// mention them.
 	#[napi(js_name = "includeNodeModules")]
@@ -82,34 +113,115 @@ fn build_glob_pattern(glob: &str) -> String {
 	}
 }
 
-fn compile_glob(glob: &str) -> Result<GlobSet> {
+/// Include patterns; the last one matching a path decides whether it is kept.
+struct PatternSet {
+	globs:       GlobSet,
+	/// Whether each glob (by index) came from a `!` pattern.
+	negated:     Vec<bool>,
+	any_negated: bool,
+}
+
+impl PatternSet {
+	fn is_match(&self, path: &str) -> bool {
+		if !self.any_negated {
+			return self.globs.is_match(path);
+		}
+		// Indices come back in ascending order.
+		self
+			.globs
+			.matches(path)
+			.last()
+			.is_some_and(|&index| !self.negated[index])
+	}
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<PatternSet> {
 	let mut builder = GlobSetBuilder::new();
-	let pattern = build_glob_pattern(glob);
-	let glob = Glob::new(&pattern)
-		.map_err(|err| Error::from_reason(format!("Invalid glob pattern: {err}")))?;
-	builder.add(glob);
+	let mut negated = Vec::with_capacity(patterns.len());
+	for pattern in patterns {
+		let (is_negated, pattern) = match pattern.strip_prefix('!') {
+			Some(rest) => (true, rest),
+			None => (false, pattern.as_str()),
+		};
+		let glob = Glob::new(&build_glob_pattern(pattern))
+			.map_err(|err| Error::from_reason(format!("Invalid glob pattern: {err}")))?;
+		builder.add(glob);
+		negated.push(is_negated);
+	}
+	let globs = builder
+		.build()
+		.map_err(|err| Error::from_reason(format!("Failed to build glob matcher: {err}")))?;
+	let any_negated = negated.contains(&true);
+	Ok(PatternSet { globs, negated, any_negated })
+}
+
+/// Build the gitignore matcher for `exclude` rules and `ignoreFiles`, or `None`
+/// when there are neither.
+fn compile_excludes(
+	root: &Path,
+	exclude: &[String],
+	ignore_files: &[String],
+) -> Result<Option<Gitignore>> {
+	if exclude.is_empty() && ignore_files.is_empty() {
+		return Ok(None);
+	}
+	let mut builder = GitignoreBuilder::new(root);
+	for file in ignore_files {
+		if let Some(err) = builder.add(file) {
+			return Err(Error::from_reason(format!("Invalid ignore file: {err}")));
+		}
+	}
+	// Added last so they override the ignore files.
+	for rule in exclude {
+		builder
+			.add_line(None, rule)
+			.map_err(|err| Error::from_reason(format!("Invalid exclude pattern: {err}")))?;
+	}
 	builder
 		.build()
-		.map_err(|err| Error::from_reason(format!("Failed to build glob matcher: {err}")))
+		.map(Some)
+		.map_err(|err| Error::from_reason(format!("Failed to build exclude matcher: {err}")))
+}
+
+/// Normalize `pattern` and `patterns` into one ordered list. Blank patterns
+/// are dropped, and a list of only `!` patterns starts from everything.
+fn collect_patterns(pattern: Option<String>, patterns: Option<Vec<String>>) -> Vec<String> {
+	let mut collected: Vec<String> = pattern
+		.into_iter()
+		.chain(patterns.unwrap_or_default())
+		.filter_map(|pattern| {
+			let pattern = pattern.trim();
+			(!pattern.is_empty() && pattern != "!").then(|| pattern.to_string())
+		})
+		.collect();
+	if collected.iter().all(|pattern| pattern.starts_with('!')) {
+		collected.insert(0, "*".to_string());
+	}
+	collected
 }
 
 /// Internal runtime config for a single glob execution.
 struct GlobConfig {
 	root:                  std::path::PathBuf,
-	pattern:               String,
+	patterns:              Vec<String>,
+	exclude:               Vec<String>,
+	ignore_files:          Vec<String>,
 	include_hidden:        bool,
 	file_type_filter:      Option<FileType>,
+	types:                 Option<TypeFilter>,
 	max_results:           usize,
 	use_gitignore:         bool,
 	mentions_node_modules: bool,
 	sort_by_mtime:         bool,
+	sort_by_frecency:      bool,
 	use_cache:             bool,
 }
 
 /// Filter and collect matching entries from a pre-scanned list.
 fn filter_entries(
 	entries: &[GlobMatch],
-	glob_set: &GlobSet,
+	patterns: &PatternSet,
+	exclude: Option<&Gitignore>,
 	config: &GlobConfig,
 	on_match: Option<&ThreadsafeFunction<GlobMatch>>,
 	ct: &task::CancelToken,
@@ -125,7 +237,14 @@ fn filter_entries(
 			// Apply post-scan node_modules policy before glob matching.
 			continue;
 		}
-		if !glob_set.is_match(&entry.path) {
+		if !patterns.is_match(&entry.path) {
+			continue;
+		}
+		if exclude.is_some_and(|exclude| {
+			exclude
+				.matched_path_or_any_parents(&entry.path, entry.file_type == FileType::Dir)
+				.is_ignore()
+		}) {
 			continue;
 		}
 		if config
@@ -134,13 +253,19 @@ fn filter_entries(
 		{
 			continue;
 		}
+		if let Some(types) = &config.types
+			&& !types.is_match(Path::new(&entry.path), entry.file_type == FileType::Dir)
+		{
+			continue;
+		}
 		if let Some(callback) = on_match {
 			callback.call(Ok(entry.clone()), ThreadsafeFunctionCallMode::NonBlocking);
 		}
 
 		matches.push(entry.clone());
-		// Only early-break when not sorting; mtime sort requires full candidate set.
-		if !config.sort_by_mtime && matches.len() >= config.max_results {
+		// Only early-break when not sorting; sorting requires the full
+		// candidate set.
+		if !config.sort_by_mtime && !config.sort_by_frecency && matches.len() >= config.max_results {
 			break;
 		}
 	}
@@ -154,7 +279,9 @@ fn run_glob(
 	on_match: Option<&ThreadsafeFunction<GlobMatch>>,
 	ct: task::CancelToken,
 ) -> Result<GlobResult> {
-	let glob_set = compile_glob(&config.pattern)?;
+	let patterns = compile_patterns(&config.patterns)?;
+	let exclude = compile_excludes(&config.root, &config.exclude, &config.ignore_files)?;
+	let exclude = exclude.as_ref();
 	if config.max_results == 0 {
 		return Ok(GlobResult { matches: Vec::new(), total_matches: 0 });
 	}
@@ -162,7 +289,7 @@ fn run_glob(
 	let mut matches = if config.use_cache {
 		let scan =
 			fs_cache::get_or_scan(&config.root, config.include_hidden, config.use_gitignore, &ct)?;
-		let mut matches = filter_entries(&scan.entries, &glob_set, &config, on_match, &ct)?;
+		let mut matches = filter_entries(&scan.entries, &patterns, exclude, &config, on_match, &ct)?;
 		// Empty-result recheck: if we got zero matches from a cached scan that's old
 		// enough, force a rescan and try once more before returning empty.
 		if matches.is_empty() && scan.cache_age_ms >= fs_cache::empty_recheck_ms() {
@@ -173,7 +300,7 @@ fn run_glob(
 				true,
 				&ct,
 			)?;
-			matches = filter_entries(&fresh, &glob_set, &config, on_match, &ct)?;
+			matches = filter_entries(&fresh, &patterns, exclude, &config, on_match, &ct)?;
 		}
 		matches
 	} else {
@@ -184,11 +311,11 @@ fn run_glob(
 			false,
 			&ct,
 		)?;
-		filter_entries(&fresh, &glob_set, &config, on_match, &ct)?
+		filter_entries(&fresh, &patterns, exclude, &config, on_match, &ct)?
 	};
 
 	if config.sort_by_mtime {
-		// Sorting mode: rank by mtime descending, then apply max-results truncation.
+		// Sorting mode: rank by mtime descending; truncation happens below.
 		matches.sort_by(|a, b| {
 			let a_mtime = a.mtime.unwrap_or(0.0);
 			let b_mtime = b.mtime.unwrap_or(0.0);
@@ -196,24 +323,36 @@ fn run_glob(
 				.partial_cmp(&a_mtime)
 				.unwrap_or(std::cmp::Ordering::Equal)
 		});
-		matches.truncate(config.max_results);
 	}
+	if config.sort_by_frecency {
+		// Stable, so mtime order (when requested) breaks ties.
+		let frecency = Frecency::load(&config.root);
+		let mut scored: Vec<(f64, GlobMatch)> = matches
+			.into_iter()
+			.map(|entry| (frecency.score(&entry.path, entry.mtime), entry))
+			.collect();
+		scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+		matches = scored.into_iter().map(|(_, entry)| entry).collect();
+	}
+	matches.truncate(config.max_results);
 	let total_matches = matches.len().min(u32::MAX as usize) as u32;
 	Ok(GlobResult { matches, total_matches })
 }
 
-/// Find filesystem entries matching a glob pattern.
+/// Find filesystem entries matching glob patterns.
 ///
-/// Resolves the search root, scans entries, applies glob and optional file-type
-/// filters, and optionally streams each accepted match through `on_match`.
+/// Resolves the search root, scans entries, applies glob, exclude and optional
+/// file-type filters, and optionally streams each accepted match through
+/// `on_match`. Without any pattern, every entry matches.
 ///
-/// If `sortByMtime` is enabled, all matching entries are collected, sorted by
-/// descending mtime, then truncated to `maxResults`.
+/// If `sortByMtime` or `frecency` is enabled, all matching entries are
+/// collected, sorted by descending mtime or frecency (see
+/// [`crate::frecency`]), then truncated to `maxResults`.
 ///
 /// # Errors
 /// Returns an error when the search path cannot be resolved, the path is not a
-/// directory, the glob pattern is invalid, or cancellation/timeout is
-/// triggered.
+/// directory, a glob pattern or exclude rule is invalid, an ignore file cannot
+/// be read, or cancellation/timeout is triggered.
 #[napi(js_name = "glob")]
 pub fn glob(
 	options: GlobOptions<'_>,
@@ -223,21 +362,26 @@ pub fn glob(
 ) -> task::Async<GlobResult> {
 	let GlobOptions {
 		pattern,
+		patterns,
+		exclude,
+		ignore_files,
 		path,
 		file_type,
+		type_filter,
+		type_add,
+		type_not,
 		hidden,
 		max_results,
 		gitignore,
 		sort_by_mtime,
+		frecency,
 		cache,
 		include_node_modules,
 		timeout_ms,
 		signal,
 	} = options;
 
-	let pattern = pattern.trim();
-	let pattern = if pattern.is_empty() { "*" } else { pattern };
-	let pattern = pattern.to_string();
+	let patterns = collect_patterns(pattern, patterns);
 
 	let ct = task::CancelToken::new(timeout_ms, signal);
 
@@ -247,13 +391,24 @@ pub fn glob(
 				root: fs_cache::resolve_search_path(&path)?,
 				include_hidden: hidden.unwrap_or(false),
 				file_type_filter: file_type,
+				types: TypeFilter::new(
+					type_filter.as_deref(),
+					type_add.as_deref(),
+					type_not.as_deref(),
+				)?,
 				max_results: max_results.map_or(usize::MAX, |value| value as usize),
 				use_gitignore: gitignore.unwrap_or(true),
-				mentions_node_modules: include_node_modules
-					.unwrap_or_else(|| pattern.contains("node_modules")),
+				mentions_node_modules: include_node_modules.unwrap_or_else(|| {
+					patterns
+						.iter()
+						.any(|pattern| !pattern.starts_with('!') && pattern.contains("node_modules"))
+				}),
 				sort_by_mtime: sort_by_mtime.unwrap_or(false),
+				sort_by_frecency: frecency.unwrap_or(false),
 				use_cache: cache.unwrap_or(false),
-				pattern,
+				patterns,
+				exclude: exclude.unwrap_or_default(),
+				ignore_files: ignore_files.unwrap_or_default(),
 			},
 			on_match.as_ref(),
 			ct,
@@ -3,21 +3,26 @@
 //! Provides two layers:
 //! - `search()` for in-memory content search.
 //! - `grep()` for filesystem search with glob/type filtering.
+//! - `grepJson()` for the same search as ripgrep `--json` messages (see
+//!   [`json`]).
+//! - `replace()` for search-and-replace across files (see [`replace`]).
 //!
 //! The filesystem search matches the previous JS wrapper behavior, including
 //! global offsets, optional match limits, and per-file match summaries.
 
 use std::{
 	fs::File,
-	io::{self, Cursor, Read},
+	io::{self, BufRead, BufReader, Cursor, Read, Seek},
 	path::{Path, PathBuf},
 };
 
+use encoding_rs::Encoding;
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use grep_matcher::Matcher;
+use grep_matcher::{Captures, Matcher};
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::{
-	BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind, SinkMatch,
+	BinaryDetection, MmapChoice, Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind,
+	SinkFinish, SinkMatch,
 };
 use napi::{
 	JsString,
@@ -28,9 +33,17 @@ use napi_derive::napi;
 use rayon::prelude::*;
 use smallvec::SmallVec;
 
-use crate::{fs_cache, task};
+use crate::{file_types::TypeFilter, fs_cache, task};
 
-const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+mod archive;
+mod engine;
+mod json;
+mod replace;
+pub mod trigram;
+
+use engine::{Engine, EngineMatcher};
+pub use json::{GrepJsonStream, grep_json};
+pub use replace::replace;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum OutputMode {
@@ -42,12 +55,36 @@ enum OutputMode {
 #[napi(object)]
 pub struct SearchOptions {
 	/// Regex pattern to search for.
-	pub pattern:        String,
+	pub pattern:        Option<String>,
+	/// Additional patterns; a line matches if any pattern does.
+	pub patterns:       Option<Vec<String>>,
+	/// File with one pattern per line, used after `patterns`.
+	#[napi(js_name = "patternFile")]
+	pub pattern_file:   Option<String>,
 	/// Case-insensitive search.
 	#[napi(js_name = "ignoreCase")]
 	pub ignore_case:    Option<bool>,
 	/// Enable multiline matching.
 	pub multiline:      Option<bool>,
+	/// Case-insensitive unless a pattern contains an uppercase letter.
+	#[napi(js_name = "smartCase")]
+	pub smart_case:     Option<bool>,
+	/// Match patterns as literal strings instead of regexes.
+	#[napi(js_name = "fixedStrings")]
+	pub fixed_strings:  Option<bool>,
+	/// Only match whole words.
+	#[napi(js_name = "wordRegexp")]
+	pub word_regexp:    Option<bool>,
+	/// Select lines that do not match.
+	#[napi(js_name = "invertMatch")]
+	pub invert_match:   Option<bool>,
+	/// Regex engine: `default`, `fancy` (look-around and backreferences), or
+	/// `auto` to fall back to `fancy` for patterns `default` rejects (default).
+	pub engine:         Option<String>,
+	/// Text encoding to transcode from before matching, as a WHATWG label
+	/// (`utf-16le`, `utf-16be`, `latin1`, `shift_jis`, ...). `auto` (default)
+	/// reads UTF-8 unless a byte-order mark says otherwise.
+	pub encoding:       Option<String>,
 	/// Maximum number of matches to return.
 	#[napi(js_name = "maxCount")]
 	pub max_count:      Option<u32>,
@@ -72,19 +109,50 @@ pub struct SearchOptions {
 #[napi(object)]
 pub struct GrepOptions<'env> {
 	/// Regex pattern to search for.
-	pub pattern:        String,
+	pub pattern:        Option<String>,
+	/// Additional patterns; a line matches if any pattern does.
+	pub patterns:       Option<Vec<String>>,
+	/// File with one pattern per line, used after `patterns`.
+	#[napi(js_name = "patternFile")]
+	pub pattern_file:   Option<String>,
 	/// Directory or file to search.
 	pub path:           String,
 	/// Glob filter for filenames (e.g., "*.ts").
 	pub glob:           Option<String>,
-	/// Filter by file type (e.g., "js", "py", "rust").
+	/// Filter by file type (e.g., "js", "py", "rust"), from ripgrep's type
+	/// list plus `typeAdd`. Unknown names match as an extension.
 	#[napi(js_name = "type")]
 	pub type_filter:    Option<String>,
+	/// Extra type definitions, as `name:glob` or `name:include:a,b`.
+	#[napi(js_name = "typeAdd")]
+	pub type_add:       Option<Vec<String>>,
+	/// Exclude files of these types.
+	#[napi(js_name = "typeNot")]
+	pub type_not:       Option<Vec<String>>,
 	/// Case-insensitive search.
 	#[napi(js_name = "ignoreCase")]
 	pub ignore_case:    Option<bool>,
 	/// Enable multiline matching.
 	pub multiline:      Option<bool>,
+	/// Case-insensitive unless a pattern contains an uppercase letter.
+	#[napi(js_name = "smartCase")]
+	pub smart_case:     Option<bool>,
+	/// Match patterns as literal strings instead of regexes.
+	#[napi(js_name = "fixedStrings")]
+	pub fixed_strings:  Option<bool>,
+	/// Only match whole words.
+	#[napi(js_name = "wordRegexp")]
+	pub word_regexp:    Option<bool>,
+	/// Select lines that do not match.
+	#[napi(js_name = "invertMatch")]
+	pub invert_match:   Option<bool>,
+	/// Regex engine: `default`, `fancy` (look-around and backreferences), or
+	/// `auto` to fall back to `fancy` for patterns `default` rejects (default).
+	pub engine:         Option<String>,
+	/// Text encoding to transcode from before matching, as a WHATWG label
+	/// (`utf-16le`, `utf-16be`, `latin1`, `shift_jis`, ...). `auto` (default)
+	/// reads UTF-8 unless a byte-order mark says otherwise.
+	pub encoding:       Option<String>,
 	/// Include hidden files (default: true).
 	pub hidden:         Option<bool>,
 	/// Enable shared filesystem scan cache (default: false).
@@ -107,6 +175,17 @@ pub struct GrepOptions<'env> {
 	pub max_columns:    Option<u32>,
 	/// Output mode (content, filesWithMatches, or count).
 	pub mode:           Option<String>,
+	/// Only search the first N bytes of larger files (default: no limit).
+	/// Truncated files are listed in `skippedFiles`.
+	#[napi(js_name = "maxFileBytes")]
+	pub max_file_bytes: Option<f64>,
+	/// Skip files that cannot match using the persistent trigram index
+	/// (default: false).
+	pub index:          Option<bool>,
+	/// Search the decompressed content of `.gz`, `.zst`, `.bz2` and `.xz`
+	/// files and the members of tar and zip archives (default: false).
+	#[napi(js_name = "searchCompressed")]
+	pub decompress:     Option<bool>,
 	/// Abort signal for cancelling the operation.
 	pub signal:         Option<Unknown<'env>>,
 	/// Timeout in milliseconds for the operation.
@@ -124,6 +203,27 @@ pub struct ContextLine {
 	pub line:        String,
 }
 
+/// One regex match within a matched line.
+///
+/// Offsets count UTF-16 code units from the start of `line`, so they can be
+/// passed straight to `String.prototype.slice`.
+#[derive(Clone)]
+#[napi(object)]
+pub struct Submatch {
+	/// Start offset (inclusive).
+	pub start:   u32,
+	/// End offset (exclusive).
+	pub end:     u32,
+	/// The matched text.
+	pub text:    String,
+	/// Capture groups in pattern order (group 1 first); `null` for groups that
+	/// did not participate. Omitted when the pattern has no groups.
+	pub groups:  Option<Vec<Option<String>>>,
+	/// Index of the pattern that matched when several were given, counting
+	/// `pattern`, then `patterns`, then the lines of `patternFile`.
+	pub pattern: Option<u32>,
+}
+
 /// A single match in the content.
 #[napi(object)]
 pub struct Match {
@@ -140,6 +240,9 @@ pub struct Match {
 	pub context_after:  Option<Vec<ContextLine>>,
 	/// Whether the line was truncated.
 	pub truncated:      Option<bool>,
+	/// Every match on the line (those within the truncated prefix, if
+	/// truncated).
+	pub submatches:     Option<Vec<Submatch>>,
 }
 
 /// Result of searching content.
@@ -153,6 +256,8 @@ pub struct SearchResult {
 	/// Whether the limit was reached.
 	#[napi(js_name = "limitReached")]
 	pub limit_reached: bool,
+	/// Encoding the content was decoded from, when not UTF-8.
+	pub encoding:      Option<String>,
 	/// Error message, if any.
 	pub error:         Option<String>,
 }
@@ -176,11 +281,35 @@ pub struct GrepMatch {
 	pub context_after:  Option<Vec<ContextLine>>,
 	/// Whether the line was truncated.
 	pub truncated:      Option<bool>,
+	/// Every match on the line (content mode only).
+	pub submatches:     Option<Vec<Submatch>>,
+	/// Path of the archive member containing the match, when `path` is an
+	/// archive.
+	pub member:         Option<String>,
+	/// Encoding the file (or member) was decoded from, when not UTF-8.
+	pub encoding:       Option<String>,
 	/// Per-file match count (count mode only).
 	#[napi(js_name = "matchCount")]
 	pub match_count:    Option<u32>,
 }
 
+/// A file that was not searched in full.
+#[derive(Clone)]
+#[napi(object)]
+pub struct GrepSkippedFile {
+	/// File path (relative for directory searches).
+	pub path:    String,
+	/// Archive member, when only that member was affected.
+	pub member:  Option<String>,
+	/// `truncated` when only the first `maxFileBytes` were searched,
+	/// `binary` when the search stopped at a NUL byte, `tooLarge` when
+	/// `replace()` left a file over `maxFileBytes` unchanged, or `unreadable`
+	/// when the file could not be read.
+	pub reason:  String,
+	/// Detail such as the file size or the read error.
+	pub message: Option<String>,
+}
+
 /// Result of searching files.
 #[napi(object)]
 pub struct GrepResult {
@@ -198,30 +327,13 @@ pub struct GrepResult {
 	/// Whether the limit/offset stopped the search early.
 	#[napi(js_name = "limitReached")]
 	pub limit_reached:      Option<bool>,
+	/// Files that were truncated, binary or could not be read.
+	#[napi(js_name = "skippedFiles")]
+	pub skipped_files:      Option<Vec<GrepSkippedFile>>,
 }
 
-enum TypeFilter {
-	Known { exts: &'static [&'static str], names: &'static [&'static str] },
-	Custom(String),
-}
-
-impl TypeFilter {
-	fn match_ext(&self, ext: &str) -> bool {
-		match self {
-			Self::Known { exts, .. } => exts.iter().any(|e| ext.eq_ignore_ascii_case(e)),
-			Self::Custom(custom_ext) => ext.eq_ignore_ascii_case(custom_ext),
-		}
-	}
-
-	fn match_name(&self, name: &str) -> bool {
-		match self {
-			Self::Known { names, .. } => names.iter().any(|n| name.eq_ignore_ascii_case(n)),
-			Self::Custom(ext) => ext.eq_ignore_ascii_case(name),
-		}
-	}
-}
-
-struct MatchCollector {
+struct MatchCollector<'m> {
+	matcher:         &'m PatternMatcher,
 	matches:         Vec<CollectedMatch>,
 	match_count:     u64,
 	collected_count: u64,
@@ -232,6 +344,8 @@ struct MatchCollector {
 	context_before:  SmallVec<[ContextLine; 8]>,
 	max_columns:     Option<usize>,
 	collect_matches: bool,
+	/// Where binary data stopped the search.
+	binary_offset:   Option<u64>,
 }
 
 struct CollectedMatch {
@@ -240,6 +354,11 @@ struct CollectedMatch {
 	context_before: SmallVec<[ContextLine; 8]>,
 	context_after:  SmallVec<[ContextLine; 8]>,
 	truncated:      bool,
+	submatches:     Vec<Submatch>,
+	/// Archive member the match was found in.
+	member:         Option<String>,
+	/// Encoding the match was decoded from, when not UTF-8.
+	encoding:       Option<&'static str>,
 }
 
 struct SearchResultInternal {
@@ -247,6 +366,8 @@ struct SearchResultInternal {
 	match_count:   u64,
 	collected:     u64,
 	limit_reached: bool,
+	/// Offset of the NUL byte that stopped the search as binary.
+	binary_offset: Option<u64>,
 }
 
 struct FileEntry {
@@ -258,16 +379,20 @@ struct FileSearchResult {
 	relative_path: String,
 	matches:       Vec<CollectedMatch>,
 	match_count:   u64,
+	searched:      bool,
+	skipped:       Vec<GrepSkippedFile>,
 }
 
-impl MatchCollector {
+impl<'m> MatchCollector<'m> {
 	fn new(
+		matcher: &'m PatternMatcher,
 		max_count: Option<u64>,
 		offset: u64,
 		max_columns: Option<usize>,
 		collect_matches: bool,
 	) -> Self {
 		Self {
+			matcher,
 			matches: Vec::new(),
 			match_count: 0,
 			collected_count: 0,
@@ -278,6 +403,7 @@ impl MatchCollector {
 			context_before: SmallVec::new(),
 			max_columns,
 			collect_matches,
+			binary_offset: None,
 		}
 	}
 
@@ -292,6 +418,62 @@ impl MatchCollector {
 			_ => (line.to_string(), false),
 		}
 	}
+
+	/// Find every match in `line`, keeping those that end within the first
+	/// `visible` bytes (the part left after truncation).
+	fn submatches(&self, line: &str, visible: usize) -> io::Result<Vec<Submatch>> {
+		if self.matcher.invert {
+			return Ok(Vec::new());
+		}
+		let matcher = &self.matcher.matcher;
+		let group_count = matcher.capture_count().saturating_sub(1);
+		let mut caps = matcher.new_captures().map_err(io::Error::other)?;
+		let mut offsets = Utf16Offsets::new(line);
+		let mut submatches = Vec::new();
+		matcher
+			.captures_iter(line.as_bytes(), &mut caps, |caps| {
+				let Some(whole) = caps.get(0) else {
+					return true;
+				};
+				if whole.end() > visible {
+					return false;
+				}
+				let groups = (group_count > 0).then(|| {
+					(1..=group_count)
+						.map(|index| caps.get(index).map(|group| line[group].to_string()))
+						.collect()
+				});
+				submatches.push(Submatch {
+					start: offsets.at(whole.start()),
+					end: offsets.at(whole.end()),
+					text: line[whole].to_string(),
+					groups,
+					pattern: self.matcher.pattern_index(line, whole),
+				});
+				true
+			})
+			.map_err(io::Error::other)?;
+		Ok(submatches)
+	}
+}
+
+/// Converts increasing byte offsets in a string to UTF-16 offsets.
+struct Utf16Offsets<'a> {
+	text:  &'a str,
+	byte:  usize,
+	utf16: usize,
+}
+
+impl<'a> Utf16Offsets<'a> {
+	const fn new(text: &'a str) -> Self {
+		Self { text, byte: 0, utf16: 0 }
+	}
+
+	fn at(&mut self, byte: usize) -> u32 {
+		self.utf16 += self.text[self.byte..byte].encode_utf16().count();
+		self.byte = byte;
+		clamp_u32(self.utf16 as u64)
+	}
 }
 
 fn bytes_to_trimmed_string(bytes: &[u8]) -> String {
@@ -301,7 +483,7 @@ fn bytes_to_trimmed_string(bytes: &[u8]) -> String {
 	}
 }
 
-impl Sink for MatchCollector {
+impl Sink for MatchCollector<'_> {
 	type Error = io::Error;
 
 	fn matched(
@@ -327,6 +509,12 @@ impl Sink for MatchCollector {
 			let raw_line = bytes_to_trimmed_string(mat.bytes());
 			let (line, truncated) = self.truncate_line(&raw_line);
 			let line_number = mat.line_number().unwrap_or(0);
+			let visible = if truncated {
+				line.len() - 3
+			} else {
+				line.len()
+			};
+			let submatches = self.submatches(&raw_line, visible)?;
 
 			self.matches.push(CollectedMatch {
 				line_number,
@@ -334,6 +522,9 @@ impl Sink for MatchCollector {
 				context_before: std::mem::take(&mut self.context_before),
 				context_after: SmallVec::new(),
 				truncated,
+				submatches,
+				member: None,
+				encoding: None,
 			});
 		} else {
 			self.context_before.clear();
@@ -382,6 +573,15 @@ impl Sink for MatchCollector {
 
 		Ok(true)
 	}
+
+	fn finish(
+		&mut self,
+		_searcher: &Searcher,
+		finish: &SinkFinish,
+	) -> std::result::Result<(), Self::Error> {
+		self.binary_offset = finish.binary_byte_offset();
+		Ok(())
+	}
 }
 
 fn clamp_u32(value: u64) -> u32 {
@@ -429,63 +629,6 @@ fn compile_glob(glob: Option<&str>) -> Result<Option<GlobSet>> {
 		.map_err(|err| Error::from_reason(format!("Failed to build glob matcher: {err}")))
 }
 
-fn resolve_type_filter(type_name: Option<&str>) -> Option<TypeFilter> {
-	let normalized = type_name
-		.map(str::trim)
-		.filter(|value| !value.is_empty())
-		.map(|value| value.trim_start_matches('.').to_lowercase())?;
-
-	let (exts, names): (&[&str], &[&str]) = match normalized.as_str() {
-		"js" | "javascript" => (&["js", "jsx", "mjs", "cjs"], &[]),
-		"ts" | "typescript" => (&["ts", "tsx", "mts", "cts"], &[]),
-		"json" => (&["json", "jsonc", "json5"], &[]),
-		"yaml" | "yml" => (&["yaml", "yml"], &[]),
-		"toml" => (&["toml"], &[]),
-		"md" | "markdown" => (&["md", "markdown", "mdx"], &[]),
-		"py" | "python" => (&["py", "pyi"], &[]),
-		"rs" | "rust" => (&["rs"], &[]),
-		"go" => (&["go"], &[]),
-		"java" => (&["java"], &[]),
-		"kt" | "kotlin" => (&["kt", "kts"], &[]),
-		"c" => (&["c", "h"], &[]),
-		"cpp" | "cxx" => (&["cpp", "cc", "cxx", "hpp", "hxx", "hh"], &[]),
-		"cs" | "csharp" => (&["cs", "csx"], &[]),
-		"php" => (&["php", "phtml"], &[]),
-		"rb" | "ruby" => (&["rb", "rake", "gemspec"], &[]),
-		"sh" | "bash" => (&["sh", "bash", "zsh"], &[]),
-		"zsh" => (&["zsh"], &[]),
-		"fish" => (&["fish"], &[]),
-		"html" => (&["html", "htm"], &[]),
-		"css" => (&["css"], &[]),
-		"scss" => (&["scss"], &[]),
-		"sass" => (&["sass"], &[]),
-		"less" => (&["less"], &[]),
-		"xml" => (&["xml"], &[]),
-		"docker" | "dockerfile" => (&[], &["dockerfile"]),
-		"make" | "makefile" => (&[], &["makefile"]),
-		_ => {
-			return Some(TypeFilter::Custom(normalized));
-		},
-	};
-
-	Some(TypeFilter::Known { exts, names })
-}
-
-fn matches_type_filter(path: &Path, filter: &TypeFilter) -> bool {
-	let base_name = path
-		.file_name()
-		.and_then(|name| name.to_str())
-		.unwrap_or("");
-	if filter.match_name(base_name) {
-		return true;
-	}
-	let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-	if ext.is_empty() {
-		return false;
-	}
-	filter.match_ext(ext)
-}
-
 fn resolve_context(
 	context: Option<u32>,
 	context_before: Option<u32>,
@@ -499,12 +642,25 @@ fn resolve_context(
 	}
 }
 
-fn build_searcher(before_context: u32, after_context: u32) -> Searcher {
+fn build_searcher(
+	before_context: u32,
+	after_context: u32,
+	invert_match: bool,
+	encoding: Option<&'static Encoding>,
+) -> Searcher {
+	// SAFETY: as in ripgrep, a file truncated while mapped may fault; files are
+	// not expected to shrink mid-search.
+	let mmap = unsafe { MmapChoice::auto() };
+	// Labels from `Encoding::name()` always resolve.
+	let encoding = encoding.and_then(|encoding| grep_searcher::Encoding::new(encoding.name()).ok());
 	SearcherBuilder::new()
+		.encoding(encoding)
 		.binary_detection(BinaryDetection::quit(b'\x00'))
 		.line_number(true)
 		.before_context(before_context as usize)
 		.after_context(after_context as usize)
+		.invert_match(invert_match)
+		.memory_map(mmap)
 		.build()
 }
 
@@ -516,10 +672,38 @@ struct SearchParams {
 	mode:           OutputMode,
 	max_count:      Option<u64>,
 	offset:         u64,
+	/// Explicit encoding; `None` sniffs for a byte-order mark.
+	encoding:       Option<&'static Encoding>,
+}
+
+/// Resolve the `encoding` option; `None` and `auto` mean BOM sniffing.
+fn parse_encoding(label: Option<&str>) -> Result<Option<&'static Encoding>> {
+	match label {
+		None | Some("auto") => Ok(None),
+		Some(label) => Encoding::for_label_no_replacement(label.as_bytes())
+			.map(Some)
+			.ok_or_else(|| Error::from_reason(format!("Unknown encoding: {label}"))),
+	}
+}
+
+/// Name of the encoding that data starting with `head` is decoded from, or
+/// `None` for UTF-8. As in the searcher, a byte-order mark overrides
+/// `encoding`.
+fn decoded_encoding(head: &[u8], encoding: Option<&'static Encoding>) -> Option<&'static str> {
+	let encoding = Encoding::for_bom(head).map_or(encoding, |(bom, _)| Some(bom))?;
+	(encoding != encoding_rs::UTF_8).then(|| encoding.name())
+}
+
+fn tag_encoding(search: &mut SearchResultInternal, encoding: Option<&'static str>) {
+	if encoding.is_some() {
+		for matched in &mut search.matches {
+			matched.encoding = encoding;
+		}
+	}
 }
 
 fn run_search(
-	matcher: &grep_regex::RegexMatcher,
+	matcher: &PatternMatcher,
 	content: &[u8],
 	params: SearchParams,
 ) -> io::Result<SearchResultInternal> {
@@ -528,9 +712,221 @@ fn run_search(
 
 /// Stream-based search that reads directly from a `Read` without buffering.
 fn run_search_reader<R: Read>(
-	matcher: &grep_regex::RegexMatcher,
+	matcher: &PatternMatcher,
 	reader: R,
 	params: SearchParams,
+) -> io::Result<SearchResultInternal> {
+	run_search_with(matcher, params, |searcher, collector| {
+		searcher.search_reader(&matcher.matcher, reader, collector)
+	})
+}
+
+/// How files on disk are read.
+#[derive(Clone, Copy)]
+struct FileOptions {
+	/// Search at most this many bytes of each file (decompressed bytes for
+	/// compressed files and archive members).
+	max_bytes:  Option<u64>,
+	/// Decode compressed files and archives (see [`archive`]).
+	decompress: bool,
+}
+
+fn skipped_file(
+	display_path: &str,
+	member: Option<&str>,
+	reason: &str,
+	message: String,
+) -> GrepSkippedFile {
+	GrepSkippedFile {
+		path:    display_path.to_string(),
+		member:  member.map(str::to_string),
+		reason:  reason.to_string(),
+		message: Some(message),
+	}
+}
+
+/// Report a search that stopped at binary data. Matches before it are kept,
+/// but a file without any counts as not searched.
+fn skipped_binary(
+	display_path: &str,
+	member: Option<&str>,
+	search: &SearchResultInternal,
+) -> Option<GrepSkippedFile> {
+	let offset = search.binary_offset?;
+	let message = format!("binary data at byte {offset}");
+	Some(skipped_file(display_path, member, "binary", message))
+}
+
+/// Whether `search` counts as searched: binary data did not cut it short
+/// before any match.
+const fn searched_text(search: &SearchResultInternal) -> bool {
+	search.binary_offset.is_none() || search.match_count > 0
+}
+
+/// Search a file on disk, memory-mapped when worthwhile. Files larger than
+/// `max_bytes` are searched up to that size and reported as truncated.
+///
+/// Returns `None` when nothing could be searched, or binary data stopped the
+/// search before any match.
+fn run_search_file(
+	matcher: &PatternMatcher,
+	path: &Path,
+	display_path: &str,
+	params: SearchParams,
+	options: FileOptions,
+	ct: &task::CancelToken,
+) -> (Option<SearchResultInternal>, Vec<GrepSkippedFile>) {
+	if options.decompress
+		&& let Some(format) = archive::Format::detect(path)
+	{
+		return run_search_decoded(
+			matcher,
+			path,
+			display_path,
+			format,
+			params,
+			options.max_bytes,
+			ct,
+		);
+	}
+	let max_bytes = options.max_bytes;
+	let file = match File::open(path) {
+		Ok(file) => file,
+		Err(err) => {
+			return (None, vec![skipped_file(display_path, None, "unreadable", err.to_string())]);
+		},
+	};
+	let size = file.metadata().map_or(0, |metadata| metadata.len());
+
+	let (search, truncated) = match max_bytes {
+		Some(max) if size > max => (run_search_reader(matcher, (&file).take(max), params), true),
+		_ => (
+			run_search_with(matcher, params, |searcher, collector| {
+				searcher.search_file(&matcher.matcher, &file, collector)
+			}),
+			false,
+		),
+	};
+	match search {
+		Ok(mut search) => {
+			if !search.matches.is_empty() {
+				tag_encoding(&mut search, file_encoding(&file, params.encoding));
+			}
+			let truncated = truncated.then(|| {
+				let message = format!("searched {} of {size} bytes", max_bytes.unwrap_or(0));
+				skipped_file(display_path, None, "truncated", message)
+			});
+			let skipped = truncated
+				.into_iter()
+				.chain(skipped_binary(display_path, None, &search))
+				.collect();
+			(searched_text(&search).then_some(search), skipped)
+		},
+		Err(err) => (None, vec![skipped_file(display_path, None, "unreadable", err.to_string())]),
+	}
+}
+
+/// Encoding `file` is decoded from, judging by its first bytes.
+fn file_encoding(mut file: &File, encoding: Option<&'static Encoding>) -> Option<&'static str> {
+	let mut head = [0; 3];
+	let len = file
+		.rewind()
+		.and_then(|()| file.read(&mut head))
+		.unwrap_or(0);
+	decoded_encoding(&head[..len], encoding)
+}
+
+/// Search the decoded content of a compressed file, or each member of an
+/// archive in turn, with `params` limits applied across members. Every
+/// stream is capped at `max_bytes` decompressed bytes, and the whole file at
+/// `MAX_ARCHIVE_DECODED_BYTES`. Cancellation stops the search between reads;
+/// the caller then reports it.
+fn run_search_decoded(
+	matcher: &PatternMatcher,
+	path: &Path,
+	display_path: &str,
+	format: archive::Format,
+	params: SearchParams,
+	max_bytes: Option<u64>,
+	ct: &task::CancelToken,
+) -> (Option<SearchResultInternal>, Vec<GrepSkippedFile>) {
+	let cap = max_bytes.unwrap_or(archive::MAX_DECODED_BYTES);
+	let budget = archive::Budget::new(ct);
+	let mut total = SearchResultInternal {
+		matches:       Vec::new(),
+		match_count:   0,
+		collected:     0,
+		limit_reached: false,
+		binary_offset: None,
+	};
+	let mut searched = false;
+	let mut skipped = Vec::new();
+
+	let listed = archive::for_each_stream(path, format, &budget, |member, reader| {
+		let stream_params = SearchParams {
+			max_count: params
+				.max_count
+				.map(|max| max.saturating_sub(total.collected)),
+			offset: params.offset.saturating_sub(total.match_count),
+			..params
+		};
+		if stream_params.max_count == Some(0) {
+			total.limit_reached = true;
+			return false;
+		}
+		let search = reader.and_then(|reader| {
+			let mut limited = reader.take(cap);
+			let mut buffered = BufReader::new(&mut limited);
+			let encoding = decoded_encoding(buffered.fill_buf()?, params.encoding);
+			let mut search = run_search_reader(matcher, buffered, stream_params)?;
+			tag_encoding(&mut search, encoding);
+			let truncated = limited.limit() == 0 && limited.into_inner().read(&mut [0])? > 0;
+			Ok((search, truncated))
+		});
+		match search {
+			Ok((search, truncated)) => {
+				searched |= searched_text(&search);
+				skipped.extend(skipped_binary(display_path, member, &search));
+				if truncated {
+					let message = format!("searched the first {cap} decompressed bytes");
+					skipped.push(skipped_file(display_path, member, "truncated", message));
+				}
+				total.match_count += search.match_count;
+				total.collected += search.collected;
+				total.limit_reached |= search.limit_reached;
+				total.matches.extend(
+					search
+						.matches
+						.into_iter()
+						.map(|matched| CollectedMatch { member: member.map(str::to_string), ..matched }),
+				);
+				!search.limit_reached
+			},
+			Err(_) if budget.exhausted() || ct.heartbeat().is_err() => false,
+			Err(err) => {
+				skipped.push(skipped_file(display_path, member, "unreadable", err.to_string()));
+				true
+			},
+		}
+	});
+	if budget.exhausted() {
+		let message = format!(
+			"searched the first {} decompressed bytes of the file",
+			archive::MAX_ARCHIVE_DECODED_BYTES
+		);
+		skipped.push(skipped_file(display_path, None, "truncated", message));
+	} else if let Err(err) = listed
+		&& ct.heartbeat().is_ok()
+	{
+		skipped.push(skipped_file(display_path, None, "unreadable", err.to_string()));
+	}
+	(searched.then_some(total), skipped)
+}
+
+fn run_search_with(
+	matcher: &PatternMatcher,
+	params: SearchParams,
+	search: impl FnOnce(&mut Searcher, &mut MatchCollector<'_>) -> io::Result<()>,
 ) -> io::Result<SearchResultInternal> {
 	let mut searcher = build_searcher(
 		if params.mode == OutputMode::Content {
@@ -543,19 +939,23 @@ fn run_search_reader<R: Read>(
 		} else {
 			0
 		},
+		matcher.invert,
+		params.encoding,
 	);
 	let mut collector = MatchCollector::new(
+		matcher,
 		params.max_count,
 		params.offset,
 		params.max_columns.map(|v| v as usize),
 		params.mode == OutputMode::Content,
 	);
-	searcher.search_reader(matcher, reader, &mut collector)?;
+	search(&mut searcher, &mut collector)?;
 	Ok(SearchResultInternal {
 		matches:       collector.matches,
 		match_count:   collector.match_count,
 		collected:     collector.collected_count,
 		limit_reached: collector.limit_reached,
+		binary_offset: collector.binary_offset,
 	})
 }
 
@@ -576,6 +976,7 @@ fn to_public_match(matched: CollectedMatch) -> Match {
 		context_before,
 		context_after,
 		truncated: if matched.truncated { Some(true) } else { None },
+		submatches: Some(matched.submatches),
 	}
 }
 
@@ -597,22 +998,26 @@ fn to_grep_match(path: &str, matched: CollectedMatch) -> GrepMatch {
 		context_before,
 		context_after,
 		truncated: if matched.truncated { Some(true) } else { None },
+		submatches: Some(matched.submatches),
+		member: matched.member,
+		encoding: matched.encoding.map(str::to_string),
 		match_count: None,
 	}
 }
 
 const fn empty_search_result(error: Option<String>) -> SearchResult {
-	SearchResult { matches: Vec::new(), match_count: 0, limit_reached: false, error }
+	SearchResult { matches: Vec::new(), match_count: 0, limit_reached: false, encoding: None, error }
 }
 
 /// Internal configuration for grep, extracted from options.
 struct GrepConfig {
-	pattern:        String,
+	patterns:       PatternConfig,
+	encoding:       Option<String>,
 	path:           String,
 	glob:           Option<String>,
 	type_filter:    Option<String>,
-	ignore_case:    Option<bool>,
-	multiline:      Option<bool>,
+	type_add:       Option<Vec<String>>,
+	type_not:       Option<Vec<String>>,
 	hidden:         Option<bool>,
 	cache:          Option<bool>,
 	max_count:      Option<u32>,
@@ -622,6 +1027,9 @@ struct GrepConfig {
 	context:        Option<u32>,
 	max_columns:    Option<u32>,
 	mode:           Option<String>,
+	max_file_bytes: Option<u64>,
+	index:          bool,
+	decompress:     bool,
 }
 
 fn collect_files(
@@ -642,7 +1050,7 @@ fn collect_files(
 		}
 		let path = root.join(&entry.path);
 		if let Some(filter) = type_filter
-			&& !matches_type_filter(&path, filter)
+			&& !filter.is_match(&path, false)
 		{
 			continue;
 		}
@@ -651,6 +1059,28 @@ fn collect_files(
 	entries
 }
 
+/// Scan a directory and collect the files passing the glob/type filters.
+fn scan_files(
+	root: &Path,
+	include_hidden: bool,
+	use_cache: bool,
+	glob_set: Option<&GlobSet>,
+	type_filter: Option<&TypeFilter>,
+	ct: &task::CancelToken,
+) -> Result<Vec<FileEntry>> {
+	if !use_cache {
+		let fresh = fs_cache::force_rescan(root, include_hidden, true, false, ct)?;
+		return Ok(collect_files(root, &fresh, glob_set, type_filter));
+	}
+	let scan = fs_cache::get_or_scan(root, include_hidden, true, ct)?;
+	let mut entries = collect_files(root, &scan.entries, glob_set, type_filter);
+	if entries.is_empty() && scan.cache_age_ms >= fs_cache::empty_recheck_ms() {
+		let fresh = fs_cache::force_rescan(root, include_hidden, true, true, ct)?;
+		entries = collect_files(root, &fresh, glob_set, type_filter);
+	}
+	Ok(entries)
+}
+
 fn build_matcher(
 	pattern: &str,
 	ignore_case: bool,
@@ -663,27 +1093,113 @@ fn build_matcher(
 		.map_err(|err| Error::from_reason(format!("Regex error: {err}")))
 }
 
+/// Pattern sources and matching flags shared by `search()` and `grep()`.
+struct PatternConfig {
+	pattern:       Option<String>,
+	patterns:      Option<Vec<String>>,
+	pattern_file:  Option<String>,
+	ignore_case:   bool,
+	smart_case:    bool,
+	multiline:     bool,
+	fixed_strings: bool,
+	word_regexp:   bool,
+	invert_match:  bool,
+	/// `engine` option, resolved when compiling.
+	engine:        Option<String>,
+}
+
+/// All patterns compiled into one matcher, plus one matcher per pattern to
+/// tell which of them produced a match.
+struct PatternMatcher {
+	matcher: EngineMatcher,
+	tags:    Vec<EngineMatcher>,
+	invert:  bool,
+}
+
+impl PatternConfig {
+	fn build(mut self) -> Result<PatternMatcher> {
+		let patterns = self.collect_patterns()?;
+		self.compile(&patterns)
+	}
+
+	/// Gather `pattern`, `patterns` and the lines of `patternFile`, in order.
+	fn collect_patterns(&mut self) -> Result<Vec<String>> {
+		let mut patterns: Vec<String> = self
+			.pattern
+			.take()
+			.into_iter()
+			.chain(self.patterns.take().into_iter().flatten())
+			.collect();
+		if let Some(path) = &self.pattern_file {
+			let contents = std::fs::read_to_string(path).map_err(|err| {
+				Error::from_reason(format!("Failed to read pattern file {path}: {err}"))
+			})?;
+			patterns.extend(contents.lines().map(str::to_string));
+		}
+		if patterns.is_empty() {
+			return Err(Error::from_reason("No pattern given"));
+		}
+		Ok(patterns)
+	}
+
+	fn compile(&self, patterns: &[String]) -> Result<PatternMatcher> {
+		let matcher = engine::compile(self, patterns, Engine::parse(self.engine.as_deref())?)?;
+		// Tag matchers use the engine the combined pattern ended up with.
+		let tags = if patterns.len() > 1 {
+			patterns
+				.iter()
+				.map(|pattern| engine::compile(self, std::slice::from_ref(pattern), matcher.engine()))
+				.collect::<Result<_>>()?
+		} else {
+			Vec::new()
+		};
+		Ok(PatternMatcher { matcher, tags, invert: self.invert_match })
+	}
+}
+
+impl PatternMatcher {
+	/// Index of the pattern behind the match at `found`: the first one that
+	/// matches the same span, as in the combined alternation.
+	fn pattern_index(&self, line: &str, found: grep_matcher::Match) -> Option<u32> {
+		let mut starts_here = None;
+		for (index, tag) in self.tags.iter().enumerate() {
+			let Ok(Some(candidate)) = tag.find_at(line.as_bytes(), found.start()) else {
+				continue;
+			};
+			if candidate.start() != found.start() {
+				continue;
+			}
+			if candidate.end() == found.end() {
+				return Some(clamp_u32(index as u64));
+			}
+			starts_here.get_or_insert_with(|| clamp_u32(index as u64));
+		}
+		starts_here
+	}
+}
+
 fn run_parallel_search(
 	entries: &[FileEntry],
-	matcher: &grep_regex::RegexMatcher,
-	context_before: u32,
-	context_after: u32,
-	max_columns: Option<u32>,
-	mode: OutputMode,
+	matcher: &PatternMatcher,
+	params: SearchParams,
+	file_options: FileOptions,
+	ct: &task::CancelToken,
 ) -> Vec<FileSearchResult> {
-	let params =
-		SearchParams { context_before, context_after, max_columns, mode, max_count: None, offset: 0 };
 	let mut results: Vec<FileSearchResult> = entries
 		.par_iter()
-		.filter_map(|entry| {
-			let file = File::open(&entry.path).ok()?;
-			let reader = file.take(MAX_FILE_BYTES);
-			let search = run_search_reader(matcher, reader, params).ok()?;
-			Some(FileSearchResult {
+		.map(|entry| {
+			let (search, skipped) =
+				run_search_file(matcher, &entry.path, &entry.relative_path, params, file_options, ct);
+			let searched = search.is_some();
+			let (matches, match_count) =
+				search.map_or_else(|| (Vec::new(), 0), |search| (search.matches, search.match_count));
+			FileSearchResult {
 				relative_path: entry.relative_path.clone(),
-				matches:       search.matches,
-				match_count:   search.match_count,
-			})
+				matches,
+				match_count,
+				searched,
+				skipped,
+			}
 		})
 		.collect();
 
@@ -693,8 +1209,11 @@ fn run_parallel_search(
 
 fn run_sequential_search(
 	entries: &[FileEntry],
-	matcher: &grep_regex::RegexMatcher,
+	matcher: &PatternMatcher,
 	params: SearchParams,
+	file_options: FileOptions,
+	skipped_files: &mut Vec<GrepSkippedFile>,
+	ct: &task::CancelToken,
 ) -> (Vec<GrepMatch>, u64, u32, u32, bool) {
 	let SearchParams { mode, max_count, offset, .. } = params;
 	let mut matches = Vec::new();
@@ -705,7 +1224,7 @@ fn run_sequential_search(
 	let mut limit_reached = false;
 
 	for entry in entries {
-		if limit_reached {
+		if limit_reached || ct.heartbeat().is_err() {
 			break;
 		}
 
@@ -718,17 +1237,15 @@ fn run_sequential_search(
 			break;
 		}
 
-		// Open file and search directly - no intermediate buffer, no precheck scan
-		let Ok(file) = File::open(&entry.path) else {
-			continue;
-		};
-		files_searched = files_searched.saturating_add(1);
-		let reader = file.take(MAX_FILE_BYTES);
-
+		// Search the file directly - no intermediate buffer, no precheck scan
 		let file_params = SearchParams { max_count: remaining, offset: file_offset, ..params };
-		let Ok(search) = run_search_reader(matcher, reader, file_params) else {
+		let (search, skipped) =
+			run_search_file(matcher, &entry.path, &entry.relative_path, file_params, file_options, ct);
+		skipped_files.extend(skipped);
+		let Some(search) = search else {
 			continue;
 		};
+		files_searched = files_searched.saturating_add(1);
 
 		if search.match_count == 0 {
 			continue;
@@ -752,6 +1269,9 @@ fn run_sequential_search(
 					context_before: None,
 					context_after:  None,
 					truncated:      None,
+					submatches:     None,
+					member:         None,
+					encoding:       None,
 					match_count:    Some(clamp_u32(search.match_count)),
 				});
 			},
@@ -766,10 +1286,20 @@ fn run_sequential_search(
 }
 
 fn search_sync(content: &[u8], options: SearchOptions) -> SearchResult {
-	let ignore_case = options.ignore_case.unwrap_or(false);
-	let multiline = options.multiline.unwrap_or(false);
 	let mode = parse_output_mode(options.mode.as_deref());
-	let matcher = match build_matcher(&options.pattern, ignore_case, multiline) {
+	let pattern_config = PatternConfig {
+		pattern:       options.pattern,
+		patterns:      options.patterns,
+		pattern_file:  options.pattern_file,
+		ignore_case:   options.ignore_case.unwrap_or(false),
+		smart_case:    options.smart_case.unwrap_or(false),
+		multiline:     options.multiline.unwrap_or(false),
+		fixed_strings: options.fixed_strings.unwrap_or(false),
+		word_regexp:   options.word_regexp.unwrap_or(false),
+		invert_match:  options.invert_match.unwrap_or(false),
+		engine:        options.engine,
+	};
+	let matcher = match pattern_config.build() {
 		Ok(matcher) => matcher,
 		Err(err) => return empty_search_result(Some(err.to_string())),
 	};
@@ -779,8 +1309,19 @@ fn search_sync(content: &[u8], options: SearchOptions) -> SearchResult {
 	let max_columns = options.max_columns;
 	let max_count = options.max_count.map(u64::from);
 	let offset = options.offset.unwrap_or(0) as u64;
-	let params =
-		SearchParams { context_before, context_after, max_columns, mode, max_count, offset };
+	let encoding = match parse_encoding(options.encoding.as_deref()) {
+		Ok(encoding) => encoding,
+		Err(err) => return empty_search_result(Some(err.to_string())),
+	};
+	let params = SearchParams {
+		context_before,
+		context_after,
+		max_columns,
+		mode,
+		max_count,
+		offset,
+		encoding,
+	};
 
 	let result = match run_search(&matcher, content, params) {
 		Ok(result) => result,
@@ -791,22 +1332,36 @@ fn search_sync(content: &[u8], options: SearchOptions) -> SearchResult {
 		matches:       result.matches.into_iter().map(to_public_match).collect(),
 		match_count:   clamp_u32(result.match_count),
 		limit_reached: result.limit_reached,
+		encoding:      decoded_encoding(content, encoding).map(str::to_string),
 		error:         None,
 	}
 }
 
-fn grep_sync(
-	options: GrepConfig,
-	on_match: Option<&ThreadsafeFunction<GrepMatch>>,
-	ct: task::CancelToken,
-) -> Result<GrepResult> {
+/// Files to search, resolved from the search path.
+enum Candidates {
+	/// The search path is a file, displayed in full; `None` when the type
+	/// filter rejects it.
+	File(Option<FileEntry>),
+	/// Files under the search path that pass the filters.
+	Dir(Vec<FileEntry>),
+}
+
+/// Matcher, limits and candidate files shared by `grep()` and `grepJson()`.
+struct PreparedSearch {
+	matcher:      PatternMatcher,
+	params:       SearchParams,
+	file_options: FileOptions,
+	candidates:   Candidates,
+}
+
+fn prepare_search(options: GrepConfig, ct: &task::CancelToken) -> Result<PreparedSearch> {
 	let search_path = resolve_search_path(&options.path)?;
 	let metadata = std::fs::metadata(&search_path)
 		.map_err(|err| Error::from_reason(format!("Path not found: {err}")))?;
-	let ignore_case = options.ignore_case.unwrap_or(false);
-	let multiline = options.multiline.unwrap_or(false);
 	let output_mode = parse_output_mode(options.mode.as_deref());
-	let matcher = build_matcher(&options.pattern, ignore_case, multiline)?;
+	let mut pattern_config = options.patterns;
+	let patterns = pattern_config.collect_patterns()?;
+	let matcher = pattern_config.compile(&patterns)?;
 
 	let (context_before, context_after) =
 		resolve_context(options.context, options.context_before, options.context_after);
@@ -815,104 +1370,148 @@ fn grep_sync(
 	} else {
 		(0, 0)
 	};
-	let max_columns = options.max_columns;
-	let max_count = options.max_count.map(u64::from);
-	let offset = options.offset.unwrap_or(0) as u64;
 	let include_hidden = options.hidden.unwrap_or(true);
 	let use_cache = options.cache.unwrap_or(false);
 	let glob_set = compile_glob(options.glob.as_deref())?;
-	let type_filter = resolve_type_filter(options.type_filter.as_deref());
+	let type_filter = TypeFilter::new(
+		options.type_filter.as_deref(),
+		options.type_add.as_deref(),
+		options.type_not.as_deref(),
+	)?;
+	let encoding = parse_encoding(options.encoding.as_deref())?;
+	let params = SearchParams {
+		context_before,
+		context_after,
+		max_columns: options.max_columns,
+		mode: output_mode,
+		max_count: options.max_count.map(u64::from),
+		offset: options.offset.unwrap_or(0) as u64,
+		encoding,
+	};
+	let file_options =
+		FileOptions { max_bytes: options.max_file_bytes, decompress: options.decompress };
 
 	if metadata.is_file() {
-		if let Some(filter) = type_filter.as_ref()
-			&& !matches_type_filter(&search_path, filter)
-		{
-			return Ok(GrepResult {
-				matches:            Vec::new(),
-				total_matches:      0,
-				files_with_matches: 0,
-				files_searched:     0,
-				limit_reached:      None,
+		let file = type_filter
+			.as_ref()
+			.is_none_or(|filter| filter.is_match(&search_path, false))
+			.then(|| FileEntry {
+				relative_path: search_path.to_string_lossy().to_string(),
+				path:          search_path,
 			});
-		}
+		return Ok(PreparedSearch {
+			matcher,
+			params,
+			file_options,
+			candidates: Candidates::File(file),
+		});
+	}
 
-		let Ok(file) = File::open(&search_path) else {
-			return Ok(GrepResult {
-				matches:            Vec::new(),
-				total_matches:      0,
-				files_with_matches: 0,
-				files_searched:     0,
-				limit_reached:      None,
-			});
-		};
-		let reader = file.take(MAX_FILE_BYTES);
+	let entries = scan_files(
+		&search_path,
+		include_hidden,
+		use_cache,
+		glob_set.as_ref(),
+		type_filter.as_ref(),
+		ct,
+	)?;
+	// Content transcoded from another encoding does not match the indexed
+	// bytes.
+	let use_index = options.index && encoding.is_none_or(|encoding| encoding == encoding_rs::UTF_8);
+	let entries = match use_index.then(|| trigram::Query::plan(&pattern_config, &patterns)) {
+		Some(Some(query)) => {
+			// Decoded content does not match the bytes on disk.
+			let always_search =
+				|path: &Path| file_options.decompress && archive::Format::detect(path).is_some();
+			trigram::filter_candidates(&search_path, entries, &query, always_search, ct)?
+		},
+		_ => entries,
+	};
+	Ok(PreparedSearch { matcher, params, file_options, candidates: Candidates::Dir(entries) })
+}
 
-		let params = SearchParams {
-			context_before,
-			context_after,
-			max_columns,
-			mode: output_mode,
-			max_count,
-			offset,
-		};
-		let search = run_search_reader(&matcher, reader, params)
-			.map_err(|err| Error::from_reason(format!("Search failed: {err}")))?;
+fn grep_sync(
+	options: GrepConfig,
+	on_match: Option<&ThreadsafeFunction<GrepMatch>>,
+	ct: task::CancelToken,
+) -> Result<GrepResult> {
+	let PreparedSearch { matcher, params, file_options, candidates } = prepare_search(options, &ct)?;
+	let SearchParams { mode: output_mode, max_count, offset, .. } = params;
 
-		if search.match_count == 0 {
+	let entries = match candidates {
+		Candidates::File(None) => {
 			return Ok(GrepResult {
 				matches:            Vec::new(),
 				total_matches:      0,
 				files_with_matches: 0,
-				files_searched:     1,
+				files_searched:     0,
 				limit_reached:      None,
+				skipped_files:      None,
 			});
-		}
+		},
+		Candidates::File(Some(file)) => {
+			let (search, skipped) =
+				run_search_file(&matcher, &file.path, &file.relative_path, params, file_options, &ct);
+			ct.heartbeat()?;
+			let skipped_files = non_empty(skipped);
+			let Some(search) = search else {
+				return Ok(GrepResult {
+					matches: Vec::new(),
+					total_matches: 0,
+					files_with_matches: 0,
+					files_searched: 0,
+					limit_reached: None,
+					skipped_files,
+				});
+			};
 
-		let path_string = search_path.to_string_lossy().to_string();
-		let mut matches = Vec::new();
-		match output_mode {
-			OutputMode::Content => {
-				for matched in search.matches {
-					matches.push(to_grep_match(&path_string, matched));
-				}
-			},
-			OutputMode::Count => {
-				matches.push(GrepMatch {
-					path:           path_string,
-					line_number:    0,
-					line:           String::new(),
-					context_before: None,
-					context_after:  None,
-					truncated:      None,
-					match_count:    Some(clamp_u32(search.match_count)),
+			if search.match_count == 0 {
+				return Ok(GrepResult {
+					matches: Vec::new(),
+					total_matches: 0,
+					files_with_matches: 0,
+					files_searched: 1,
+					limit_reached: None,
+					skipped_files,
 				});
-			},
-		}
+			}
 
-		let limit_reached =
-			search.limit_reached || max_count.is_some_and(|max| search.collected >= max);
+			let mut matches = Vec::new();
+			match output_mode {
+				OutputMode::Content => {
+					for matched in search.matches {
+						matches.push(to_grep_match(&file.relative_path, matched));
+					}
+				},
+				OutputMode::Count => {
+					matches.push(GrepMatch {
+						path:           file.relative_path,
+						line_number:    0,
+						line:           String::new(),
+						context_before: None,
+						context_after:  None,
+						truncated:      None,
+						submatches:     None,
+						member:         None,
+						encoding:       None,
+						match_count:    Some(clamp_u32(search.match_count)),
+					});
+				},
+			}
 
-		return Ok(GrepResult {
-			matches,
-			total_matches: clamp_u32(search.match_count),
-			files_with_matches: 1,
-			files_searched: 1,
-			limit_reached: if limit_reached { Some(true) } else { None },
-		});
-	}
+			let limit_reached =
+				search.limit_reached || max_count.is_some_and(|max| search.collected >= max);
 
-	let entries = if use_cache {
-		let scan = fs_cache::get_or_scan(&search_path, include_hidden, true, &ct)?;
-		let mut entries =
-			collect_files(&search_path, &scan.entries, glob_set.as_ref(), type_filter.as_ref());
-		if entries.is_empty() && scan.cache_age_ms >= fs_cache::empty_recheck_ms() {
-			let fresh = fs_cache::force_rescan(&search_path, include_hidden, true, true, &ct)?;
-			entries = collect_files(&search_path, &fresh, glob_set.as_ref(), type_filter.as_ref());
-		}
-		entries
-	} else {
-		let fresh = fs_cache::force_rescan(&search_path, include_hidden, true, false, &ct)?;
-		collect_files(&search_path, &fresh, glob_set.as_ref(), type_filter.as_ref())
+			return Ok(GrepResult {
+				matches,
+				total_matches: clamp_u32(search.match_count),
+				files_with_matches: 1,
+				files_searched: 1,
+				limit_reached: if limit_reached { Some(true) } else { None },
+				skipped_files,
+			});
+		},
+		Candidates::Dir(entries) => entries,
 	};
 	// Check cancellation before heavy work
 	ct.heartbeat()?;
@@ -923,25 +1522,27 @@ fn grep_sync(
 			files_with_matches: 0,
 			files_searched:     0,
 			limit_reached:      None,
+			skipped_files:      None,
 		});
 	}
 
 	let allow_parallel = max_count.is_none() && offset == 0;
 	if allow_parallel {
-		let results = run_parallel_search(
-			&entries,
-			&matcher,
-			context_before,
-			context_after,
-			max_columns,
-			output_mode,
-		);
+		let results = run_parallel_search(&entries, &matcher, params, file_options, &ct);
+		ct.heartbeat()?;
 		let mut matches = Vec::new();
 		let mut total_matches = 0u64;
 		let mut files_with_matches = 0u32;
-		let files_searched = clamp_u32(results.len() as u64);
+		let mut files_searched = 0u32;
+		let mut skipped_files = Vec::new();
 
 		for result in results {
+			skipped_files.extend(result.skipped);
+			// Unreadable files were not searched at all.
+			if !result.searched {
+				continue;
+			}
+			files_searched = files_searched.saturating_add(1);
 			if result.match_count == 0 {
 				continue;
 			}
@@ -966,6 +1567,9 @@ fn grep_sync(
 						context_before: None,
 						context_after:  None,
 						truncated:      None,
+						submatches:     None,
+						member:         None,
+						encoding:       None,
 						match_count:    Some(clamp_u32(result.match_count)),
 					};
 					if let Some(callback) = on_match {
@@ -982,18 +1586,14 @@ fn grep_sync(
 			files_with_matches,
 			files_searched,
 			limit_reached: None,
+			skipped_files: non_empty(skipped_files),
 		});
 	}
 
+	let mut skipped_files = Vec::new();
 	let (matches, total_matches, files_with_matches, files_searched, limit_reached) =
-		run_sequential_search(&entries, &matcher, SearchParams {
-			context_before,
-			context_after,
-			max_columns,
-			mode: output_mode,
-			max_count,
-			offset,
-		});
+		run_sequential_search(&entries, &matcher, params, file_options, &mut skipped_files, &ct);
+	ct.heartbeat()?;
 
 	// Fire callbacks for sequential search results
 	if let Some(callback) = on_match {
@@ -1008,9 +1608,14 @@ fn grep_sync(
 		files_with_matches,
 		files_searched,
 		limit_reached: if limit_reached { Some(true) } else { None },
+		skipped_files: non_empty(skipped_files),
 	})
 }
 
+fn non_empty(skipped_files: Vec<GrepSkippedFile>) -> Option<Vec<GrepSkippedFile>> {
+	(!skipped_files.is_empty()).then_some(skipped_files)
+}
+
 /// Search content for a pattern (one-shot, compiles pattern each time).
 /// For repeated searches with the same pattern, use [`grep`] with file filters.
 ///
@@ -1095,13 +1700,29 @@ pub fn grep(
 		ThreadsafeFunction<GrepMatch>,
 	>,
 ) -> task::Async<GrepResult> {
+	let (config, ct) = grep_config(options);
+	task::blocking("grep", ct, move |ct| grep_sync(config, on_match.as_ref(), ct))
+}
+
+/// Split `grep()` options into the search config and its cancel token.
+fn grep_config(options: GrepOptions<'_>) -> (GrepConfig, task::CancelToken) {
 	let GrepOptions {
 		pattern,
+		patterns,
+		pattern_file,
 		path,
 		glob,
 		type_filter,
+		type_add,
+		type_not,
 		ignore_case,
 		multiline,
+		smart_case,
+		fixed_strings,
+		word_regexp,
+		invert_match,
+		engine,
+		encoding,
 		hidden,
 		cache,
 		max_count,
@@ -1111,17 +1732,32 @@ pub fn grep(
 		context,
 		max_columns,
 		mode,
+		max_file_bytes,
+		index,
+		decompress,
 		timeout_ms,
 		signal,
 	} = options;
 
 	let config = GrepConfig {
-		pattern,
+		patterns: PatternConfig {
+			pattern,
+			patterns,
+			pattern_file,
+			ignore_case: ignore_case.unwrap_or(false),
+			smart_case: smart_case.unwrap_or(false),
+			multiline: multiline.unwrap_or(false),
+			fixed_strings: fixed_strings.unwrap_or(false),
+			word_regexp: word_regexp.unwrap_or(false),
+			invert_match: invert_match.unwrap_or(false),
+			engine,
+		},
+		encoding,
 		path,
 		glob,
 		type_filter,
-		ignore_case,
-		multiline,
+		type_add,
+		type_not,
 		hidden,
 		cache,
 		max_count,
@@ -1131,8 +1767,10 @@ pub fn grep(
 		context,
 		max_columns,
 		mode,
+		max_file_bytes: max_file_bytes.map(|bytes| bytes.max(0.0) as u64),
+		index: index.unwrap_or(false),
+		decompress: decompress.unwrap_or(false),
 	};
 
-	let ct = task::CancelToken::new(timeout_ms, signal);
-	task::blocking("grep", ct, move |ct| grep_sync(config, on_match.as_ref(), ct))
+	(config, task::CancelToken::new(timeout_ms, signal))
 }
@@ -0,0 +1,200 @@
+//! Decoding readers for `grep({ searchCompressed: true })`.
+//!
+//! Formats are detected from the file name:
+//! - `.gz`, `.zst`, `.bz2` and `.xz` files are decompressed and searched as a
+//!   single stream.
+//! - `.tar` (optionally compressed: `.tar.gz`, `.tgz`, ...) and zip-based
+//!   archives (`.zip`, `.jar`, `.war`, `.whl`) are searched member by member.
+//!
+//! All decoders are pure Rust. Zip members are limited to the stored and
+//! deflate methods, which covers jars and wheels.
+//!
+//! Decoding is metered by a [`Budget`]: reads check for cancellation and stop
+//! once an archive has produced `MAX_ARCHIVE_DECODED_BYTES`, including the
+//! members that tar decodes only to skip them.
+
+use std::{
+	cell::Cell,
+	fs::File,
+	io::{self, BufReader, Read},
+	path::Path,
+};
+
+use crate::task;
+
+/// Decompressed bytes searched per stream or member when `maxFileBytes` is
+/// not set, so a compression bomb cannot stall a search.
+pub const MAX_DECODED_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Decompressed bytes read from one file across all of its members.
+pub const MAX_ARCHIVE_DECODED_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Decoded bytes left for one file, and the search's cancel token.
+pub struct Budget<'a> {
+	remaining: Cell<u64>,
+	exhausted: Cell<bool>,
+	ct:        &'a task::CancelToken,
+}
+
+impl<'a> Budget<'a> {
+	pub const fn new(ct: &'a task::CancelToken) -> Self {
+		Self { remaining: Cell::new(MAX_ARCHIVE_DECODED_BYTES), exhausted: Cell::new(false), ct }
+	}
+
+	/// Whether decoding stopped at `MAX_ARCHIVE_DECODED_BYTES`.
+	pub const fn exhausted(&self) -> bool {
+		self.exhausted.get()
+	}
+
+	fn check(&self) -> io::Result<()> {
+		self
+			.ct
+			.heartbeat()
+			.map_err(|err| io::Error::other(err.reason.clone()))
+	}
+}
+
+/// Reader charging its bytes to a [`Budget`]. It fails once the search is
+/// cancelled and ends early once the budget runs out.
+struct Metered<'b, 'a, R> {
+	inner:  R,
+	budget: &'b Budget<'a>,
+}
+
+impl<R: Read> Read for Metered<'_, '_, R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.budget.check()?;
+		let remaining = self.budget.remaining.get();
+		if remaining == 0 && !buf.is_empty() {
+			self.budget.exhausted.set(true);
+			return Ok(0);
+		}
+		let len = buf
+			.len()
+			.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+		let read = self.inner.read(&mut buf[..len])?;
+		self.budget.remaining.set(remaining - read as u64);
+		Ok(read)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+	Gzip,
+	Zstd,
+	Bzip2,
+	Xz,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	/// A single compressed stream.
+	Compressed(Codec),
+	/// A tar archive, optionally compressed as a whole.
+	Tar(Option<Codec>),
+	Zip,
+}
+
+impl Format {
+	/// Detect the format from the file name, or `None` for plain files.
+	pub fn detect(path: &Path) -> Option<Self> {
+		let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+		let (stem, ext) = name.rsplit_once('.')?;
+		let codec = match ext {
+			"gz" => Codec::Gzip,
+			"zst" => Codec::Zstd,
+			"bz2" => Codec::Bzip2,
+			"xz" => Codec::Xz,
+			"tgz" => return Some(Self::Tar(Some(Codec::Gzip))),
+			"tzst" => return Some(Self::Tar(Some(Codec::Zstd))),
+			"tbz" | "tbz2" => return Some(Self::Tar(Some(Codec::Bzip2))),
+			"txz" => return Some(Self::Tar(Some(Codec::Xz))),
+			"tar" => return Some(Self::Tar(None)),
+			"zip" | "jar" | "war" | "whl" => return Some(Self::Zip),
+			_ => return None,
+		};
+		if stem
+			.rsplit_once('.')
+			.is_some_and(|(_, inner)| inner == "tar")
+		{
+			Some(Self::Tar(Some(codec)))
+		} else {
+			Some(Self::Compressed(codec))
+		}
+	}
+}
+
+fn decode<'a>(codec: Codec, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+	Ok(match codec {
+		Codec::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+		Codec::Zstd => {
+			Box::new(ruzstd::decoding::StreamingDecoder::new(reader).map_err(io::Error::other)?)
+		},
+		Codec::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+		Codec::Xz => Box::new(lzma_rust2::XzReader::new(BufReader::new(reader), true)),
+	})
+}
+
+/// Call `visit` with each stream to search in `path`: the decoded content of
+/// a compressed file (member `None`), or every regular file in an archive.
+///
+/// Members that cannot be opened are passed as errors. `visit` returns
+/// `false` to stop early. The returned error means the file or the archive
+/// listing could not be read, or the search was cancelled. Decoded bytes are
+/// charged to `budget`.
+pub fn for_each_stream(
+	path: &Path,
+	format: Format,
+	budget: &Budget<'_>,
+	mut visit: impl FnMut(Option<&str>, io::Result<&mut dyn Read>) -> bool,
+) -> io::Result<()> {
+	let file = File::open(path)?;
+	match format {
+		Format::Compressed(codec) => {
+			let mut reader = Metered { inner: decode(codec, file)?, budget };
+			visit(None, Ok(&mut reader));
+		},
+		Format::Tar(codec) => {
+			let reader: Box<dyn Read> = match codec {
+				Some(codec) => decode(codec, file)?,
+				None => Box::new(file),
+			};
+			let mut archive = tar::Archive::new(Metered { inner: reader, budget });
+			for entry in archive.entries()? {
+				budget.check()?;
+				let mut entry = entry?;
+				if !entry.header().entry_type().is_file() {
+					continue;
+				}
+				let name = entry.path()?.to_string_lossy().into_owned();
+				if !visit(Some(&name), Ok(&mut entry)) {
+					break;
+				}
+			}
+		},
+		Format::Zip => {
+			let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(io::Error::other)?;
+			for index in 0..archive.len() {
+				budget.check()?;
+				if budget.exhausted() {
+					break;
+				}
+				let name = archive
+					.name_for_index(index)
+					.unwrap_or_default()
+					.to_string();
+				let keep_going = match archive.by_index(index) {
+					Ok(member) if member.is_file() => {
+						visit(Some(&name), Ok(&mut Metered { inner: member, budget }))
+					},
+					Ok(_) => true,
+					Err(err) => visit(Some(&name), Err(io::Error::other(err))),
+				};
+				if !keep_going {
+					break;
+				}
+			}
+		},
+	}
+	Ok(())
+}
@@ -0,0 +1,358 @@
+//! Regex engines behind `grep()` and `search()`.
+//!
+//! The default engine is Rust's `regex` (via `grep_regex`), which runs in
+//! linear time but rejects look-around and backreferences. `fancy-regex`
+//! supports both by backtracking. It is used when requested, or in `auto`
+//! mode when the default engine rejects a pattern that it accepts. Both sit
+//! behind [`EngineMatcher`], so the searcher and the match collector do not
+//! care which one is in use.
+
+use std::{collections::HashMap, io};
+
+use grep_matcher::{Captures, LineMatchKind, LineTerminator, Match, Matcher};
+use grep_regex::{RegexCaptures, RegexMatcher, RegexMatcherBuilder};
+use napi::{Error, Result};
+
+use super::PatternConfig;
+
+/// Which regex engine to use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+	/// Default engine, falling back to `fancy` for patterns it rejects.
+	Auto,
+	/// Rust `regex` only.
+	Default,
+	/// `fancy-regex` only.
+	Fancy,
+}
+
+impl Engine {
+	/// Resolve the `engine` option; `None` means `auto`.
+	pub fn parse(engine: Option<&str>) -> Result<Self> {
+		match engine {
+			None | Some("auto") => Ok(Self::Auto),
+			Some("default") => Ok(Self::Default),
+			Some("fancy") => Ok(Self::Fancy),
+			Some(engine) => Err(Error::from_reason(format!("Unknown regex engine: {engine}"))),
+		}
+	}
+}
+
+/// Compile `patterns` as one alternation with the flags in `config`.
+pub fn compile(
+	config: &PatternConfig,
+	patterns: &[String],
+	engine: Engine,
+) -> Result<EngineMatcher> {
+	match engine {
+		Engine::Default => build_default(config, patterns).map(EngineMatcher::Default),
+		Engine::Fancy => FancyMatcher::new(config, patterns).map(EngineMatcher::Fancy),
+		Engine::Auto => match build_default(config, patterns) {
+			Ok(matcher) => Ok(EngineMatcher::Default(matcher)),
+			// Report the default engine's error if fancy cannot parse it either.
+			Err(err) => FancyMatcher::new(config, patterns)
+				.map(EngineMatcher::Fancy)
+				.map_err(|_| err),
+		},
+	}
+}
+
+fn build_default(config: &PatternConfig, patterns: &[String]) -> Result<RegexMatcher> {
+	RegexMatcherBuilder::new()
+		.case_insensitive(config.ignore_case)
+		.case_smart(config.smart_case)
+		.multi_line(config.multiline)
+		.fixed_strings(config.fixed_strings)
+		.word(config.word_regexp)
+		.build_many(patterns)
+		.map_err(|err| Error::from_reason(format!("Regex error: {err}")))
+}
+
+/// A matcher backed by either engine.
+pub enum EngineMatcher {
+	Default(RegexMatcher),
+	Fancy(FancyMatcher),
+}
+
+impl EngineMatcher {
+	/// The engine this matcher was built with.
+	pub const fn engine(&self) -> Engine {
+		match self {
+			Self::Default(_) => Engine::Default,
+			Self::Fancy(_) => Engine::Fancy,
+		}
+	}
+}
+
+pub enum EngineCaptures {
+	Default(RegexCaptures),
+	Fancy(FancyCaptures),
+}
+
+impl Captures for EngineCaptures {
+	fn len(&self) -> usize {
+		match self {
+			Self::Default(caps) => caps.len(),
+			Self::Fancy(caps) => caps.len(),
+		}
+	}
+
+	fn get(&self, i: usize) -> Option<Match> {
+		match self {
+			Self::Default(caps) => caps.get(i),
+			Self::Fancy(caps) => caps.get(i),
+		}
+	}
+}
+
+impl Matcher for EngineMatcher {
+	type Captures = EngineCaptures;
+	type Error = io::Error;
+
+	fn find_at(&self, haystack: &[u8], at: usize) -> io::Result<Option<Match>> {
+		match self {
+			Self::Default(matcher) => matcher.find_at(haystack, at).map_err(io::Error::other),
+			Self::Fancy(matcher) => matcher.find_at(haystack, at),
+		}
+	}
+
+	fn new_captures(&self) -> io::Result<EngineCaptures> {
+		match self {
+			Self::Default(matcher) => matcher
+				.new_captures()
+				.map(EngineCaptures::Default)
+				.map_err(io::Error::other),
+			Self::Fancy(matcher) => matcher.new_captures().map(EngineCaptures::Fancy),
+		}
+	}
+
+	fn capture_count(&self) -> usize {
+		match self {
+			Self::Default(matcher) => matcher.capture_count(),
+			Self::Fancy(matcher) => matcher.capture_count(),
+		}
+	}
+
+	fn capture_index(&self, name: &str) -> Option<usize> {
+		match self {
+			Self::Default(matcher) => matcher.capture_index(name),
+			Self::Fancy(matcher) => matcher.capture_index(name),
+		}
+	}
+
+	fn captures_at(
+		&self,
+		haystack: &[u8],
+		at: usize,
+		caps: &mut EngineCaptures,
+	) -> io::Result<bool> {
+		match (self, caps) {
+			(Self::Default(matcher), EngineCaptures::Default(caps)) => matcher
+				.captures_at(haystack, at, caps)
+				.map_err(io::Error::other),
+			(Self::Fancy(matcher), EngineCaptures::Fancy(caps)) => {
+				matcher.captures_at(haystack, at, caps)
+			},
+			_ => Ok(false),
+		}
+	}
+
+	fn shortest_match_at(&self, haystack: &[u8], at: usize) -> io::Result<Option<usize>> {
+		match self {
+			Self::Default(matcher) => matcher
+				.shortest_match_at(haystack, at)
+				.map_err(io::Error::other),
+			Self::Fancy(matcher) => matcher.shortest_match_at(haystack, at),
+		}
+	}
+
+	fn non_matching_bytes(&self) -> Option<&grep_matcher::ByteSet> {
+		match self {
+			Self::Default(matcher) => matcher.non_matching_bytes(),
+			Self::Fancy(_) => None,
+		}
+	}
+
+	fn line_terminator(&self) -> Option<LineTerminator> {
+		match self {
+			Self::Default(matcher) => matcher.line_terminator(),
+			Self::Fancy(_) => None,
+		}
+	}
+
+	fn find_candidate_line(&self, haystack: &[u8]) -> io::Result<Option<LineMatchKind>> {
+		match self {
+			Self::Default(matcher) => matcher
+				.find_candidate_line(haystack)
+				.map_err(io::Error::other),
+			Self::Fancy(matcher) => matcher.find_candidate_line(haystack),
+		}
+	}
+}
+
+/// `fancy-regex` adapted to the `grep_matcher` interface.
+///
+/// `fancy-regex` only searches `&str`, so each run of valid UTF-8 in the
+/// haystack is searched on its own: matches never span an invalid byte, and
+/// anchors and look-around see it as the edge of the text.
+pub struct FancyMatcher {
+	regex: fancy_regex::Regex,
+	names: HashMap<String, usize>,
+}
+
+impl FancyMatcher {
+	fn new(config: &PatternConfig, patterns: &[String]) -> Result<Self> {
+		let patterns: Vec<String> = patterns
+			.iter()
+			.map(|pattern| {
+				if config.fixed_strings {
+					fancy_regex::escape(pattern).into_owned()
+				} else {
+					pattern.clone()
+				}
+			})
+			.collect();
+		let case_insensitive = config.ignore_case
+			|| (config.smart_case
+				&& !patterns
+					.iter()
+					.any(|pattern| has_uppercase_literal(pattern)));
+
+		let mut pattern = patterns
+			.iter()
+			.map(|pattern| format!("(?:{pattern})"))
+			.collect::<Vec<_>>()
+			.join("|");
+		if config.word_regexp {
+			pattern = format!(r"(?<!\w)(?:{pattern})(?!\w)");
+		}
+
+		let regex = fancy_regex::RegexBuilder::new(&pattern)
+			.case_insensitive(case_insensitive)
+			.multi_line(config.multiline)
+			.build()
+			.map_err(|err| Error::from_reason(format!("Regex error: {err}")))?;
+		let names = regex
+			.capture_names()
+			.enumerate()
+			.filter_map(|(index, name)| Some((name?.to_string(), index)))
+			.collect();
+		Ok(Self { regex, names })
+	}
+}
+
+/// Run `search` on each valid UTF-8 run of `haystack` that ends at or after
+/// `at`, from `at` or the run's start, until it finds something. Returns the
+/// run's offset in `haystack` with the result.
+fn search_utf8_runs<'h, T>(
+	haystack: &'h [u8],
+	at: usize,
+	mut search: impl FnMut(&'h str, usize) -> io::Result<Option<T>>,
+) -> io::Result<Option<(usize, T)>> {
+	if haystack.is_empty() {
+		return Ok(if at == 0 {
+			search("", 0)?.map(|found| (0, found))
+		} else {
+			None
+		});
+	}
+	let mut offset = 0;
+	for chunk in haystack.utf8_chunks() {
+		let (start, text) = (offset, chunk.valid());
+		offset += text.len() + chunk.invalid().len();
+		if start + text.len() < at {
+			continue;
+		}
+		let from = text.ceil_char_boundary(at.saturating_sub(start));
+		if let Some(found) = search(text, from)? {
+			return Ok(Some((start, found)));
+		}
+	}
+	Ok(None)
+}
+
+impl Matcher for FancyMatcher {
+	type Captures = FancyCaptures;
+	type Error = io::Error;
+
+	fn find_at(&self, haystack: &[u8], at: usize) -> io::Result<Option<Match>> {
+		let found = search_utf8_runs(haystack, at, |text, from| {
+			let found = self
+				.regex
+				.find_from_pos(text, from)
+				.map_err(io::Error::other)?;
+			Ok(found.map(|found| found.range()))
+		})?;
+		Ok(found.map(|(start, range)| Match::new(start + range.start, start + range.end)))
+	}
+
+	fn new_captures(&self) -> io::Result<FancyCaptures> {
+		Ok(FancyCaptures { groups: vec![None; self.regex.captures_len()] })
+	}
+
+	fn capture_count(&self) -> usize {
+		self.regex.captures_len()
+	}
+
+	fn capture_index(&self, name: &str) -> Option<usize> {
+		self.names.get(name).copied()
+	}
+
+	fn captures_at(&self, haystack: &[u8], at: usize, caps: &mut FancyCaptures) -> io::Result<bool> {
+		caps.groups.fill(None);
+		let found = search_utf8_runs(haystack, at, |text, from| {
+			self
+				.regex
+				.captures_from_pos(text, from)
+				.map_err(io::Error::other)
+		})?;
+		let Some((start, found)) = found else {
+			return Ok(false);
+		};
+		for (slot, group) in caps.groups.iter_mut().zip(found.iter()) {
+			*slot = group.map(|group| Match::new(start + group.start(), start + group.end()));
+		}
+		Ok(true)
+	}
+}
+
+/// Capture group offsets from the last [`FancyMatcher`] match.
+pub struct FancyCaptures {
+	groups: Vec<Option<Match>>,
+}
+
+impl Captures for FancyCaptures {
+	fn len(&self) -> usize {
+		self.groups.len()
+	}
+
+	fn get(&self, i: usize) -> Option<Match> {
+		self.groups.get(i).copied().flatten()
+	}
+}
+
+/// Smart case for `fancy-regex`: whether `pattern` has an uppercase letter
+/// outside of escapes (`\W`, `\p{Lu}`) and group names.
+fn has_uppercase_literal(pattern: &str) -> bool {
+	let mut chars = pattern.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => {
+				if matches!(chars.next(), Some('p' | 'P')) && chars.peek() == Some(&'{') {
+					chars.by_ref().find(|&c| c == '}');
+				}
+			},
+			'(' if chars.peek() == Some(&'?') => {
+				chars.next();
+				chars.next_if_eq(&'P');
+				// `(?<name>` but not look-behind `(?<=` / `(?<!`.
+				if chars.next_if_eq(&'<').is_some() && !matches!(chars.peek(), Some('=' | '!')) {
+					chars.by_ref().find(|&c| c == '>');
+				}
+			},
+			c if c.is_uppercase() => return true,
+			_ => {},
+		}
+	}
+	false
+}
@@ -0,0 +1,570 @@
+//! ripgrep `--json` output for `grepJson()`.
+//!
+//! Every file with matches produces `begin`, `match`/`context` and `end`
+//! messages in ripgrep's format, and a `summary` message ends the stream.
+//! Lines and submatches that are not valid UTF-8 are sent as base64 `bytes`.
+//! Archive members searched with `searchCompressed` are reported as
+//! `archive:member`.
+//!
+//! Messages are built per file and sent as one batch through a bounded
+//! channel that `next()` drains. A consumer that falls behind fills the
+//! channel, and the search blocks until it catches up, so memory stays flat
+//! however many matches there are. Parallel searches take a chunk of files at
+//! a time on the global rayon pool; the stream's own thread sends the results
+//! and is the only one that waits on the consumer, so a slow reader never
+//! ties up the pool.
+
+use std::{
+	fmt::Write as _,
+	fs::File,
+	io::{self, Read},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use grep_matcher::Matcher;
+use grep_searcher::{Searcher, Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use tokio::sync::{Mutex as AsyncMutex, mpsc};
+
+use super::{
+	Candidates, FileEntry, GrepOptions, PatternMatcher, PreparedSearch, SearchParams, archive,
+	build_searcher, grep_config, prepare_search,
+};
+use crate::{prof::profile_region, task};
+
+/// Files whose messages may wait for `next()` before the search blocks.
+const BUFFERED_FILES: usize = 64;
+
+/// Messages `next()` gathers from buffered files before returning. A file's
+/// messages are never split.
+const MESSAGES_PER_CALL: usize = 1024;
+
+/// How the search ended, as seen by `next()` once the channel is drained.
+#[derive(Default)]
+enum End {
+	#[default]
+	Running,
+	Failed(String),
+	Closed,
+}
+
+/// Messages from a running `grepJson()` search.
+#[napi]
+pub struct GrepJsonStream {
+	batches: Arc<AsyncMutex<mpsc::Receiver<Vec<String>>>>,
+	end:     Arc<Mutex<End>>,
+	abort:   task::AbortToken,
+}
+
+#[napi]
+impl GrepJsonStream {
+	/// Next messages, one JSON document each, or `null` after the summary.
+	/// Rejects if the search failed.
+	#[napi]
+	pub fn next<'env>(&self, env: &'env Env) -> Result<PromiseRaw<'env, Option<Vec<String>>>> {
+		let batches = Arc::clone(&self.batches);
+		let end = Arc::clone(&self.end);
+		task::future(env, "grepJson.next", async move {
+			if matches!(*end.lock(), End::Closed) {
+				return Ok(None);
+			}
+			let mut batches = batches.lock().await;
+			let Some(mut messages) = batches.recv().await else {
+				let end = std::mem::replace(&mut *end.lock(), End::Closed);
+				return match end {
+					End::Failed(reason) => Err(Error::from_reason(reason)),
+					_ => Ok(None),
+				};
+			};
+			while messages.len() < MESSAGES_PER_CALL
+				&& let Ok(batch) = batches.try_recv()
+			{
+				messages.extend(batch);
+			}
+			Ok(Some(messages))
+		})
+	}
+
+	/// Stop the search and drop buffered messages. Later `next()` calls
+	/// resolve to `null`.
+	#[napi]
+	pub fn close(&self) {
+		*self.end.lock() = End::Closed;
+		self.abort.abort(task::AbortReason::User);
+		// A pending `next()` holds the lock, but then the search is not blocked
+		// on a full channel and stops at its next cancellation check.
+		if let Ok(mut batches) = self.batches.try_lock() {
+			batches.close();
+			while batches.try_recv().is_ok() {}
+		}
+	}
+}
+
+/// Search files like `grep()`, streaming ripgrep `--json` messages.
+///
+/// The search starts right away and pauses while messages for
+/// `BUFFERED_FILES` files wait to be read. `mode` is ignored.
+#[napi(js_name = "grepJson")]
+pub fn grep_json(options: GrepOptions<'_>) -> GrepJsonStream {
+	let (mut config, mut ct) = grep_config(options);
+	config.mode = None;
+	let abort = ct.emplace_abort_token();
+	let (sender, receiver) = mpsc::channel(BUFFERED_FILES);
+	let end = Arc::new(Mutex::new(End::Running));
+	spawn_blocking({
+		let end = Arc::clone(&end);
+		move || {
+			let _guard = profile_region("grepJson");
+			let result =
+				prepare_search(config, &ct).and_then(|prepared| stream(&prepared, &sender, &ct));
+			if let Err(err) = result {
+				let mut end = end.lock();
+				if matches!(*end, End::Running) {
+					*end = End::Failed(err.reason.clone());
+				}
+			}
+		}
+	});
+	GrepJsonStream { batches: Arc::new(AsyncMutex::new(receiver)), end, abort }
+}
+
+/// Search every candidate file, sending each file's messages as one batch,
+/// then the summary.
+fn stream(
+	prepared: &PreparedSearch,
+	sender: &mpsc::Sender<Vec<String>>,
+	ct: &task::CancelToken,
+) -> Result<()> {
+	let started = Instant::now();
+	let send = |messages: Vec<String>| {
+		ct.heartbeat()?;
+		sender
+			.blocking_send(messages)
+			.map_err(|_| Error::from_reason("grepJson stream closed"))
+	};
+	let entries: &[FileEntry] = match &prepared.candidates {
+		Candidates::File(file) => file.as_slice(),
+		Candidates::Dir(entries) => entries,
+	};
+	let params = prepared.params;
+
+	let stats = if params.max_count.is_none() && params.offset == 0 {
+		let mut total = Stats::default();
+		for chunk in entries.chunks(BUFFERED_FILES) {
+			ct.heartbeat()?;
+			let outputs: Vec<Output> = chunk
+				.par_iter()
+				.map(|entry| search_file(prepared, entry, params, ct))
+				.collect();
+			for output in outputs {
+				total.add(&output.stats);
+				if !output.messages.is_empty() {
+					send(output.messages)?;
+				}
+			}
+		}
+		total
+	} else {
+		// Limits carry over from file to file, as in `grep()`.
+		let mut total = Output::default();
+		for entry in entries {
+			ct.heartbeat()?;
+			let file_params = total.remaining(params);
+			if file_params.max_count == Some(0) {
+				break;
+			}
+			let mut output = search_file(prepared, entry, file_params, ct);
+			let messages = std::mem::take(&mut output.messages);
+			total.merge(output);
+			if !messages.is_empty() {
+				send(messages)?;
+			}
+			if total.limit_reached {
+				break;
+			}
+		}
+		total.stats
+	};
+
+	let mut summary = String::from(r#"{"type":"summary","data":{"elapsed_total":"#);
+	write_duration(&mut summary, started.elapsed());
+	summary.push_str(r#","stats":"#);
+	stats.write(&mut summary);
+	summary.push_str("}}");
+	send(vec![summary])
+}
+
+/// Counters of ripgrep's `end` and `summary` messages.
+#[derive(Clone, Copy, Default)]
+struct Stats {
+	elapsed:             Duration,
+	searches:            u64,
+	searches_with_match: u64,
+	bytes_searched:      u64,
+	bytes_printed:       u64,
+	matched_lines:       u64,
+	matches:             u64,
+}
+
+impl Stats {
+	fn add(&mut self, other: &Self) {
+		self.elapsed += other.elapsed;
+		self.searches += other.searches;
+		self.searches_with_match += other.searches_with_match;
+		self.bytes_searched += other.bytes_searched;
+		self.bytes_printed += other.bytes_printed;
+		self.matched_lines += other.matched_lines;
+		self.matches += other.matches;
+	}
+
+	fn write(&self, out: &mut String) {
+		out.push_str(r#"{"elapsed":"#);
+		write_duration(out, self.elapsed);
+		let _ = write!(
+			out,
+			r#","searches":{},"searches_with_match":{},"bytes_searched":{},"bytes_printed":{},"matched_lines":{},"matches":{}}}"#,
+			self.searches,
+			self.searches_with_match,
+			self.bytes_searched,
+			self.bytes_printed,
+			self.matched_lines,
+			self.matches,
+		);
+	}
+}
+
+fn write_duration(out: &mut String, duration: Duration) {
+	let _ = write!(
+		out,
+		r#"{{"secs":{},"nanos":{},"human":"{:.6}s"}}"#,
+		duration.as_secs(),
+		duration.subsec_nanos(),
+		duration.as_secs_f64(),
+	);
+}
+
+/// Write `s` as a JSON string literal.
+fn write_str(out: &mut String, s: &str) {
+	out.push('"');
+	for ch in s.chars() {
+		match ch {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			ch if ch < ' ' => {
+				let _ = write!(out, "\\u{:04x}", ch as u32);
+			},
+			ch => out.push(ch),
+		}
+	}
+	out.push('"');
+}
+
+/// Write ripgrep's arbitrary data object: `{"text":...}` for UTF-8, else
+/// `{"bytes":<base64>}`.
+fn write_data(out: &mut String, bytes: &[u8]) {
+	if let Ok(text) = std::str::from_utf8(bytes) {
+		out.push_str(r#"{"text":"#);
+		write_str(out, text);
+	} else {
+		out.push_str(r#"{"bytes":""#);
+		out.push_str(&STANDARD.encode(bytes));
+		out.push('"');
+	}
+	out.push('}');
+}
+
+/// Messages and counts for one file, or for all members of an archive.
+#[derive(Default)]
+struct Output {
+	messages:      Vec<String>,
+	stats:         Stats,
+	/// Matches seen, including those skipped by `offset`.
+	match_count:   u64,
+	/// Matches reported.
+	collected:     u64,
+	limit_reached: bool,
+}
+
+impl Output {
+	/// `params` with the limits left after this output.
+	fn remaining(&self, params: SearchParams) -> SearchParams {
+		SearchParams {
+			max_count: params
+				.max_count
+				.map(|max| max.saturating_sub(self.collected)),
+			offset: params.offset.saturating_sub(self.match_count),
+			..params
+		}
+	}
+
+	fn merge(&mut self, other: Self) {
+		self.messages.extend(other.messages);
+		self.stats.add(&other.stats);
+		self.match_count += other.match_count;
+		self.collected += other.collected;
+		self.limit_reached |= other.limit_reached;
+	}
+}
+
+/// Search one file, or each stream of a compressed file or archive. Files
+/// that cannot be read are left out, as ripgrep only reports them on stderr.
+fn search_file(
+	prepared: &PreparedSearch,
+	entry: &FileEntry,
+	params: SearchParams,
+	ct: &task::CancelToken,
+) -> Output {
+	let matcher = &prepared.matcher;
+	let options = prepared.file_options;
+	let mut output = Output::default();
+
+	if options.decompress
+		&& let Some(format) = archive::Format::detect(&entry.path)
+	{
+		let cap = options.max_bytes.unwrap_or(archive::MAX_DECODED_BYTES);
+		let budget = archive::Budget::new(ct);
+		let _ = archive::for_each_stream(&entry.path, format, &budget, |member, reader| {
+			let Ok(reader) = reader else {
+				return true;
+			};
+			let stream_params = output.remaining(params);
+			if stream_params.max_count == Some(0) {
+				output.limit_reached = true;
+				return false;
+			}
+			let path = member.map_or_else(
+				|| entry.relative_path.clone(),
+				|member| format!("{}:{member}", entry.relative_path),
+			);
+			let search = search_stream(matcher, &path, stream_params, ct, |searcher, sink| {
+				searcher.search_reader(&matcher.matcher, reader.take(cap), sink)
+			});
+			if let Ok(stream) = search {
+				output.merge(stream);
+			}
+			!output.limit_reached
+		});
+		return output;
+	}
+
+	let Ok(file) = File::open(&entry.path) else {
+		return output;
+	};
+	let size = file.metadata().map_or(0, |metadata| metadata.len());
+	let search = search_stream(matcher, &entry.relative_path, params, ct, |searcher, sink| {
+		match options.max_bytes {
+			Some(max) if size > max => {
+				searcher.search_reader(&matcher.matcher, (&file).take(max), sink)
+			},
+			_ => searcher.search_file(&matcher.matcher, &file, sink),
+		}
+	});
+	if let Ok(stream) = search {
+		output.merge(stream);
+	}
+	output
+}
+
+fn search_stream(
+	matcher: &PatternMatcher,
+	path: &str,
+	params: SearchParams,
+	ct: &task::CancelToken,
+	search: impl FnOnce(&mut Searcher, &mut JsonSink<'_>) -> io::Result<()>,
+) -> io::Result<Output> {
+	let mut searcher =
+		build_searcher(params.context_before, params.context_after, matcher.invert, params.encoding);
+	let mut sink = JsonSink {
+		matcher,
+		path,
+		ct,
+		max_count: params.max_count,
+		offset: params.offset,
+		started: Instant::now(),
+		before: Vec::new(),
+		after: false,
+		output: Output::default(),
+	};
+	search(&mut searcher, &mut sink)?;
+	Ok(sink.output)
+}
+
+/// Builds the messages for one searched stream.
+struct JsonSink<'a> {
+	matcher:   &'a PatternMatcher,
+	path:      &'a str,
+	ct:        &'a task::CancelToken,
+	max_count: Option<u64>,
+	offset:    u64,
+	started:   Instant,
+	/// Before-context, held back until its match is known not to be skipped.
+	before:    Vec<String>,
+	/// Whether after-context belongs to a reported match.
+	after:     bool,
+	output:    Output,
+}
+
+impl JsonSink<'_> {
+	fn push(&mut self, message: String) {
+		if self.output.messages.is_empty() {
+			let mut begin = String::from(r#"{"type":"begin","data":{"path":"#);
+			write_data(&mut begin, self.path.as_bytes());
+			begin.push_str("}}");
+			self.push_counted(begin);
+		}
+		self.push_counted(message);
+	}
+
+	fn push_counted(&mut self, message: String) {
+		self.output.stats.bytes_printed += message.len() as u64 + 1;
+		self.output.messages.push(message);
+	}
+
+	/// A `match` or `context` message, and the number of submatches in it.
+	fn line_message(
+		&self,
+		kind: &str,
+		bytes: &[u8],
+		line_number: Option<u64>,
+		absolute_offset: u64,
+	) -> io::Result<(String, u64)> {
+		let mut message = format!(r#"{{"type":"{kind}","data":{{"path":"#);
+		write_data(&mut message, self.path.as_bytes());
+		message.push_str(r#","lines":"#);
+		write_data(&mut message, bytes);
+		match line_number {
+			Some(line_number) => {
+				let _ = write!(message, r#","line_number":{line_number}"#);
+			},
+			None => message.push_str(r#","line_number":null"#),
+		}
+		let _ = write!(message, r#","absolute_offset":{absolute_offset},"submatches":["#);
+		let mut count = 0u64;
+		if kind == "match" && !self.matcher.invert {
+			self
+				.matcher
+				.matcher
+				.find_iter(bytes, |found| {
+					if count > 0 {
+						message.push(',');
+					}
+					message.push_str(r#"{"match":"#);
+					write_data(&mut message, &bytes[found]);
+					let _ = write!(message, r#","start":{},"end":{}}}"#, found.start(), found.end());
+					count += 1;
+					true
+				})
+				.map_err(io::Error::other)?;
+		}
+		message.push_str("]}}");
+		Ok((message, count))
+	}
+}
+
+impl Sink for JsonSink<'_> {
+	type Error = io::Error;
+
+	fn matched(
+		&mut self,
+		_searcher: &Searcher,
+		mat: &SinkMatch<'_>,
+	) -> std::result::Result<bool, Self::Error> {
+		self
+			.ct
+			.heartbeat()
+			.map_err(|err| io::Error::other(err.reason.clone()))?;
+		self.output.match_count += 1;
+		if self.output.limit_reached {
+			return Ok(false);
+		}
+		if self.output.match_count <= self.offset {
+			self.before.clear();
+			self.after = false;
+			return Ok(true);
+		}
+
+		let (message, submatches) =
+			self.line_message("match", mat.bytes(), mat.line_number(), mat.absolute_byte_offset())?;
+		for context in std::mem::take(&mut self.before) {
+			self.push(context);
+		}
+		self.push(message);
+		self.after = true;
+		let lines = mat.lines().count() as u64;
+		self.output.stats.matched_lines += lines;
+		self.output.stats.matches += if self.matcher.invert {
+			lines
+		} else {
+			submatches
+		};
+		self.output.collected += 1;
+		// Keep going for the after-context of the last match.
+		if self
+			.max_count
+			.is_some_and(|max| self.output.collected >= max)
+		{
+			self.output.limit_reached = true;
+		}
+		Ok(true)
+	}
+
+	fn context(
+		&mut self,
+		_searcher: &Searcher,
+		ctx: &SinkContext<'_>,
+	) -> std::result::Result<bool, Self::Error> {
+		let kind = ctx.kind();
+		if *kind == SinkContextKind::Other || (*kind == SinkContextKind::After && !self.after) {
+			return Ok(true);
+		}
+		let (message, _) = self.line_message(
+			"context",
+			ctx.bytes(),
+			ctx.line_number(),
+			ctx.absolute_byte_offset(),
+		)?;
+		if *kind == SinkContextKind::Before {
+			self.before.push(message);
+		} else {
+			self.push(message);
+		}
+		Ok(true)
+	}
+
+	fn finish(
+		&mut self,
+		_searcher: &Searcher,
+		finish: &SinkFinish,
+	) -> std::result::Result<(), Self::Error> {
+		let stats = &mut self.output.stats;
+		stats.elapsed = self.started.elapsed();
+		stats.searches = 1;
+		stats.bytes_searched = finish.byte_count();
+		if self.output.messages.is_empty() {
+			return Ok(());
+		}
+		stats.searches_with_match = 1;
+
+		let mut end = String::from(r#"{"type":"end","data":{"path":"#);
+		write_data(&mut end, self.path.as_bytes());
+		match finish.binary_byte_offset() {
+			Some(offset) => {
+				let _ = write!(end, r#","binary_offset":{offset}"#);
+			},
+			None => end.push_str(r#","binary_offset":null"#),
+		}
+		end.push_str(r#","stats":"#);
+		stats.write(&mut end);
+		end.push_str("}}");
+		self.output.messages.push(end);
+		Ok(())
+	}
+}
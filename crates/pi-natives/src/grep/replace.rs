@@ -0,0 +1,386 @@
+//! Project-wide search and replace on top of the grep engine.
+//!
+//! Files and the matcher come from the same [`GrepConfig`] as `grep()`, so
+//! every filter and pattern flag applies. Replacements are computed in memory
+//! for every file first; a dry run returns unified diffs, otherwise each
+//! changed file is written atomically (temporary file plus rename) and
+//! evicted from the filesystem scan cache. Files in other encodings are
+//! decoded for matching and written back in the same encoding.
+
+use std::{
+	borrow::Cow,
+	fs::{self, File},
+	io::{self, Read, Write},
+	path::Path,
+};
+
+use encoding_rs::Encoding;
+use grep_matcher::{Captures, Matcher};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use rayon::prelude::*;
+use similar::TextDiff;
+
+use super::{
+	Candidates, EngineMatcher, FileEntry, GrepConfig, GrepOptions, GrepSkippedFile, PreparedSearch,
+	clamp_u32, grep_config, non_empty, prepare_search, skipped_file,
+};
+use crate::{fs_cache, task};
+
+/// Replacement applied to every match found with the `grep()` options.
+#[napi(object)]
+pub struct ReplaceEdit {
+	/// Replacement template; `$1`/`${1}` and `$name`/`${name}` insert capture
+	/// groups, `$$` a literal `$`.
+	pub replacement: String,
+	/// Return diffs without writing any file (default: false).
+	#[napi(js_name = "dryRun")]
+	pub dry_run:     Option<bool>,
+}
+
+/// Replacements made (or planned) in a single file.
+#[napi(object)]
+pub struct ReplaceFileResult {
+	/// File path (relative for directory searches).
+	pub path:         String,
+	/// Number of matches rewritten.
+	pub replacements: u32,
+	/// Unified diff of the change (dry runs only).
+	pub diff:         Option<String>,
+	/// Why the file could not be written, if it failed.
+	pub error:        Option<String>,
+}
+
+/// Result of a replace run.
+#[napi(object)]
+pub struct ReplaceResult {
+	/// Files with at least one match, sorted by path.
+	pub files:              Vec<ReplaceFileResult>,
+	/// Total matches rewritten across written (or, for dry runs, all) files.
+	#[napi(js_name = "totalReplacements")]
+	pub total_replacements: u32,
+	/// Number of files written (or that would be written on a dry run).
+	#[napi(js_name = "filesChanged")]
+	pub files_changed:      u32,
+	/// Number of files searched.
+	#[napi(js_name = "filesSearched")]
+	pub files_searched:     u32,
+	/// Whether this was a dry run.
+	#[napi(js_name = "dryRun")]
+	pub dry_run:            bool,
+	/// Files left unchanged because they were binary, too large or could not
+	/// be read.
+	#[napi(js_name = "skippedFiles")]
+	pub skipped_files:      Option<Vec<GrepSkippedFile>>,
+}
+
+/// Content of a file as matched: UTF-8 (or raw) bytes plus what is needed to
+/// write it back in its original encoding.
+struct FileText {
+	content:  Vec<u8>,
+	encoding: &'static Encoding,
+	/// Byte-order mark the file started with, kept on write.
+	bom:      Vec<u8>,
+}
+
+impl FileText {
+	/// Decode `bytes` as the searcher does: a byte-order mark wins over
+	/// `encoding`, and UTF-8 is matched as raw bytes.
+	fn decode(mut bytes: Vec<u8>, encoding: Option<&'static Encoding>) -> io::Result<Self> {
+		let (encoding, bom_len) =
+			Encoding::for_bom(&bytes).unwrap_or_else(|| (encoding.unwrap_or(encoding_rs::UTF_8), 0));
+		let bom = bytes.drain(..bom_len).collect();
+		if encoding == encoding_rs::UTF_8 {
+			return Ok(Self { content: bytes, encoding, bom });
+		}
+		// Rewriting lossily decoded text would corrupt the file.
+		let content = encoding
+			.decode_without_bom_handling_and_without_replacement(&bytes)
+			.ok_or_else(|| {
+				io::Error::new(io::ErrorKind::InvalidData, format!("not valid {}", encoding.name()))
+			})?
+			.into_owned()
+			.into_bytes();
+		Ok(Self { content, encoding, bom })
+	}
+
+	/// Encode `content` (as matched) back into the file's encoding.
+	fn encode<'a>(&self, content: &'a [u8]) -> io::Result<Cow<'a, [u8]>> {
+		if self.encoding == encoding_rs::UTF_8 && self.bom.is_empty() {
+			return Ok(Cow::Borrowed(content));
+		}
+		let mut encoded = self.bom.clone();
+		if self.encoding == encoding_rs::UTF_8 {
+			encoded.extend_from_slice(content);
+			return Ok(Cow::Owned(encoded));
+		}
+		let text = std::str::from_utf8(content)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		if self.encoding == encoding_rs::UTF_16LE {
+			encoded.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+		} else if self.encoding == encoding_rs::UTF_16BE {
+			encoded.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+		} else {
+			let (bytes, _, had_errors) = self.encoding.encode(text);
+			if had_errors {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("replacement cannot be encoded as {}", self.encoding.name()),
+				));
+			}
+			encoded.extend_from_slice(&bytes);
+		}
+		Ok(Cow::Owned(encoded))
+	}
+}
+
+/// New content of a file with at least one match.
+struct PlannedReplacement {
+	entry:        FileEntry,
+	text:         FileText,
+	replaced:     Vec<u8>,
+	replacements: u64,
+}
+
+/// Rewrite every match in `haystack`, expanding `template` per match.
+fn replace_all(
+	matcher: &EngineMatcher,
+	haystack: &[u8],
+	template: &[u8],
+	dst: &mut Vec<u8>,
+) -> io::Result<u64> {
+	let mut caps = matcher.new_captures().map_err(io::Error::other)?;
+	let mut count = 0u64;
+	matcher
+		.replace_with_captures(haystack, &mut caps, dst, |caps, dst| {
+			count += 1;
+			caps.interpolate(|name| matcher.capture_index(name), haystack, template, dst);
+			true
+		})
+		.map_err(io::Error::other)?;
+	Ok(count)
+}
+
+/// Replace matches in `content`, line by line unless `multiline` is set so
+/// patterns behave as they do in `grep()`. Returns `None` without matches.
+fn replace_content(
+	matcher: &EngineMatcher,
+	content: &[u8],
+	template: &[u8],
+	multiline: bool,
+) -> io::Result<Option<(Vec<u8>, u64)>> {
+	if !matcher.is_match(content).map_err(io::Error::other)? {
+		return Ok(None);
+	}
+
+	let mut replaced = Vec::with_capacity(content.len());
+	let count = if multiline {
+		replace_all(matcher, content, template, &mut replaced)?
+	} else {
+		let mut count = 0u64;
+		for line in content.split_inclusive(|&b| b == b'\n') {
+			let (body, terminator) = match line.strip_suffix(b"\n") {
+				Some(body) => (body, &b"\n"[..]),
+				None => (line, &b""[..]),
+			};
+			count += replace_all(matcher, body, template, &mut replaced)?;
+			replaced.extend_from_slice(terminator);
+		}
+		count
+	};
+
+	Ok((count > 0).then_some((replaced, count)))
+}
+
+/// Matcher and settings shared by every file of a replace run.
+struct ReplaceRun<'a> {
+	matcher:   &'a EngineMatcher,
+	template:  &'a [u8],
+	multiline: bool,
+	encoding:  Option<&'static Encoding>,
+	max_bytes: Option<u64>,
+}
+
+impl ReplaceRun<'_> {
+	/// Compute the new content of `entry`; `Ok(None)` when it has no match.
+	/// Files that are binary, too large or unreadable are reported as skipped.
+	fn plan_file(
+		&self,
+		entry: FileEntry,
+	) -> std::result::Result<Option<PlannedReplacement>, GrepSkippedFile> {
+		let unreadable =
+			|err: io::Error| skipped_file(&entry.relative_path, None, "unreadable", err.to_string());
+		let mut file = File::open(&entry.path).map_err(unreadable)?;
+		let size = file.metadata().map_err(unreadable)?.len();
+		// A partial rewrite would drop the rest of the file.
+		if let Some(max) = self.max_bytes
+			&& size > max
+		{
+			let message = format!("{size} bytes is over maxFileBytes ({max})");
+			return Err(skipped_file(&entry.relative_path, None, "tooLarge", message));
+		}
+		let mut bytes = Vec::new();
+		file.read_to_end(&mut bytes).map_err(unreadable)?;
+		let text = FileText::decode(bytes, self.encoding).map_err(unreadable)?;
+		// Leave binary files alone, as grep() does.
+		if let Some(offset) = text.content.iter().position(|&b| b == 0) {
+			let message = format!("binary data at byte {offset}");
+			return Err(skipped_file(&entry.relative_path, None, "binary", message));
+		}
+		let replaced = replace_content(self.matcher, &text.content, self.template, self.multiline)
+			.map_err(unreadable)?;
+		Ok(replaced.map(|(replaced, replacements)| PlannedReplacement {
+			entry,
+			text,
+			replaced,
+			replacements,
+		}))
+	}
+}
+
+fn unified_diff(path: &str, original: &[u8], replaced: &[u8]) -> String {
+	let original = String::from_utf8_lossy(original);
+	let replaced = String::from_utf8_lossy(replaced);
+	// Relative paths get git-style prefixes; a single-file search reports the
+	// absolute path, which is used as is.
+	let (old_header, new_header) = if Path::new(path).is_absolute() {
+		(path.to_string(), path.to_string())
+	} else {
+		(format!("a/{path}"), format!("b/{path}"))
+	};
+	TextDiff::from_lines(original.as_ref(), replaced.as_ref())
+		.unified_diff()
+		.context_radius(3)
+		.header(&old_header, &new_header)
+		.to_string()
+}
+
+/// Replace `path` with `contents` via a temporary file in the same directory,
+/// so readers never see a partially written file. Symlinks are followed and
+/// the file's permissions are kept.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+	let target = fs::canonicalize(path)?;
+	let permissions = fs::metadata(&target)?.permissions();
+	let file_name = target
+		.file_name()
+		.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+	let mut temp_name = std::ffi::OsString::from(".");
+	temp_name.push(file_name);
+	temp_name.push(format!(".{}.replace.tmp", std::process::id()));
+	let temp_path = target.with_file_name(temp_name);
+
+	let result = (|| {
+		let mut file = File::create_new(&temp_path)?;
+		file.write_all(contents)?;
+		file.set_permissions(permissions)?;
+		file.sync_all()?;
+		drop(file);
+		fs::rename(&temp_path, &target)
+	})();
+	if result.is_err() {
+		let _ = fs::remove_file(&temp_path);
+	}
+	result
+}
+
+fn replace_sync(
+	config: GrepConfig,
+	edit: ReplaceEdit,
+	ct: task::CancelToken,
+) -> Result<ReplaceResult> {
+	if config.patterns.invert_match {
+		return Err(Error::from_reason("replace() does not support invertMatch"));
+	}
+	let multiline = config.patterns.multiline;
+	let PreparedSearch { matcher, params, file_options, candidates } = prepare_search(config, &ct)?;
+	let entries = match candidates {
+		Candidates::File(file) => file.into_iter().collect(),
+		Candidates::Dir(entries) => entries,
+	};
+	ct.heartbeat()?;
+
+	let run = ReplaceRun {
+		matcher: &matcher.matcher,
+		template: edit.replacement.as_bytes(),
+		multiline,
+		encoding: params.encoding,
+		max_bytes: file_options.max_bytes,
+	};
+	let results: Vec<_> = entries
+		.into_par_iter()
+		.map(|entry| run.plan_file(entry))
+		.collect();
+	ct.heartbeat()?;
+
+	let mut planned = Vec::new();
+	let mut skipped_files = Vec::new();
+	let mut files_searched = 0u32;
+	for result in results {
+		match result {
+			Ok(plan) => {
+				files_searched = files_searched.saturating_add(1);
+				planned.extend(plan);
+			},
+			Err(skipped) => skipped_files.push(skipped),
+		}
+	}
+	planned.sort_by(|a, b| a.entry.relative_path.cmp(&b.entry.relative_path));
+	skipped_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+	let dry_run = edit.dry_run.unwrap_or(false);
+	let mut files = Vec::with_capacity(planned.len());
+	let mut total_replacements = 0u64;
+	let mut files_changed = 0u32;
+	for plan in planned {
+		let PlannedReplacement { entry, text, replaced, replacements } = plan;
+		let mut diff = None;
+		let mut error = None;
+		match text.encode(&replaced) {
+			Ok(_) if dry_run => {
+				diff = Some(unified_diff(&entry.relative_path, &text.content, &replaced));
+			},
+			Ok(encoded) => match write_atomic(&entry.path, &encoded) {
+				Ok(()) => fs_cache::invalidate_path(&entry.path),
+				Err(err) => error = Some(err.to_string()),
+			},
+			Err(err) => error = Some(err.to_string()),
+		}
+		if error.is_none() {
+			total_replacements = total_replacements.saturating_add(replacements);
+			files_changed = files_changed.saturating_add(1);
+		}
+		files.push(ReplaceFileResult {
+			path: entry.relative_path,
+			replacements: clamp_u32(replacements),
+			diff,
+			error,
+		});
+	}
+
+	Ok(ReplaceResult {
+		files,
+		total_replacements: clamp_u32(total_replacements),
+		files_changed,
+		files_searched,
+		dry_run,
+		skipped_files: non_empty(skipped_files),
+	})
+}
+
+/// Replace regex matches in files, or preview the change.
+///
+/// # Arguments
+/// - `options`: Pattern, path and filters, as for `grep()`. Output options are
+///   ignored; `searchCompressed` is too, since archives cannot be rewritten.
+/// - `edit`: Replacement template and dry-run flag.
+///
+/// # Returns
+/// Per-file replacement counts, with unified diffs for dry runs and write
+/// errors otherwise.
+#[napi(js_name = "replace")]
+pub fn replace(options: GrepOptions<'_>, edit: ReplaceEdit) -> task::Async<ReplaceResult> {
+	let (mut config, ct) = grep_config(options);
+	config.mode = None;
+	config.decompress = false;
+	task::blocking("replace", ct, move |ct| replace_sync(config, edit, ct))
+}
@@ -0,0 +1,651 @@
+//! Persistent trigram index that narrows `grep()` candidates.
+//!
+//! Each index covers a repository root: the nearest ancestor of the search
+//! path containing `.git`, or the search path itself. It maps every trigram
+//! (three ASCII-lowercased bytes) to the files containing it, together with
+//! each file's mtime and size.
+//!
+//! A query is planned from the literals a match must start and end with
+//! (via `regex-syntax`); files lacking the trigrams of every alternative are
+//! skipped before grep-searcher runs. Patterns without usable literals (and
+//! inverted searches) bypass the index.
+//!
+//! Freshness:
+//! - Every query stats the candidate files and re-indexes those whose mtime or
+//!   size changed, so the index updates itself incrementally. Files are read
+//!   without holding the index lock, and a file that cannot be read is dropped
+//!   from the index so it is always searched.
+//! - [`invalidate_path`] drops entries under a mutated path; it is called from
+//!   `invalidateFsScanCache`.
+//! - Files larger than [`MAX_INDEXED_BYTES`] are never filtered out, nor are
+//!   files with a UTF-16 byte-order mark, which are searched decoded.
+//!
+//! Indexes live in memory once loaded and are written to
+//! `GREP_INDEX_DIR` (default: `<cache dir>/omp/grep-index`) after a full build
+//! or once enough files changed.
+
+use std::{
+	collections::HashMap,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, LazyLock},
+	time::UNIX_EPOCH,
+};
+
+use dashmap::DashMap;
+use napi::Result;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use regex_syntax::{
+	ParserBuilder,
+	hir::literal::{ExtractKind, Extractor},
+};
+
+use super::{FileEntry, PatternConfig};
+use crate::{fs_cache, task};
+
+/// Files above this size are not indexed and always searched.
+const MAX_INDEXED_BYTES: u64 = 16 * 1024 * 1024;
+
+const MAGIC: &[u8; 4] = b"PIGI";
+const FORMAT_VERSION: u32 = 1;
+
+static INDEXES: LazyLock<DashMap<PathBuf, Arc<SharedIndex>>> = LazyLock::new(DashMap::new);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Query planning
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Trigrams of one literal; all must be present.
+type Conjunction = Vec<u32>;
+
+/// Files matching any pattern: each pattern needs every one of its clauses,
+/// and a clause is satisfied by any of its literals.
+pub(super) struct Query {
+	patterns: Vec<Vec<Vec<Conjunction>>>,
+}
+
+impl Query {
+	/// Plan a query, or `None` when some pattern has no literal of at least
+	/// three bytes to look up.
+	pub(super) fn plan(config: &PatternConfig, patterns: &[String]) -> Option<Self> {
+		if config.invert_match || patterns.is_empty() {
+			return None;
+		}
+		let patterns = patterns
+			.iter()
+			.map(|pattern| plan_pattern(config, pattern))
+			.collect::<Option<_>>()?;
+		Some(Self { patterns })
+	}
+}
+
+fn plan_pattern(config: &PatternConfig, pattern: &str) -> Option<Vec<Vec<Conjunction>>> {
+	let escaped;
+	let pattern = if config.fixed_strings {
+		escaped = regex_syntax::escape(pattern);
+		&escaped
+	} else {
+		pattern
+	};
+	// Smart case may end up case-sensitive; planning case-insensitively only
+	// adds candidates.
+	let hir = ParserBuilder::new()
+		.utf8(false)
+		.case_insensitive(config.ignore_case || config.smart_case)
+		.multi_line(config.multiline)
+		.build()
+		.parse(pattern)
+		.ok()?;
+
+	let clauses: Vec<Vec<Conjunction>> = [ExtractKind::Prefix, ExtractKind::Suffix]
+		.into_iter()
+		.filter_map(|kind| {
+			let seq = Extractor::new().kind(kind).extract(&hir);
+			seq.literals()?
+				.iter()
+				.map(|literal| {
+					let trigrams = trigrams(literal.as_bytes());
+					(!trigrams.is_empty()).then_some(trigrams)
+				})
+				.collect::<Option<Vec<_>>>()
+		})
+		.collect();
+	(!clauses.is_empty()).then_some(clauses)
+}
+
+/// Sorted, deduplicated trigrams of `bytes`, ASCII-lowercased.
+fn trigrams(bytes: &[u8]) -> Vec<u32> {
+	let mut trigrams: Vec<u32> = bytes
+		.windows(3)
+		.map(|w| {
+			u32::from(w[0].to_ascii_lowercase()) << 16
+				| u32::from(w[1].to_ascii_lowercase()) << 8
+				| u32::from(w[2].to_ascii_lowercase())
+		})
+		.collect();
+	trigrams.sort_unstable();
+	trigrams.dedup();
+	trigrams
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+	let mut out = Vec::with_capacity(a.len().min(b.len()));
+	let (mut i, mut j) = (0, 0);
+	while i < a.len() && j < b.len() {
+		match a[i].cmp(&b[j]) {
+			std::cmp::Ordering::Less => i += 1,
+			std::cmp::Ordering::Greater => j += 1,
+			std::cmp::Ordering::Equal => {
+				out.push(a[i]);
+				i += 1;
+				j += 1;
+			},
+		}
+	}
+	out
+}
+
+fn union(a: &[u32], b: &[u32]) -> Vec<u32> {
+	let mut out = Vec::with_capacity(a.len() + b.len());
+	out.extend_from_slice(a);
+	out.extend_from_slice(b);
+	out.sort_unstable();
+	out.dedup();
+	out
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Index
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+	mtime_ns: u64,
+	size:     u64,
+}
+
+struct FileRecord {
+	path:    String,
+	stamp:   FileStamp,
+	/// False for files too large to index.
+	indexed: bool,
+	live:    bool,
+}
+
+#[derive(Default)]
+struct TrigramIndex {
+	/// Records by id; replaced files leave a dead record behind until the
+	/// next compaction.
+	files:    Vec<FileRecord>,
+	by_path:  HashMap<String, u32>,
+	/// File ids (ascending) per trigram.
+	postings: HashMap<u32, Vec<u32>>,
+	dead:     usize,
+	/// Files (re)indexed or dropped since the index was last written.
+	unsaved:  usize,
+}
+
+impl TrigramIndex {
+	const fn live_count(&self) -> usize {
+		self.files.len() - self.dead
+	}
+
+	fn lookup(&self, path: &str) -> Option<&FileRecord> {
+		let record = &self.files[*self.by_path.get(path)? as usize];
+		record.live.then_some(record)
+	}
+
+	fn remove(&mut self, path: &str) {
+		if let Some(id) = self.by_path.remove(path) {
+			let record = &mut self.files[id as usize];
+			if record.live {
+				record.live = false;
+				self.dead += 1;
+				self.unsaved += 1;
+			}
+		}
+	}
+
+	fn insert(&mut self, path: String, stamp: FileStamp, trigrams: Option<Vec<u32>>) {
+		self.remove(&path);
+		let id = u32::try_from(self.files.len()).unwrap_or(u32::MAX);
+		for &trigram in trigrams.iter().flatten() {
+			self.postings.entry(trigram).or_default().push(id);
+		}
+		self.by_path.insert(path.clone(), id);
+		self
+			.files
+			.push(FileRecord { path, stamp, indexed: trigrams.is_some(), live: true });
+		self.unsaved += 1;
+	}
+
+	/// Drop dead records and renumber the rest.
+	fn compact(&mut self) {
+		let mut remap = vec![u32::MAX; self.files.len()];
+		let mut files = Vec::with_capacity(self.live_count());
+		for (old, record) in std::mem::take(&mut self.files).into_iter().enumerate() {
+			if record.live {
+				remap[old] = files.len() as u32;
+				files.push(record);
+			}
+		}
+		self.postings.retain(|_, ids| {
+			ids.retain_mut(|id| {
+				*id = remap[*id as usize];
+				*id != u32::MAX
+			});
+			!ids.is_empty()
+		});
+		self.by_path = files
+			.iter()
+			.enumerate()
+			.map(|(id, record)| (record.path.clone(), id as u32))
+			.collect();
+		self.files = files;
+		self.dead = 0;
+	}
+
+	/// Live file ids that may match `query`, ascending.
+	fn candidates(&self, query: &Query) -> Vec<u32> {
+		let mut matched = Vec::new();
+		for clauses in &query.patterns {
+			let mut pattern_ids: Option<Vec<u32>> = None;
+			for clause in clauses {
+				let mut clause_ids = Vec::new();
+				for conjunction in clause {
+					clause_ids = union(&clause_ids, &self.conjunction_ids(conjunction));
+				}
+				pattern_ids = Some(match pattern_ids {
+					Some(ids) => intersect(&ids, &clause_ids),
+					None => clause_ids,
+				});
+			}
+			matched = union(&matched, &pattern_ids.unwrap_or_default());
+		}
+		matched.retain(|&id| self.files[id as usize].live);
+		matched
+	}
+
+	fn conjunction_ids(&self, trigrams: &[u32]) -> Vec<u32> {
+		let mut lists: Vec<&Vec<u32>> = Vec::with_capacity(trigrams.len());
+		for trigram in trigrams {
+			match self.postings.get(trigram) {
+				Some(ids) => lists.push(ids),
+				None => return Vec::new(),
+			}
+		}
+		lists.sort_by_key(|ids| ids.len());
+		let Some((first, rest)) = lists.split_first() else {
+			return Vec::new();
+		};
+		rest
+			.iter()
+			.fold((*first).clone(), |ids, next| intersect(&ids, next))
+	}
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Persistence
+// ═══════════════════════════════════════════════════════════════════════════
+
+fn index_dir() -> Option<PathBuf> {
+	if let Some(dir) = std::env::var_os("GREP_INDEX_DIR") {
+		return Some(PathBuf::from(dir));
+	}
+	let cache = if cfg!(windows) {
+		std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+	} else {
+		std::env::var_os("XDG_CACHE_HOME")
+			.map(PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+	}?;
+	Some(cache.join("omp").join("grep-index"))
+}
+
+/// Index file for `root`, named by a stable (FNV-1a) hash of the path.
+fn index_file(root: &Path) -> Option<PathBuf> {
+	let hash = root
+		.as_os_str()
+		.as_encoded_bytes()
+		.iter()
+		.fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+			(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+		});
+	Some(index_dir()?.join(format!("{hash:016x}.idx")))
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		out.push((value as u8) | 0x80);
+		value >>= 7;
+	}
+	out.push(value as u8);
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+	fn varint(&mut self) -> Option<u64> {
+		let mut value = 0u64;
+		for shift in (0..64).step_by(7) {
+			let (&byte, rest) = self.bytes.split_first()?;
+			self.bytes = rest;
+			value |= u64::from(byte & 0x7f) << shift;
+			if byte & 0x80 == 0 {
+				return Some(value);
+			}
+		}
+		None
+	}
+
+	fn take(&mut self, len: usize) -> Option<&[u8]> {
+		let (head, rest) = self.bytes.split_at_checked(len)?;
+		self.bytes = rest;
+		Some(head)
+	}
+}
+
+impl TrigramIndex {
+	/// Serialize live records; assumes a compacted index.
+	fn encode(&self, root: &Path) -> Vec<u8> {
+		let mut out = Vec::new();
+		out.extend_from_slice(MAGIC);
+		write_varint(&mut out, u64::from(FORMAT_VERSION));
+		let root = root.as_os_str().as_encoded_bytes();
+		write_varint(&mut out, root.len() as u64);
+		out.extend_from_slice(root);
+
+		write_varint(&mut out, self.files.len() as u64);
+		for record in &self.files {
+			write_varint(&mut out, record.path.len() as u64);
+			out.extend_from_slice(record.path.as_bytes());
+			write_varint(&mut out, record.stamp.mtime_ns);
+			write_varint(&mut out, record.stamp.size);
+			out.push(u8::from(record.indexed));
+		}
+
+		write_varint(&mut out, self.postings.len() as u64);
+		for (&trigram, ids) in &self.postings {
+			write_varint(&mut out, u64::from(trigram));
+			write_varint(&mut out, ids.len() as u64);
+			let mut previous = 0;
+			for &id in ids {
+				write_varint(&mut out, u64::from(id - previous));
+				previous = id;
+			}
+		}
+		out
+	}
+
+	fn decode(bytes: &[u8], root: &Path) -> Option<Self> {
+		let mut reader = Reader { bytes: bytes.strip_prefix(MAGIC)? };
+		if reader.varint()? != u64::from(FORMAT_VERSION) {
+			return None;
+		}
+		let root_len = reader.varint()? as usize;
+		if reader.take(root_len)? != root.as_os_str().as_encoded_bytes() {
+			return None;
+		}
+
+		let mut index = Self::default();
+		let file_count = reader.varint()?;
+		for id in 0..file_count {
+			let path_len = reader.varint()? as usize;
+			let path = String::from_utf8(reader.take(path_len)?.to_vec()).ok()?;
+			let stamp = FileStamp { mtime_ns: reader.varint()?, size: reader.varint()? };
+			let indexed = reader.take(1)?[0] != 0;
+			index.by_path.insert(path.clone(), u32::try_from(id).ok()?);
+			index
+				.files
+				.push(FileRecord { path, stamp, indexed, live: true });
+		}
+
+		let posting_count = reader.varint()?;
+		for _ in 0..posting_count {
+			let trigram = u32::try_from(reader.varint()?).ok()?;
+			let len = reader.varint()?;
+			let mut ids = Vec::with_capacity(len.min(file_count) as usize);
+			let mut id = 0u64;
+			for _ in 0..len {
+				id += reader.varint()?;
+				if id >= file_count {
+					return None;
+				}
+				ids.push(id as u32);
+			}
+			index.postings.insert(trigram, ids);
+		}
+		Some(index)
+	}
+
+	fn load(root: &Path) -> Self {
+		index_file(root)
+			.and_then(|path| fs::read(path).ok())
+			.and_then(|bytes| Self::decode(&bytes, root))
+			.unwrap_or_default()
+	}
+
+	/// Encode the index for writing, counting it as saved.
+	fn snapshot(&mut self, root: &Path) -> Vec<u8> {
+		if self.dead > 0 {
+			self.compact();
+		}
+		self.unsaved = 0;
+		self.encode(root)
+	}
+}
+
+/// A loaded index. Queries hold `index` only to look up and record files,
+/// never while reading them or writing the index out.
+#[derive(Default)]
+struct SharedIndex {
+	index:      Mutex<TrigramIndex>,
+	/// Serializes writes so an older snapshot never replaces a newer one.
+	write_lock: Mutex<()>,
+}
+
+impl SharedIndex {
+	fn load(root: &Path) -> Self {
+		Self { index: Mutex::new(TrigramIndex::load(root)), write_lock: Mutex::new(()) }
+	}
+
+	fn save(&self, root: &Path) -> io::Result<()> {
+		// A save in progress is followed by another once enough files change.
+		let Some(_write) = self.write_lock.try_lock() else {
+			return Ok(());
+		};
+		let bytes = self.index.lock().snapshot(root);
+		let path = index_file(root).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+		let dir = path
+			.parent()
+			.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+		fs::create_dir_all(dir)?;
+		let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+		let result = fs::File::create(&temp)
+			.and_then(|mut file| file.write_all(&bytes))
+			.and_then(|()| fs::rename(&temp, &path));
+		if result.is_err() {
+			let _ = fs::remove_file(&temp);
+		}
+		result
+	}
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Public API
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Nearest ancestor of `path` holding a `.git` entry, else `path` itself.
+fn index_root(path: &Path) -> PathBuf {
+	path
+		.ancestors()
+		.find(|dir| dir.join(".git").exists())
+		.unwrap_or(path)
+		.to_path_buf()
+}
+
+/// Whether the searcher decodes `bytes` from UTF-16, so their trigrams do
+/// not match what it searches.
+fn has_utf16_bom(bytes: &[u8]) -> bool {
+	encoding_rs::Encoding::for_bom(bytes).is_some_and(|(encoding, _)| encoding != encoding_rs::UTF_8)
+}
+
+fn stamp(path: &Path) -> Option<FileStamp> {
+	let metadata = fs::metadata(path).ok()?;
+	let mtime_ns = metadata
+		.modified()
+		.ok()
+		.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+		.map_or(0, |since| since.as_nanos() as u64);
+	Some(FileStamp { mtime_ns, size: metadata.len() })
+}
+
+/// A stale file, read again for the index.
+enum Reread {
+	/// Its trigrams, or `None` when it is too large to index or UTF-16.
+	Indexed(FileStamp, Option<Vec<u32>>),
+	/// Its record is dropped, so old trigrams cannot filter it out.
+	Unreadable,
+}
+
+/// Keep the entries that may match `query`, refreshing the index for
+/// `search_path`'s root along the way. Entries for which `always_search`
+/// holds are kept without consulting the index.
+pub(super) fn filter_candidates(
+	search_path: &Path,
+	entries: Vec<FileEntry>,
+	query: &Query,
+	always_search: impl Fn(&Path) -> bool + Sync,
+	ct: &task::CancelToken,
+) -> Result<Vec<FileEntry>> {
+	let root = index_root(search_path);
+	let shared = INDEXES
+		.entry(root.clone())
+		.or_insert_with(|| Arc::new(SharedIndex::load(&root)))
+		.clone();
+
+	let stamped: Vec<(FileEntry, String, Option<FileStamp>)> = entries
+		.into_par_iter()
+		.map(|entry| {
+			let key = fs_cache::normalize_relative_path(&root, &entry.path).into_owned();
+			let stamp = if always_search(&entry.path) {
+				None
+			} else {
+				stamp(&entry.path)
+			};
+			(entry, key, stamp)
+		})
+		.collect();
+	ct.heartbeat()?;
+
+	let stale: Vec<(&str, &Path, FileStamp)> = {
+		let index = shared.index.lock();
+		stamped
+			.iter()
+			.filter_map(|(entry, key, stamp)| {
+				let stamp = (*stamp)?;
+				let fresh = index
+					.lookup(key)
+					.is_some_and(|record| record.stamp == stamp);
+				(!fresh).then_some((key.as_str(), entry.path.as_path(), stamp))
+			})
+			.collect()
+	};
+	// Read without the lock, so other queries on this root need not wait
+	// for a (first) build.
+	let reread: Vec<(&str, Reread)> = stale
+		.into_par_iter()
+		.map(|(key, path, stamp)| {
+			let reread = if stamp.size > MAX_INDEXED_BYTES {
+				Reread::Indexed(stamp, None)
+			} else if let Ok(bytes) = fs::read(path) {
+				Reread::Indexed(stamp, (!has_utf16_bom(&bytes)).then(|| trigrams(&bytes)))
+			} else {
+				Reread::Unreadable
+			};
+			(key, reread)
+		})
+		.collect();
+	ct.heartbeat()?;
+
+	let mut index = shared.index.lock();
+	for (key, reread) in reread {
+		match reread {
+			// Another query may have indexed this version meanwhile.
+			Reread::Indexed(stamp, _)
+				if index
+					.lookup(key)
+					.is_some_and(|record| record.stamp == stamp) => {},
+			Reread::Indexed(stamp, trigrams) => index.insert(key.to_string(), stamp, trigrams),
+			Reread::Unreadable => index.remove(key),
+		}
+	}
+	if index.dead > index.live_count() {
+		index.compact();
+	}
+
+	let candidates = index.candidates(query);
+	let keep: Vec<bool> = stamped
+		.iter()
+		.map(|(_, key, stamp)| {
+			// Unreadable or excluded: let the searcher handle it.
+			let (Some(_), Some(&id)) = (stamp, index.by_path.get(key.as_str())) else {
+				return true;
+			};
+			// Files too large to index or decoded from UTF-16 are always searched.
+			!index.files[id as usize].indexed || candidates.binary_search(&id).is_ok()
+		})
+		.collect();
+	let save = index.unsaved > 0 && index.unsaved * 20 >= index.live_count();
+	drop(index);
+	if save {
+		let _ = shared.save(&root);
+	}
+
+	Ok(stamped
+		.into_iter()
+		.zip(keep)
+		.filter_map(|((entry, ..), keep)| keep.then_some(entry))
+		.collect())
+}
+
+/// Forget indexed files at or below `target`.
+pub fn invalidate_path(target: &Path) {
+	for entry in INDEXES.iter() {
+		let root = entry.key();
+		if target.starts_with(root) {
+			let relative = fs_cache::normalize_relative_path(root, target).into_owned();
+			let mut index = entry.value().index.lock();
+			if index.by_path.contains_key(&relative) {
+				index.remove(&relative);
+				continue;
+			}
+			let prefix = format!("{relative}/");
+			let doomed: Vec<String> = index
+				.by_path
+				.keys()
+				.filter(|path| relative.is_empty() || **path == relative || path.starts_with(&prefix))
+				.cloned()
+				.collect();
+			for path in doomed {
+				index.remove(&path);
+			}
+		} else if root.starts_with(target) {
+			*entry.value().index.lock() = TrigramIndex::default();
+		}
+	}
+}
+
+/// Drop every loaded index, in memory and on disk.
+pub fn invalidate_all() {
+	for entry in INDEXES.iter() {
+		if let Some(path) = index_file(entry.key()) {
+			let _ = fs::remove_file(path);
+		}
+	}
+	INDEXES.clear();
+}
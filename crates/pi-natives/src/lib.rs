@@ -23,7 +23,11 @@
 
 pub mod clipboard;
 pub mod fd;
+pub mod file_types;
+pub mod frecency;
 pub mod fs_cache;
+pub mod fs_watch;
+pub mod fuzzy;
 pub mod glob;
 pub mod grep;
 pub mod highlight;
@@ -2,14 +2,16 @@
 //!
 //! # Overview
 //! Provides a stateful PTY session that supports streaming output and stdin
-//! passthrough while a command is running.
+//! passthrough while a command is running. The session can run either a
+//! system command or an interactive brush REPL attached to a persistent
+//! [`Shell`] session.
 
 use std::{
 	collections::HashMap,
 	io::{Read, Write},
 	str,
 	sync::{Arc, Mutex, mpsc},
-	time::Duration,
+	time::{Duration, Instant},
 };
 
 use napi::{
@@ -17,9 +19,9 @@ use napi::{
 	threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
 };
 use napi_derive::napi;
-use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+use portable_pty::{Child, CommandBuilder, MasterPty, PtyPair, PtySize, native_pty_system};
 
-use crate::task;
+use crate::{shell::Shell, task};
 
 /// Options for running a command in a PTY session.
 #[napi(object)]
@@ -41,6 +43,17 @@ pub struct PtyStartOptions<'env> {
 	pub rows:       Option<u16>,
 }
 
+/// Options for running an interactive shell in a PTY session.
+#[napi(object)]
+pub struct PtyShellOptions<'env> {
+	/// Abort signal for ending the shell.
+	pub signal: Option<Unknown<'env>>,
+	/// PTY column count.
+	pub cols:   Option<u16>,
+	/// PTY row count.
+	pub rows:   Option<u16>,
+}
+
 /// Result of a PTY command run.
 #[napi(object)]
 pub struct PtyRunResult {
@@ -113,38 +126,75 @@ impl PtySession {
 			rows:    options.rows.unwrap_or(40).clamp(5, 200),
 		};
 		let ct = task::CancelToken::new(options.timeout_ms, options.signal);
+		let control_rx = self.register()?;
 		let core = Arc::clone(&self.core);
-
-		// Register control channel synchronously so write()/kill() work immediately.
-		let (control_tx, control_rx) = mpsc::channel::<ControlMessage>();
-		{
-			let mut guard = core
-				.lock()
-				.map_err(|_| Error::from_reason("PTY session lock poisoned"))?;
-			if guard.is_some() {
-				return Err(Error::from_reason("PTY session already running"));
-			}
-			*guard = Some(PtySessionCore { control_tx });
-		}
 		task::future(env, "pty.start", async move {
 			let run_result =
 				tokio::task::spawn_blocking(move || run_pty_sync(run_config, on_chunk, control_rx, ct))
 					.await;
-
-			// Always clear core regardless of result
-			let mut guard = core
-				.lock()
-				.map_err(|_| Error::from_reason("PTY session lock poisoned"))?;
-			*guard = None;
-			drop(guard);
-
-			match run_result {
-				Ok(inner) => inner,
-				Err(err) => Err(Error::from_reason(format!("PTY execution task failed: {err}"))),
-			}
+			finish(&core, run_result)
 		})
 	}
 
+	/// Take over a persistent shell session interactively.
+	///
+	/// Runs the session's brush shell as an interactive REPL on the PTY, with
+	/// the options of a shell created with `CreateOptions::interactive`:
+	/// prompts (`PS1`/`PS2`), job control, and history with line editing. It
+	/// keeps the session's variables, functions and working directory.
+	/// `write()` feeds it keystrokes; Ctrl-C interrupts the running command.
+	/// Resolves when the user exits, after which the session continues to
+	/// serve `Shell.run` calls. Unix only.
+	#[napi(js_name = "startShell")]
+	pub fn start_shell<'env>(
+		&self,
+		env: &'env Env,
+		shell: &Shell,
+		options: Option<PtyShellOptions<'env>>,
+		#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_chunk: Option<
+			ThreadsafeFunction<String>,
+		>,
+	) -> Result<PromiseRaw<'env, PtyRunResult>> {
+		#[cfg(unix)]
+		{
+			let (signal, cols, rows) =
+				options.map_or((None, None, None), |opt| (opt.signal, opt.cols, opt.rows));
+			let ct = task::CancelToken::new(None, signal);
+			let pair = open_pty(cols.unwrap_or(120).clamp(20, 400), rows.unwrap_or(40).clamp(5, 200))?;
+			let terminal = pair
+				.master
+				.tty_name()
+				.ok_or_else(|| Error::from_reason("PTY has no terminal device"))
+				.and_then(|path| {
+					crate::shell::InteractiveTerminal::open(&path)
+						.map_err(|err| Error::from_reason(format!("Failed to open PTY terminal: {err}")))
+				})?;
+			drop(pair.slave);
+
+			let control = Arc::new(crate::shell::InteractiveControl::default());
+			let repl = shell.interactive(terminal, Arc::clone(&control));
+			let control_rx = self.register()?;
+			let core = Arc::clone(&self.core);
+			task::future(env, "pty.startShell", async move {
+				let (result_tx, result_rx) = mpsc::channel();
+				tokio::spawn(async move {
+					let _ = result_tx.send(repl.await);
+				});
+				let program = ShellProgram { control, result_rx };
+				let run_result = tokio::task::spawn_blocking(move || {
+					pump(pair.master, program, on_chunk, &control_rx, &ct)
+				})
+				.await;
+				finish(&core, run_result)
+			})
+		}
+		#[cfg(not(unix))]
+		{
+			let _ = (env, shell, options, on_chunk);
+			Err(Error::from_reason("Interactive shells are only supported on Unix"))
+		}
+	}
+
 	/// Write raw input bytes to PTY stdin.
 	#[napi]
 	pub fn write(&self, data: String) -> Result<()> {
@@ -168,6 +218,21 @@ impl PtySession {
 }
 
 impl PtySession {
+	/// Register the control channel synchronously so `write()`/`kill()` work
+	/// immediately.
+	fn register(&self) -> Result<mpsc::Receiver<ControlMessage>> {
+		let (control_tx, control_rx) = mpsc::channel::<ControlMessage>();
+		let mut guard = self
+			.core
+			.lock()
+			.map_err(|_| Error::from_reason("PTY session lock poisoned"))?;
+		if guard.is_some() {
+			return Err(Error::from_reason("PTY session already running"));
+		}
+		*guard = Some(PtySessionCore { control_tx });
+		Ok(control_rx)
+	}
+
 	fn send_control(&self, message: ControlMessage) -> Result<()> {
 		let guard = self
 			.core
@@ -189,15 +254,7 @@ fn run_pty_sync(
 	control_rx: mpsc::Receiver<ControlMessage>,
 	ct: task::CancelToken,
 ) -> Result<PtyRunResult> {
-	let pty_system = native_pty_system();
-	let pair = pty_system
-		.openpty(PtySize {
-			rows:         config.rows,
-			cols:         config.cols,
-			pixel_width:  0,
-			pixel_height: 0,
-		})
-		.map_err(|err| Error::from_reason(format!("Failed to open PTY: {err}")))?;
+	let pair = open_pty(config.cols, config.rows)?;
 
 	let mut cmd = CommandBuilder::new("sh");
 	cmd.arg("-lc");
@@ -211,13 +268,195 @@ fn run_pty_sync(
 		}
 	}
 
-	let mut child = pair
+	let child = pair
 		.slave
 		.spawn_command(cmd)
 		.map_err(|err| Error::from_reason(format!("Failed to spawn PTY command: {err}")))?;
 	drop(pair.slave);
 
-	let master = pair.master;
+	pump(pair.master, ChildProgram(child), on_chunk, &control_rx, &ct)
+}
+
+/// Clear the session once its program has finished.
+fn finish(
+	core: &Mutex<Option<PtySessionCore>>,
+	run_result: std::result::Result<Result<PtyRunResult>, tokio::task::JoinError>,
+) -> Result<PtyRunResult> {
+	// Always clear core regardless of result
+	let mut guard = core
+		.lock()
+		.map_err(|_| Error::from_reason("PTY session lock poisoned"))?;
+	*guard = None;
+	drop(guard);
+
+	match run_result {
+		Ok(inner) => inner,
+		Err(err) => Err(Error::from_reason(format!("PTY execution task failed: {err}"))),
+	}
+}
+
+fn open_pty(cols: u16, rows: u16) -> Result<PtyPair> {
+	native_pty_system()
+		.openpty(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 })
+		.map_err(|err| Error::from_reason(format!("Failed to open PTY: {err}")))
+}
+
+/// Program attached to the slave side of the PTY.
+trait PtyProgram {
+	/// Observe input before it is written to the PTY.
+	fn input(&mut self, _data: &str) {}
+	fn kill(&mut self);
+	fn try_wait(&mut self) -> Result<Option<i32>>;
+	fn wait(&mut self) -> Result<i32>;
+	/// Whether the terminal can outlive the program, so output is drained only
+	/// briefly after it exits.
+	fn detaches_output(&self) -> bool {
+		false
+	}
+}
+
+/// A command spawned on the PTY.
+struct ChildProgram(Box<dyn Child + Send + Sync>);
+
+impl PtyProgram for ChildProgram {
+	fn kill(&mut self) {
+		let _ = self.0.kill();
+	}
+
+	fn try_wait(&mut self) -> Result<Option<i32>> {
+		let status = self
+			.0
+			.try_wait()
+			.map_err(|err| Error::from_reason(format!("Failed checking PTY status: {err}")))?;
+		Ok(status.map(|status| i32::try_from(status.exit_code()).unwrap_or(i32::MAX)))
+	}
+
+	fn wait(&mut self) -> Result<i32> {
+		let status = self
+			.0
+			.wait()
+			.map_err(|err| Error::from_reason(format!("Failed waiting PTY process: {err}")))?;
+		Ok(i32::try_from(status.exit_code()).unwrap_or(i32::MAX))
+	}
+}
+
+/// An interactive brush REPL running in-process on the PTY.
+#[cfg(unix)]
+struct ShellProgram {
+	control:   Arc<crate::shell::InteractiveControl>,
+	result_rx: mpsc::Receiver<Result<i32>>,
+}
+
+#[cfg(unix)]
+impl PtyProgram for ShellProgram {
+	fn input(&mut self, data: &str) {
+		// The terminal is not the controlling terminal of the shell's
+		// commands, so the line discipline cannot deliver SIGINT itself.
+		if data.contains('\x03') {
+			self.control.interrupt();
+		}
+	}
+
+	fn kill(&mut self) {
+		self.control.shutdown();
+	}
+
+	fn try_wait(&mut self) -> Result<Option<i32>> {
+		match self.result_rx.try_recv() {
+			Ok(result) => result.map(Some),
+			Err(mpsc::TryRecvError::Empty) => Ok(None),
+			Err(mpsc::TryRecvError::Disconnected) => {
+				Err(Error::from_reason("Interactive shell task failed"))
+			},
+		}
+	}
+
+	fn wait(&mut self) -> Result<i32> {
+		self
+			.result_rx
+			.recv()
+			.map_err(|_| Error::from_reason("Interactive shell task failed"))?
+	}
+
+	// Background jobs started from the REPL keep the terminal open.
+	fn detaches_output(&self) -> bool {
+		true
+	}
+}
+
+/// Lets the PTY reader thread block for output while remaining stoppable.
+#[cfg(unix)]
+struct ReaderStop {
+	master: std::os::fd::RawFd,
+	stop:   std::io::PipeReader,
+}
+
+#[cfg(unix)]
+impl ReaderStop {
+	/// Returns the stop handle for the reader thread and the pipe whose
+	/// closing stops it.
+	fn new(master: &dyn MasterPty) -> Result<(Self, std::io::PipeWriter)> {
+		let master = master
+			.as_raw_fd()
+			.ok_or_else(|| Error::from_reason("PTY has no file descriptor"))?;
+		let (stop, stop_writer) = std::io::pipe()
+			.map_err(|err| Error::from_reason(format!("Failed to create PTY reader pipe: {err}")))?;
+		Ok((Self { master, stop }, stop_writer))
+	}
+
+	/// Wait until the PTY has output (or an error) to read; `false` once the
+	/// stop pipe is closed.
+	fn wait_readable(&self) -> bool {
+		use std::os::fd::AsRawFd;
+
+		let mut fds =
+			[libc::pollfd { fd: self.master, events: libc::POLLIN, revents: 0 }, libc::pollfd {
+				fd:      self.stop.as_raw_fd(),
+				events:  libc::POLLIN,
+				revents: 0,
+			}];
+		loop {
+			// SAFETY: `fds` is a valid array of two pollfds, and the master
+			// descriptor outlives the reader thread, which is joined before
+			// `pump` returns.
+			if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } >= 0 {
+				return fds[1].revents == 0;
+			}
+			if std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+				// Let the read report the failure.
+				return true;
+			}
+		}
+	}
+}
+
+/// Without a pollable descriptor, the reader ends only with the terminal.
+#[cfg(not(unix))]
+struct ReaderStop;
+
+#[cfg(not(unix))]
+impl ReaderStop {
+	#[allow(clippy::unnecessary_wraps, reason = "matches the Unix signature")]
+	const fn new(_master: &dyn MasterPty) -> Result<(Self, ())> {
+		Ok((Self, ()))
+	}
+
+	const fn wait_readable(&self) -> bool {
+		true
+	}
+}
+
+/// Stream PTY output and forward control messages until `program` exits.
+fn pump(
+	master: Box<dyn MasterPty + Send>,
+	mut program: impl PtyProgram,
+	on_chunk: Option<ThreadsafeFunction<String>>,
+	control_rx: &mpsc::Receiver<ControlMessage>,
+	ct: &task::CancelToken,
+) -> Result<PtyRunResult> {
+	/// How long to keep draining output once a detaching program exits.
+	const DETACHED_DRAIN: Duration = Duration::from_millis(100);
+
 	let mut writer = master
 		.take_writer()
 		.map_err(|err| Error::from_reason(format!("Failed to create PTY writer: {err}")))?;
@@ -225,13 +464,16 @@ fn run_pty_sync(
 		.try_clone_reader()
 		.map_err(|err| Error::from_reason(format!("Failed to create PTY reader: {err}")))?;
 
+	// Dropping `stop_reader` ends the reader thread even while processes that
+	// outlive the program (e.g. background jobs) keep the terminal open.
+	let (reader_stop, stop_reader) = ReaderStop::new(&*master)?;
 	let (reader_tx, reader_rx) = mpsc::channel::<ReaderEvent>();
 	let reader_thread = std::thread::spawn(move || {
 		const REPLACEMENT: &str = "\u{FFFD}";
 		const BUF: usize = 4096;
 		let mut buf = [0u8; BUF + 4];
 		let mut it = 0;
-		loop {
+		while reader_stop.wait_readable() {
 			match reader.read(&mut buf[it..BUF]) {
 				Ok(0) => {
 					break;
@@ -249,7 +491,8 @@ fn run_pty_sync(
 							Err(err) => {
 								let valid_up_to = err.valid_up_to();
 								if valid_up_to > 0 {
-									// SAFETY: [..valid_up_to] is guaranteed valid UTF-8 by valid_up_to().
+									// SAFETY: [..valid_up_to] is guaranteed valid UTF-8
+									// by valid_up_to().
 									let text = unsafe { str::from_utf8_unchecked(&pending[..valid_up_to]) };
 									let _ = reader_tx.send(ReaderEvent::Chunk(text.to_string()));
 									buf.copy_within(valid_up_to..it, 0);
@@ -290,18 +533,22 @@ fn run_pty_sync(
 	let mut cancelled = false;
 	let mut reader_done = false;
 	let mut exit_code: Option<i32> = None;
+	let mut drain_deadline: Option<Instant> = None;
 
-	while exit_code.is_none() || !reader_done {
+	while exit_code.is_none()
+		|| !(reader_done || drain_deadline.is_some_and(|d| Instant::now() >= d))
+	{
 		if let Err(err) = ct.heartbeat() {
 			let message = err.to_string();
 			timed_out = message.contains("Timeout");
 			cancelled = !timed_out;
-			let _ = child.kill();
+			program.kill();
 		}
 
 		loop {
 			match control_rx.try_recv() {
 				Ok(ControlMessage::Input(data)) => {
+					program.input(&data);
 					let _ = writer.write_all(data.as_bytes());
 					let _ = writer.flush();
 				},
@@ -310,7 +557,7 @@ fn run_pty_sync(
 				},
 				Ok(ControlMessage::Kill) => {
 					cancelled = true;
-					let _ = child.kill();
+					program.kill();
 				},
 				Err(mpsc::TryRecvError::Empty) => break,
 				Err(mpsc::TryRecvError::Disconnected) => break,
@@ -332,12 +579,11 @@ fn run_pty_sync(
 			}
 		}
 
-		if exit_code.is_none()
-			&& let Some(status) = child
-				.try_wait()
-				.map_err(|err| Error::from_reason(format!("Failed checking PTY status: {err}")))?
-		{
-			exit_code = Some(i32::try_from(status.exit_code()).unwrap_or(i32::MAX));
+		if exit_code.is_none() {
+			exit_code = program.try_wait()?;
+			if exit_code.is_some() && program.detaches_output() {
+				drain_deadline = Some(Instant::now() + DETACHED_DRAIN);
+			}
 		}
 
 		if exit_code.is_none() || !reader_done {
@@ -346,12 +592,10 @@ fn run_pty_sync(
 	}
 
 	if exit_code.is_none() {
-		let status = child
-			.wait()
-			.map_err(|err| Error::from_reason(format!("Failed waiting PTY process: {err}")))?;
-		exit_code = Some(i32::try_from(status.exit_code()).unwrap_or(i32::MAX));
+		exit_code = Some(program.wait()?);
 	}
 
+	drop(stop_reader);
 	let _ = reader_thread.join();
 
 	Ok(PtyRunResult { exit_code, cancelled, timed_out })
@@ -2,7 +2,9 @@
 //!
 //! # Overview
 //! Executes shell commands in a non-interactive brush-core shell, streaming
-//! output back to JavaScript via a threadsafe callback.
+//! output back to JavaScript via threadsafe callbacks. Stdout and stderr share
+//! one pipe unless a dedicated stderr callback is supplied. Stdin is null by
+//! default, or a pipe fed from a buffer or from `Shell.write()` calls.
 //!
 //! # Example
 //! ```ignore
@@ -19,34 +21,54 @@ use std::{
 	fs,
 	io::{self, Write},
 	str,
-	sync::Arc,
+	sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	},
 	time::Duration,
 };
 
+#[cfg(unix)]
+mod interactive;
+mod jobs;
+mod parse;
+mod policy;
+mod snapshot;
+mod trace;
 #[cfg(windows)]
 mod windows;
 
 use brush_builtins::{BuiltinSet, default_builtins};
 use brush_core::{
 	CreateOptions, ExecutionContext, ExecutionControlFlow, ExecutionExitCode, ExecutionResult,
-	ProcessGroupPolicy, Shell as BrushShell, ShellValue, ShellVariable, builtins,
+	ProcessGroupPolicy, ResourceLimits, Shell as BrushShell, ShellValue, ShellVariable, builtins,
+	commandtrace::CommandTrace,
 	env::EnvironmentScope,
+	interfaces::CommandPolicy,
 	openfiles::{self, OpenFile, OpenFiles},
 	sys, traps,
 };
 use clap::Parser;
+#[cfg(unix)]
+pub use interactive::{InteractiveControl, InteractiveTerminal};
+use jobs::{JobOutputStore, JobTable, ShellJob};
 use napi::{
 	bindgen_prelude::*,
 	threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
 	tokio::{
 		self,
-		sync::{Mutex as TokioMutex, mpsc},
+		sync::{MappedMutexGuard, Mutex as TokioMutex, MutexGuard as TokioMutexGuard, mpsc},
 		time,
 	},
 };
 use napi_derive::napi;
+use parking_lot::Mutex;
+pub use parse::parse_shell;
+use policy::{CommandPolicySlot, PolicyCallback, ShellCommandPolicy};
+use snapshot::ShellSnapshot;
 use tokio::io::AsyncReadExt as _;
 use tokio_util::sync::CancellationToken;
+use trace::ShellTraceEntry;
 #[cfg(windows)]
 use windows::configure_windows_path;
 
@@ -59,8 +81,10 @@ struct ShellSessionCore {
 
 #[derive(Clone)]
 struct ShellConfig {
-	session_env:   Option<HashMap<String, String>>,
-	snapshot_path: Option<String>,
+	session_env:    Option<HashMap<String, String>>,
+	snapshot_path:  Option<String>,
+	snapshot:       Option<ShellSnapshot>,
+	command_policy: Option<Arc<CommandPolicySlot>>,
 }
 
 /// Options for configuring a persistent shell session.
@@ -75,45 +99,233 @@ pub struct ShellOptions {
 /// Options for running a shell command (internal, lifetime-free).
 struct ShellRunConfig {
 	/// Command string to execute in the shell.
-	command: String,
+	command:    String,
 	/// Working directory for the command.
-	cwd:     Option<String>,
+	cwd:        Option<String>,
 	/// Environment variables to apply for this command only.
-	env:     Option<HashMap<String, String>>,
+	env:        Option<HashMap<String, String>>,
+	/// Limits applied to spawned external processes.
+	limits:     Option<ResourceLimits>,
+	/// Records each simple command when tracing is enabled.
+	trace:      Option<CommandTrace>,
+	/// Captures the output of background jobs when enabled.
+	job_output: Option<Arc<JobOutputStore>>,
+}
+
+/// Resource limits for a single command run.
+#[napi(object)]
+pub struct ShellLimits {
+	/// CPU time limit in seconds for each spawned process.
+	pub cpu_seconds:         Option<u32>,
+	/// Address space limit in bytes for each spawned process.
+	pub address_space_bytes: Option<f64>,
+	/// Open file descriptor limit for each spawned process.
+	pub open_files:          Option<u32>,
+	/// Combined stdout/stderr bytes after which the command is killed.
+	pub max_output_bytes:    Option<f64>,
+}
+
+/// Split JS-facing limits into process limits and the output byte cap.
+fn split_limits(limits: Option<ShellLimits>) -> (Option<ResourceLimits>, Option<u64>) {
+	let Some(limits) = limits else {
+		return (None, None);
+	};
+	let process_limits = ResourceLimits {
+		cpu_seconds:         limits.cpu_seconds.map(u64::from),
+		address_space_bytes: limits
+			.address_space_bytes
+			.map(|bytes| bytes.max(0.0) as u64),
+		open_files:          limits.open_files.map(u64::from),
+	};
+	let has_process_limits = process_limits.cpu_seconds.is_some()
+		|| process_limits.address_space_bytes.is_some()
+		|| process_limits.open_files.is_some();
+	(
+		has_process_limits.then_some(process_limits),
+		limits.max_output_bytes.map(|bytes| bytes.max(0.0) as u64),
+	)
+}
+
+/// Options for waiting on a background job.
+#[napi(object)]
+pub struct ShellWaitOptions<'env> {
+	/// Timeout in milliseconds before giving up on the wait.
+	#[napi(js_name = "timeoutMs")]
+	pub timeout_ms: Option<u32>,
+	/// Abort signal for cancelling the wait.
+	pub signal:     Option<Unknown<'env>>,
 }
 
 /// Options for running a shell command.
 #[napi(object)]
 pub struct ShellRunOptions<'env> {
 	/// Command string to execute in the shell.
-	pub command:    String,
+	pub command:            String,
 	/// Working directory for the command.
-	pub cwd:        Option<String>,
+	pub cwd:                Option<String>,
 	/// Environment variables to apply for this command only.
-	pub env:        Option<HashMap<String, String>>,
+	pub env:                Option<HashMap<String, String>>,
 	/// Timeout in milliseconds before cancelling the command.
 	#[napi(js_name = "timeoutMs")]
-	pub timeout_ms: Option<u32>,
+	pub timeout_ms:         Option<u32>,
 	/// Abort signal for cancelling the operation.
-	pub signal:     Option<Unknown<'env>>,
+	pub signal:             Option<Unknown<'env>>,
+	/// Data piped to the command's stdin.
+	pub stdin:              Option<Either<String, Uint8Array>>,
+	/// Keep stdin open for `Shell.write()` until `Shell.closeStdin()` is called
+	/// or the command exits.
+	#[napi(js_name = "streamStdin")]
+	pub stream_stdin:       Option<bool>,
+	/// Resource limits for this command.
+	pub limits:             Option<ShellLimits>,
+	/// Record every simple command run, returned as `trace` in the result.
+	pub trace:              Option<bool>,
+	/// Capture the output of background jobs started by this command for
+	/// `Shell.jobs()` and `Shell.waitJob()`, instead of sending it to the
+	/// command's output.
+	#[napi(js_name = "captureJobOutput")]
+	pub capture_job_output: Option<bool>,
 }
 
 /// Result of running a shell command.
 #[napi(object)]
 pub struct ShellRunResult {
 	/// Exit code when the command completes normally.
-	pub exit_code: Option<i32>,
+	pub exit_code:             Option<i32>,
 	/// Whether the command was cancelled via abort.
-	pub cancelled: bool,
+	pub cancelled:             bool,
 	/// Whether the command timed out before completion.
-	pub timed_out: bool,
+	pub timed_out:             bool,
+	/// Bytes read from stdout (includes stderr when streams are merged).
+	pub stdout_bytes:          f64,
+	/// Bytes read from stderr, or `None` when streams are merged.
+	pub stderr_bytes:          Option<f64>,
+	/// Whether the command was killed for exceeding `maxOutputBytes`.
+	pub output_limit_exceeded: bool,
+	/// Commands run, in start order, when the `trace` option is set.
+	pub trace:                 Option<Vec<ShellTraceEntry>>,
+}
+
+/// Streaming callbacks and byte counters for a single command run.
+struct ShellOutput {
+	/// Receives stdout, plus stderr when `on_stderr` is unset.
+	on_chunk:  Option<ThreadsafeFunction<String>>,
+	/// Receives stderr from a dedicated pipe when set.
+	on_stderr: Option<ThreadsafeFunction<String>>,
+	stats:     Arc<OutputStats>,
+}
+
+impl ShellOutput {
+	fn new(
+		on_chunk: Option<ThreadsafeFunction<String>>,
+		on_stderr: Option<ThreadsafeFunction<String>>,
+		max_output_bytes: Option<u64>,
+	) -> Self {
+		let stats = Arc::new(OutputStats {
+			separate_stderr: on_stderr.is_some(),
+			stdout_bytes: AtomicU64::new(0),
+			stderr_bytes: AtomicU64::new(0),
+			max_output_bytes,
+			total_bytes: AtomicU64::new(0),
+			limit_reached: CancellationToken::new(),
+		});
+		Self { on_chunk, on_stderr, stats }
+	}
+}
+
+/// Why a command was stopped before it completed.
+enum StopReason {
+	Abort(task::AbortReason),
+	OutputLimit,
+}
+
+/// Receiving end of a command's stdin feed; the pipe closes once every
+/// sender is dropped.
+type StdinReceiver = mpsc::UnboundedReceiver<Vec<u8>>;
+
+/// Build a stdin feed that delivers `data` and then reaches EOF.
+fn buffered_stdin(data: Either<String, Uint8Array>) -> StdinReceiver {
+	let (tx, rx) = mpsc::unbounded_channel();
+	let _ = tx.send(stdin_bytes(data));
+	rx
+}
+
+fn stdin_bytes(data: Either<String, Uint8Array>) -> Vec<u8> {
+	match data {
+		Either::A(text) => text.into_bytes(),
+		Either::B(bytes) => bytes.to_vec(),
+	}
+}
+
+/// Per-stream byte counts and the output cap, shared with the reader tasks.
+struct OutputStats {
+	separate_stderr:  bool,
+	stdout_bytes:     AtomicU64,
+	stderr_bytes:     AtomicU64,
+	max_output_bytes: Option<u64>,
+	/// Bytes read across both streams, including any past the cap.
+	total_bytes:      AtomicU64,
+	/// Cancelled once output exceeds `max_output_bytes`.
+	limit_reached:    CancellationToken,
+}
+
+impl OutputStats {
+	/// Count `n` freshly read bytes on the stream tracked by `counter`,
+	/// returning how many may be forwarded before the output cap.
+	fn record(&self, counter: &AtomicU64, n: usize) -> usize {
+		let Some(limit) = self.max_output_bytes else {
+			counter.fetch_add(n as u64, Ordering::Relaxed);
+			return n;
+		};
+		let before = self.total_bytes.fetch_add(n as u64, Ordering::Relaxed);
+		let allowed = limit.saturating_sub(before).min(n as u64);
+		counter.fetch_add(allowed, Ordering::Relaxed);
+		if allowed < n as u64 {
+			self.limit_reached.cancel();
+		}
+		allowed as usize
+	}
+
+	fn stdout_bytes(&self) -> f64 {
+		self.stdout_bytes.load(Ordering::Relaxed) as f64
+	}
+
+	fn stderr_bytes(&self) -> Option<f64> {
+		self
+			.separate_stderr
+			.then(|| self.stderr_bytes.load(Ordering::Relaxed) as f64)
+	}
+}
+
+/// Session state observable between commands.
+#[napi(object)]
+pub struct ShellState {
+	/// Current working directory.
+	pub cwd:                    String,
+	/// Exported variables with their scalar values.
+	pub env:                    HashMap<String, String>,
+	/// Names of defined shell functions, sorted.
+	pub functions:              Vec<String>,
+	/// Defined aliases keyed by name.
+	pub aliases:                HashMap<String, String>,
+	/// Directory stack from `pushd`, most recent first (as printed by `dirs`,
+	/// without the current directory).
+	pub dir_stack:              Vec<String>,
+	/// Exit statuses of each command in the last pipeline.
+	pub last_pipeline_statuses: Vec<u32>,
 }
 
 /// Persistent brush-core shell session.
 #[napi]
 pub struct Shell {
-	session: Arc<TokioMutex<Option<ShellSessionCore>>>,
-	config:  ShellConfig,
+	session:    Arc<TokioMutex<Option<ShellSessionCore>>>,
+	config:     ShellConfig,
+	/// Stdin feed of the running command when started with `streamStdin`.
+	stdin:      Arc<Mutex<Option<mpsc::UnboundedSender<Vec<u8>>>>>,
+	/// Captured output of background jobs started with `captureJobOutput`.
+	job_output: Arc<JobOutputStore>,
+	/// Background jobs as of the last time the session was released.
+	job_table:  Arc<JobTable>,
 }
 
 #[napi]
@@ -123,16 +335,40 @@ impl Shell {
 	///
 	/// The options set session-scoped environment variables and a snapshot path.
 	pub fn new(options: Option<ShellOptions>) -> Self {
-		let config = options.map_or_else(
-			|| ShellConfig { session_env: None, snapshot_path: None },
-			|opt| ShellConfig { session_env: opt.session_env, snapshot_path: opt.snapshot_path },
-		);
-		Self { session: Arc::new(TokioMutex::new(None)), config }
+		let (session_env, snapshot_path) =
+			options.map_or((None, None), |opt| (opt.session_env, opt.snapshot_path));
+		let config = ShellConfig {
+			session_env,
+			snapshot_path,
+			snapshot: None,
+			command_policy: Some(Arc::default()),
+		};
+		Self {
+			session: Arc::new(TokioMutex::new(None)),
+			config,
+			stdin: Arc::new(Mutex::new(None)),
+			job_output: Arc::default(),
+			job_table: Arc::default(),
+		}
+	}
+
+	/// Create a shell session restored from a [`Shell::save_snapshot`] result.
+	///
+	/// The snapshot is applied whenever the session is created (including
+	/// after a cancellation), on top of `session_env` and `snapshot_path`.
+	#[napi(factory, js_name = "fromSnapshot")]
+	pub fn from_snapshot(snapshot: ShellSnapshot, options: Option<ShellOptions>) -> Self {
+		let mut shell = Self::new(options);
+		shell.config.snapshot = Some(snapshot);
+		shell
 	}
 
 	/// Run a shell command using the provided options.
 	///
-	/// The `on_chunk` callback receives streamed stdout/stderr output. Returns
+	/// The `on_chunk` callback receives streamed stdout/stderr output. When
+	/// `on_stderr` is provided, stderr is read from its own pipe and delivered
+	/// there instead. With `stream_stdin`, stdin stays open for
+	/// [`Shell::write`] until [`Shell::close_stdin`] or command exit. Returns
 	/// the exit code when the command completes, or flags when cancelled or
 	/// timed out.
 	#[napi]
@@ -143,16 +379,226 @@ impl Shell {
 		#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_chunk: Option<
 			ThreadsafeFunction<String>,
 		>,
+		#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_stderr: Option<
+			ThreadsafeFunction<String>,
+		>,
 	) -> Result<PromiseRaw<'e, ShellRunResult>> {
 		let ct = task::CancelToken::new(options.timeout_ms, options.signal);
 		let session = self.session.clone();
 		let config = self.config.clone();
-
-		let run_config =
-			ShellRunConfig { command: options.command, cwd: options.cwd, env: options.env };
+		let job_table = self.job_table.clone();
+
+		let (limits, max_output_bytes) = split_limits(options.limits);
+		let run_config = ShellRunConfig {
+			command: options.command,
+			cwd: options.cwd,
+			env: options.env,
+			limits,
+			trace: options.trace.unwrap_or(false).then(CommandTrace::new),
+			job_output: options
+				.capture_job_output
+				.unwrap_or(false)
+				.then(|| self.job_output.clone()),
+		};
+		let output = ShellOutput::new(on_chunk, on_stderr, max_output_bytes);
+
+		let streaming = options.stream_stdin.unwrap_or(false);
+		let stdin = if streaming {
+			// Register the feed synchronously so write() works immediately.
+			let mut guard = self.stdin.lock();
+			if guard.is_some() {
+				return Err(Error::from_reason("Shell stdin stream already active"));
+			}
+			let (tx, rx) = mpsc::unbounded_channel();
+			if let Some(data) = options.stdin {
+				let _ = tx.send(stdin_bytes(data));
+			}
+			*guard = Some(tx);
+			Some(rx)
+		} else {
+			options.stdin.map(buffered_stdin)
+		};
+		let stdin_slot = self.stdin.clone();
 
 		task::future(env, "shell.run", async move {
-			run_shell_session(session, config, run_config, on_chunk, ct).await
+			let result =
+				run_shell_session(session, config, job_table, run_config, output, stdin, ct).await;
+			if streaming {
+				stdin_slot.lock().take();
+			}
+			result
+		})
+	}
+
+	/// Write data to the stdin of a command started with `stream_stdin`.
+	#[napi]
+	pub fn write(&self, data: Either<String, Uint8Array>) -> Result<()> {
+		let guard = self.stdin.lock();
+		let tx = guard
+			.as_ref()
+			.ok_or_else(|| Error::from_reason("Shell stdin is not open"))?;
+		tx.send(stdin_bytes(data))
+			.map_err(|_| Error::from_reason("Shell stdin is no longer available"))
+	}
+
+	/// Close the stdin of the running command, signalling EOF.
+	///
+	/// Does nothing when stdin is not open.
+	#[napi(js_name = "closeStdin")]
+	pub fn close_stdin(&self) {
+		self.stdin.lock().take();
+	}
+
+	/// Inspect the session's current state.
+	///
+	/// Waits for any running command to finish, and creates the session first
+	/// if no command has run yet.
+	#[napi]
+	pub async fn state(&self) -> Result<ShellState> {
+		let session = self.lock_session().await?;
+		Ok(collect_state(&session.shell))
+	}
+
+	/// Capture variables, functions, aliases, options and the working
+	/// directory for later use with [`Shell::from_snapshot`].
+	///
+	/// Waits for any running command to finish.
+	#[napi(js_name = "saveSnapshot")]
+	pub async fn save_snapshot(&self) -> Result<ShellSnapshot> {
+		let session = self.lock_session().await?;
+		Ok(snapshot::capture(&session.shell))
+	}
+
+	/// Set the policy consulted before every simple command, replacing any
+	/// previous one.
+	///
+	/// Commands matching `policy.deny` are blocked; others are passed to
+	/// `check`, which may allow, deny or rewrite them. Pass neither to remove
+	/// the policy. Takes effect from the next command, including one already
+	/// running.
+	#[napi(js_name = "setCommandPolicy")]
+	pub fn set_command_policy(
+		&self,
+		policy: Option<ShellCommandPolicy>,
+		#[napi(ts_arg_type = "((command: ShellCommandInfo) => ShellPolicyDecision | undefined | \
+		                      Promise<ShellPolicyDecision | undefined>) | undefined | null")]
+		check: Option<PolicyCallback>,
+	) {
+		if let Some(slot) = &self.config.command_policy {
+			slot.set(policy, check);
+		}
+	}
+
+	/// List the session's background jobs.
+	///
+	/// Jobs that have finished are listed once with their exit code and then
+	/// forgotten, like the `jobs` builtin. While a command is running, lists
+	/// the jobs as they were when it started instead, without exit codes.
+	#[napi]
+	pub fn jobs(&self) -> Result<Vec<ShellJob>> {
+		let Ok(mut session_guard) = self.session.try_lock() else {
+			return Ok(self.job_table.list(&self.job_output));
+		};
+		let Some(session) = session_guard.as_mut() else {
+			return Ok(Vec::new());
+		};
+		let finished = session
+			.shell
+			.jobs
+			.poll()
+			.map_err(|err| Error::from_reason(format!("Failed to poll jobs: {err}")))?;
+		self.job_table.update(&session.shell.jobs);
+
+		let mut jobs: Vec<ShellJob> = session
+			.shell
+			.jobs
+			.jobs
+			.iter()
+			.map(|job| ShellJob::new(job, None, Some(&self.job_output)))
+			.collect();
+		for (job, result) in &finished {
+			let code = result.as_ref().ok().map(exit_code);
+			jobs.push(ShellJob::new(job, code, Some(&self.job_output)));
+			self.job_output.remove(job.id);
+		}
+		jobs.sort_by_key(|job| job.id);
+		Ok(jobs)
+	}
+
+	/// Send a signal (default `TERM`) to a background job.
+	///
+	/// The signal may be a name (`INT`, `SIGKILL`) or a number. Jobs started
+	/// with `&` have every process group they started signaled. Does not wait
+	/// for a running command, but only reaches jobs that existed when it
+	/// started.
+	#[napi(js_name = "killJob")]
+	pub fn kill_job(&self, id: u32, signal: Option<String>) -> Result<()> {
+		let signal = signal.as_deref().unwrap_or("TERM");
+		let signal = signal
+			.parse::<traps::TrapSignal>()
+			.map_err(|err| Error::from_reason(format!("Invalid signal {signal}: {err}")))?;
+		let job = self
+			.job_table
+			.signaler(id)
+			.ok_or_else(|| Error::from_reason(format!("No such job: {id}")))?;
+		job.kill(signal)
+			.map_err(|err| Error::from_reason(format!("Failed to signal job {id}: {err}")))
+	}
+
+	/// Wait for a background job to finish, returning it with its exit code.
+	///
+	/// The job is forgotten once reported. Fails if no such job exists,
+	/// including when it was already reported by [`Shell::jobs`], or when the
+	/// wait times out or is aborted; the job keeps running in that case.
+	#[napi(js_name = "waitJob")]
+	pub fn wait_job<'e>(
+		&self,
+		env: &'e Env,
+		id: u32,
+		options: Option<ShellWaitOptions<'e>>,
+	) -> Result<PromiseRaw<'e, ShellJob>> {
+		const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+		let ct = options.map_or_else(task::CancelToken::default, |options| {
+			task::CancelToken::new(options.timeout_ms, options.signal)
+		});
+		let session = self.session.clone();
+		let job_output = self.job_output.clone();
+		let job_table = self.job_table.clone();
+
+		// Poll rather than hold the session across the wait, so commands can
+		// keep running while the job finishes.
+		task::future(env, "shell.waitJob", async move {
+			loop {
+				{
+					let mut session_guard = session.lock().await;
+					let jobs = session_guard
+						.as_mut()
+						.map(|session| &mut session.shell.jobs)
+						.ok_or_else(|| Error::from_reason(format!("No such job: {id}")))?;
+					let index = jobs
+						.jobs
+						.iter()
+						.position(|job| job.id == id as usize)
+						.ok_or_else(|| Error::from_reason(format!("No such job: {id}")))?;
+					let polled = jobs.jobs[index]
+						.poll_done()
+						.map_err(|err| Error::from_reason(format!("Failed to poll job {id}: {err}")))?;
+					if let Some(result) = polled {
+						let job = jobs.jobs.remove(index);
+						job_table.update(jobs);
+						let code = result.as_ref().ok().map(exit_code);
+						let shell_job = ShellJob::new(&job, code, Some(&job_output));
+						job_output.remove(job.id);
+						return Ok(shell_job);
+					}
+				}
+				ct.heartbeat()?;
+				tokio::select! {
+					() = time::sleep(POLL_INTERVAL) => {},
+					_ = ct.wait() => {},
+				}
+			}
 		})
 	}
 
@@ -170,19 +616,63 @@ impl Shell {
 	}
 }
 
+impl Shell {
+	/// Hand the session to an interactive REPL on `terminal`, resolving with
+	/// its last exit code once the user exits.
+	///
+	/// The session stays locked for the whole takeover, so `run` calls wait
+	/// for it to end; afterwards they see the state the user left behind.
+	#[cfg(unix)]
+	pub(crate) fn interactive(
+		&self,
+		terminal: InteractiveTerminal,
+		control: Arc<InteractiveControl>,
+	) -> impl Future<Output = Result<i32>> + Send + 'static {
+		let session = self.session.clone();
+		let config = self.config.clone();
+		let job_table = self.job_table.clone();
+		async move {
+			let mut session_guard = session.lock().await;
+			let session = match &mut *session_guard {
+				Some(session) => session,
+				None => session_guard.insert(create_session(&config).await?),
+			};
+			let result = interactive::run(&mut session.shell, terminal, &control).await;
+			job_table.update(&session.shell.jobs);
+			result
+		}
+	}
+
+	/// Lock the session, creating it first if no command has run yet.
+	async fn lock_session(&self) -> Result<MappedMutexGuard<'_, ShellSessionCore>> {
+		let mut session_guard = self.session.lock().await;
+		if session_guard.is_none() {
+			*session_guard = Some(create_session(&self.config).await?);
+		}
+		Ok(TokioMutexGuard::map(session_guard, |session| {
+			session.as_mut().expect("session was just created")
+		}))
+	}
+}
+
 /// Run a shell command within a persistent session.
 async fn run_shell_session(
 	session: Arc<TokioMutex<Option<ShellSessionCore>>>,
 	config: ShellConfig,
+	job_table: Arc<JobTable>,
 	run_config: ShellRunConfig,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	output: ShellOutput,
+	stdin: Option<StdinReceiver>,
 	mut ct: task::CancelToken,
 ) -> Result<ShellRunResult> {
 	let tokio_cancel = CancellationToken::new();
+	let stats = output.stats.clone();
+	let trace = run_config.trace.clone();
 
 	let mut run_task = tokio::spawn({
 		let session = session.clone();
 		let tokio_cancel = tokio_cancel.clone();
+		let job_table = job_table.clone();
 		let at = ct.emplace_abort_token();
 		async move {
 			let mut session_guard = session.lock().await;
@@ -192,13 +682,20 @@ async fn run_shell_session(
 				None => session_guard.insert(create_session(&config).await?),
 			};
 			session.current_abort = Some(at);
-			run_shell_command(session, &run_config, on_chunk, tokio_cancel).await
+			let result = run_shell_command(session, &run_config, output, stdin, tokio_cancel).await;
+			job_table.update(&session.shell.jobs);
+			result
 		}
 	});
 
-	let res = tokio::select! {
-		res = &mut run_task => res,
-		reason = ct.wait() => {
+	let stopped = tokio::select! {
+		res = &mut run_task => Ok(res),
+		reason = ct.wait() => Err(StopReason::Abort(reason)),
+		() = stats.limit_reached.cancelled() => Err(StopReason::OutputLimit),
+	};
+	let res = match stopped {
+		Ok(res) => res,
+		Err(reason) => {
 			tokio_cancel.cancel();
 			let graceful = time::timeout(Duration::from_secs(2), &mut run_task).await;
 			if graceful.is_err() {
@@ -206,12 +703,17 @@ async fn run_shell_session(
 				let _ = run_task.await;
 			}
 			*session.lock().await = None;
+			job_table.clear();
 			return Ok(ShellRunResult {
-				exit_code: None,
-				cancelled: matches!(reason, task::AbortReason::Signal),
-				timed_out: matches!(reason, task::AbortReason::Timeout),
+				exit_code:             None,
+				cancelled:             matches!(reason, StopReason::Abort(task::AbortReason::Signal)),
+				timed_out:             matches!(reason, StopReason::Abort(task::AbortReason::Timeout)),
+				stdout_bytes:          stats.stdout_bytes(),
+				stderr_bytes:          stats.stderr_bytes(),
+				output_limit_exceeded: matches!(reason, StopReason::OutputLimit),
+				trace:                 trace.as_ref().map(trace::entries),
 			});
-		}
+		},
 	};
 	let res =
 		res.unwrap_or_else(|e| Err(Error::from_reason(format!("Shell execution task failed: {e}"))));
@@ -224,8 +726,45 @@ async fn run_shell_session(
 		}
 	} else {
 		*session.lock().await = None;
+		job_table.clear();
+	}
+	Ok(ShellRunResult {
+		exit_code:             Some(exit_code(&res?)),
+		cancelled:             false,
+		timed_out:             false,
+		stdout_bytes:          stats.stdout_bytes(),
+		stderr_bytes:          stats.stderr_bytes(),
+		output_limit_exceeded: false,
+		trace:                 trace.as_ref().map(trace::entries),
+	})
+}
+
+fn collect_state(shell: &BrushShell) -> ShellState {
+	let env = shell
+		.env
+		.iter_exported()
+		.map(|(name, var)| (name.clone(), var.value().to_cow_str(shell).into_owned()))
+		.collect();
+	let mut functions: Vec<String> = shell.funcs().iter().map(|(name, _)| name.clone()).collect();
+	functions.sort_unstable();
+
+	ShellState {
+		cwd: shell.working_dir().to_string_lossy().into_owned(),
+		env,
+		functions,
+		aliases: shell.aliases.clone(),
+		dir_stack: shell
+			.directory_stack
+			.iter()
+			.rev()
+			.map(|dir| dir.to_string_lossy().into_owned())
+			.collect(),
+		last_pipeline_statuses: shell
+			.last_pipeline_statuses
+			.iter()
+			.map(|&status| u32::from(status))
+			.collect(),
 	}
-	Ok(ShellRunResult { exit_code: Some(exit_code(&res?)), cancelled: false, timed_out: false })
 }
 
 /// Options for executing a shell command via brush-core.
@@ -247,23 +786,38 @@ pub struct ShellExecuteOptions<'env> {
 	pub snapshot_path: Option<String>,
 	/// Abort signal for cancelling the operation.
 	pub signal:        Option<Unknown<'env>>,
+	/// Data piped to the command's stdin.
+	pub stdin:         Option<Either<String, Uint8Array>>,
+	/// Resource limits for this command.
+	pub limits:        Option<ShellLimits>,
+	/// Record every simple command run, returned as `trace` in the result.
+	pub trace:         Option<bool>,
 }
 
 /// Result of executing a shell command via brush-core.
 #[napi(object)]
 pub struct ShellExecuteResult {
 	/// Exit code when the command completes normally.
-	pub exit_code: Option<i32>,
+	pub exit_code:             Option<i32>,
 	/// Whether the command was cancelled via abort.
-	pub cancelled: bool,
+	pub cancelled:             bool,
 	/// Whether the command timed out before completion.
-	pub timed_out: bool,
+	pub timed_out:             bool,
+	/// Bytes read from stdout (includes stderr when streams are merged).
+	pub stdout_bytes:          f64,
+	/// Bytes read from stderr, or `None` when streams are merged.
+	pub stderr_bytes:          Option<f64>,
+	/// Whether the command was killed for exceeding `maxOutputBytes`.
+	pub output_limit_exceeded: bool,
+	/// Commands run, in start order, when the `trace` option is set.
+	pub trace:                 Option<Vec<ShellTraceEntry>>,
 }
 
 /// Execute a brush shell command.
 ///
 /// Creates a fresh session for each call. The `on_chunk` callback receives
-/// streamed stdout/stderr output. Returns the exit code when the command
+/// streamed stdout/stderr output; when `on_stderr` is provided, stderr is
+/// delivered there separately. Returns the exit code when the command
 /// completes, or flags when cancelled or timed out.
 #[napi(js_name = "executeShell")]
 pub fn execute_shell<'env>(
@@ -272,15 +826,31 @@ pub fn execute_shell<'env>(
 	#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_chunk: Option<
 		ThreadsafeFunction<String>,
 	>,
+	#[napi(ts_arg_type = "((chunk: string) => void) | undefined | null")] on_stderr: Option<
+		ThreadsafeFunction<String>,
+	>,
 ) -> Result<PromiseRaw<'env, ShellExecuteResult>> {
-	let config =
-		ShellConfig { session_env: options.session_env, snapshot_path: options.snapshot_path };
-	let run_config =
-		ShellRunConfig { command: options.command, cwd: options.cwd, env: options.env };
+	let config = ShellConfig {
+		session_env:    options.session_env,
+		snapshot_path:  options.snapshot_path,
+		snapshot:       None,
+		command_policy: None,
+	};
+	let (limits, max_output_bytes) = split_limits(options.limits);
+	let run_config = ShellRunConfig {
+		command: options.command,
+		cwd: options.cwd,
+		env: options.env,
+		limits,
+		trace: options.trace.unwrap_or(false).then(CommandTrace::new),
+		job_output: None,
+	};
+	let output = ShellOutput::new(on_chunk, on_stderr, max_output_bytes);
+	let stdin = options.stdin.map(buffered_stdin);
 
 	let ct = task::CancelToken::new(options.timeout_ms, options.signal);
 	task::future(env, "shell.execute", async move {
-		run_shell_oneshot(config, run_config, on_chunk, ct).await
+		run_shell_oneshot(config, run_config, output, stdin, ct).await
 	})
 }
 
@@ -288,22 +858,30 @@ pub fn execute_shell<'env>(
 async fn run_shell_oneshot(
 	config: ShellConfig,
 	run_config: ShellRunConfig,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	output: ShellOutput,
+	stdin: Option<StdinReceiver>,
 	ct: task::CancelToken,
 ) -> Result<ShellExecuteResult> {
 	let tokio_cancel = CancellationToken::new();
+	let stats = output.stats.clone();
+	let trace = run_config.trace.clone();
 
 	let mut task = tokio::spawn({
 		let tokio_cancel = tokio_cancel.clone();
 		async move {
 			let mut session = create_session(&config).await?;
-			run_shell_command(&mut session, &run_config, on_chunk, tokio_cancel).await
+			run_shell_command(&mut session, &run_config, output, stdin, tokio_cancel).await
 		}
 	});
 
-	let run_result = tokio::select! {
-		result = &mut task => result,
-		reason = ct.wait() => {
+	let stopped = tokio::select! {
+		result = &mut task => Ok(result),
+		reason = ct.wait() => Err(StopReason::Abort(reason)),
+		() = stats.limit_reached.cancelled() => Err(StopReason::OutputLimit),
+	};
+	let run_result = match stopped {
+		Ok(result) => result,
+		Err(reason) => {
 			tokio_cancel.cancel();
 			let graceful = time::timeout(Duration::from_secs(2), &mut task).await;
 			if graceful.is_err() {
@@ -311,17 +889,29 @@ async fn run_shell_oneshot(
 				let _ = task.await;
 			}
 			return Ok(ShellExecuteResult {
-				exit_code: None,
-				cancelled: matches!(reason, task::AbortReason::Signal),
-				timed_out: matches!(reason, task::AbortReason::Timeout),
-			})
+				exit_code:             None,
+				cancelled:             matches!(reason, StopReason::Abort(task::AbortReason::Signal)),
+				timed_out:             matches!(reason, StopReason::Abort(task::AbortReason::Timeout)),
+				stdout_bytes:          stats.stdout_bytes(),
+				stderr_bytes:          stats.stderr_bytes(),
+				output_limit_exceeded: matches!(reason, StopReason::OutputLimit),
+				trace:                 trace.as_ref().map(trace::entries),
+			});
 		},
 	};
 
 	let res = run_result
 		.unwrap_or_else(|e| Err(Error::from_reason(format!("Shell execution task failed: {e}"))));
 
-	Ok(ShellExecuteResult { exit_code: Some(exit_code(&res?)), cancelled: false, timed_out: false })
+	Ok(ShellExecuteResult {
+		exit_code:             Some(exit_code(&res?)),
+		cancelled:             false,
+		timed_out:             false,
+		stdout_bytes:          stats.stdout_bytes(),
+		stderr_bytes:          stats.stderr_bytes(),
+		output_limit_exceeded: false,
+		trace:                 trace.as_ref().map(trace::entries),
+	})
 }
 
 fn null_file() -> Result<OpenFile> {
@@ -409,6 +999,10 @@ async fn create_session(config: &ShellConfig) -> Result<ShellSessionCore> {
 		no_rc: true,
 		do_not_inherit_env: true,
 		builtins: default_builtins(BuiltinSet::BashMode),
+		command_policy: config
+			.command_policy
+			.clone()
+			.map(|slot| slot as Arc<dyn CommandPolicy>),
 		..Default::default()
 	};
 
@@ -484,6 +1078,10 @@ async fn create_session(config: &ShellConfig) -> Result<ShellSessionCore> {
 		source_snapshot(&mut shell, snapshot_path).await?;
 	}
 
+	if let Some(snapshot) = config.snapshot.as_ref() {
+		snapshot::restore(&mut shell, snapshot)?;
+	}
+
 	Ok(ShellSessionCore { shell, current_abort: None })
 }
 
@@ -505,7 +1103,8 @@ async fn source_snapshot(shell: &mut BrushShell, snapshot_path: &str) -> Result<
 async fn run_shell_command(
 	session: &mut ShellSessionCore,
 	options: &ShellRunConfig,
-	on_chunk: Option<ThreadsafeFunction<String>>,
+	output: ShellOutput,
+	stdin: Option<StdinReceiver>,
 	cancel_token: CancellationToken,
 ) -> Result<ExecutionResult> {
 	if let Some(cwd) = options.cwd.as_deref() {
@@ -517,19 +1116,42 @@ async fn run_shell_command(
 
 	let (reader_file, writer_file) = pipe_to_files("output")?;
 
-	let stdout_file = OpenFile::from(
-		writer_file
-			.try_clone()
-			.map_err(|err| Error::from_reason(format!("Failed to clone pipe: {err}")))?,
-	);
-	let stderr_file = OpenFile::from(writer_file);
+	let (stdout_file, stderr_file, stderr_reader) = if output.on_stderr.is_some() {
+		let (stderr_reader, stderr_writer) = pipe_to_files("stderr")?;
+		(OpenFile::from(writer_file), OpenFile::from(stderr_writer), Some(stderr_reader))
+	} else {
+		let stdout_file = OpenFile::from(
+			writer_file
+				.try_clone()
+				.map_err(|err| Error::from_reason(format!("Failed to clone pipe: {err}")))?,
+		);
+		(stdout_file, OpenFile::from(writer_file), None)
+	};
+
+	let stdin_file = match stdin {
+		Some(feed) => {
+			let (stdin_reader, stdin_writer) = pipe_to_files("stdin")?;
+			// Builtins such as `read` block the calling worker on the pipe, so the
+			// writer must not depend on the async runtime making progress.
+			tokio::task::spawn_blocking(move || write_input(stdin_writer, feed));
+			OpenFile::from(stdin_reader)
+		},
+		None => null_file()?,
+	};
 
 	let mut params = session.shell.default_exec_params();
-	params.set_fd(OpenFiles::STDIN_FD, null_file()?);
+	params.set_fd(OpenFiles::STDIN_FD, stdin_file);
 	params.set_fd(OpenFiles::STDOUT_FD, stdout_file);
 	params.set_fd(OpenFiles::STDERR_FD, stderr_file);
 	params.process_group_policy = ProcessGroupPolicy::NewProcessGroup;
+	params.resource_limits = options.limits;
 	params.set_cancel_token(cancel_token.clone());
+	if let Some(trace) = &options.trace {
+		params.set_trace(trace.clone());
+	}
+	if let Some(job_output) = &options.job_output {
+		params.set_job_output(job_output.clone());
+	}
 
 	let mut env_scope_pushed = false;
 	if let Some(env) = options.env.as_ref() {
@@ -557,8 +1179,30 @@ async fn run_shell_command(
 	let (activity_tx, mut activity_rx) = mpsc::channel::<()>(1);
 	let mut reader_handle = tokio::spawn({
 		let reader_cancel = reader_cancel.clone();
+		let ShellOutput { on_chunk, on_stderr, stats } = output;
 		async move {
-			read_output(reader_file, on_chunk, reader_cancel, activity_tx).await;
+			let stdout = read_output(
+				reader_file,
+				on_chunk,
+				reader_cancel.clone(),
+				activity_tx.clone(),
+				&stats,
+				&stats.stdout_bytes,
+			);
+			let stderr = async {
+				if let Some(reader) = stderr_reader {
+					read_output(
+						reader,
+						on_stderr,
+						reader_cancel,
+						activity_tx,
+						&stats,
+						&stats.stderr_bytes,
+					)
+					.await;
+				}
+			};
+			tokio::join!(stdout, stderr);
 			Result::<()>::Ok(())
 		}
 	});
@@ -758,6 +1402,8 @@ async fn read_output(
 	on_chunk: Option<ThreadsafeFunction<String>>,
 	cancel_token: CancellationToken,
 	activity: mpsc::Sender<()>,
+	stats: &OutputStats,
+	bytes_read: &AtomicU64,
 ) {
 	const REPLACEMENT: &str = "\u{FFFD}";
 	const BUF: usize = 4096;
@@ -782,7 +1428,8 @@ async fn read_output(
 		if n > 0 {
 			let _ = activity.try_send(());
 		}
-		it += n;
+		let allowed = stats.record(bytes_read, n);
+		it += allowed;
 
 		// Consume as much of `pending` as is decodable *right now*.
 		while it > 0 {
@@ -822,6 +1469,11 @@ async fn read_output(
 				},
 			}
 		}
+
+		if allowed < n {
+			// Output cap reached; the command is being killed.
+			break;
+		}
 	}
 
 	// Flush whatever is left at EOF (including an incomplete final sequence).
@@ -836,6 +1488,16 @@ async fn read_output(
 	}
 }
 
+/// Forward stdin feed data into the pipe until the feed closes or every
+/// reader goes away.
+fn write_input(mut writer: fs::File, mut feed: StdinReceiver) {
+	while let Some(data) = feed.blocking_recv() {
+		if writer.write_all(&data).is_err() {
+			break;
+		}
+	}
+}
+
 fn emit_chunk(text: &str, callback: Option<&ThreadsafeFunction<String>>) {
 	if let Some(callback) = callback {
 		callback.call(Ok(text.to_string()), ThreadsafeFunctionCallMode::NonBlocking);
@@ -0,0 +1,263 @@
+//! Interactive brush REPL on a pseudo-terminal.
+//!
+//! Runs a persistent session's shell in interactive mode against the slave
+//! side of a PTY, so a user can take over the agent's shell with its
+//! environment, functions and jobs intact. The REPL prints prompts, edits
+//! lines with history recall and completion, and records history. Commands
+//! run in their own process groups with job control, but the shell never
+//! claims the host process's terminal.
+
+use std::{
+	fs,
+	io::{self, Write},
+	os::unix::fs::OpenOptionsExt,
+	path::Path,
+};
+
+use brush_core::{
+	CreateOptions, ExecutionControlFlow, ExecutionResult, ProcessGroupPolicy, Shell as BrushShell,
+	openfiles::{OpenFile, OpenFiles},
+	options::RuntimeOptions,
+};
+use napi::{
+	Error, Result,
+	tokio::{self, io::unix::AsyncFd, sync::Notify},
+};
+use parking_lot::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use self::editor::{LineEditor, RawMode};
+use super::{exit_code, parse};
+
+mod editor;
+
+/// Slave side of the PTY, opened without making it the controlling terminal.
+pub struct InteractiveTerminal {
+	/// Blocking descriptor handed to commands as stdin.
+	input:  fs::File,
+	/// Descriptor for prompts and command output.
+	output: fs::File,
+	/// Non-blocking descriptor the REPL reads lines from.
+	reader: fs::File,
+}
+
+impl InteractiveTerminal {
+	/// Open the terminal device at `path` (the PTY's slave).
+	pub fn open(path: &Path) -> io::Result<Self> {
+		let open = |flags: i32| {
+			fs::OpenOptions::new()
+				.read(true)
+				.write(true)
+				.custom_flags(libc::O_NOCTTY | flags)
+				.open(path)
+		};
+		let input = open(0)?;
+		let output = input.try_clone()?;
+		let reader = open(libc::O_NONBLOCK)?;
+		Ok(Self { input, output, reader })
+	}
+
+	fn write(&self, text: &str) {
+		let _ = (&self.output).write_all(text.as_bytes());
+	}
+}
+
+/// Handle used by the PTY session to interrupt or end the REPL.
+#[derive(Default)]
+pub struct InteractiveControl {
+	shutdown:         CancellationToken,
+	/// Cancels the command currently running, if any.
+	command:          Mutex<Option<CancellationToken>>,
+	prompt_interrupt: Notify,
+}
+
+impl InteractiveControl {
+	/// Handle Ctrl-C: cancel the running command, or discard the line being
+	/// entered at the prompt.
+	pub fn interrupt(&self) {
+		match self.command.lock().as_ref() {
+			Some(token) => token.cancel(),
+			None => self.prompt_interrupt.notify_one(),
+		}
+	}
+
+	/// End the REPL, cancelling any running command.
+	pub fn shutdown(&self) {
+		self.shutdown.cancel();
+	}
+}
+
+/// Interactive options, restored when the REPL ends so later `run` calls
+/// behave as before.
+struct SavedOptions {
+	interactive: bool,
+	manage_terminal: bool,
+	enable_job_control: bool,
+	enable_command_history: bool,
+	enable_bang_style_history_substitution: bool,
+	emacs_mode: bool,
+	expand_aliases: bool,
+}
+
+impl SavedOptions {
+	/// Switch to the options of a shell created with
+	/// `CreateOptions::interactive`, except that the terminal is not managed:
+	/// the PTY is not the host process's controlling terminal, so commands
+	/// get their own process groups without being moved to its foreground.
+	fn enter(shell: &mut BrushShell) -> Self {
+		let defaults =
+			RuntimeOptions::defaults_from(&CreateOptions { interactive: true, ..Default::default() });
+		let options = &mut shell.options;
+		let saved = Self {
+			interactive: options.interactive,
+			manage_terminal: options.manage_terminal,
+			enable_job_control: options.enable_job_control,
+			enable_command_history: options.enable_command_history,
+			enable_bang_style_history_substitution: options.enable_bang_style_history_substitution,
+			emacs_mode: options.emacs_mode,
+			expand_aliases: options.expand_aliases,
+		};
+		options.interactive = defaults.interactive;
+		options.manage_terminal = false;
+		options.enable_job_control = defaults.enable_job_control;
+		options.enable_command_history = defaults.enable_command_history;
+		options.enable_bang_style_history_substitution =
+			defaults.enable_bang_style_history_substitution;
+		options.emacs_mode = defaults.emacs_mode;
+		options.expand_aliases = defaults.expand_aliases;
+		saved
+	}
+
+	const fn restore(self, shell: &mut BrushShell) {
+		let options = &mut shell.options;
+		options.interactive = self.interactive;
+		options.manage_terminal = self.manage_terminal;
+		options.enable_job_control = self.enable_job_control;
+		options.enable_command_history = self.enable_command_history;
+		options.enable_bang_style_history_substitution = self.enable_bang_style_history_substitution;
+		options.emacs_mode = self.emacs_mode;
+		options.expand_aliases = self.expand_aliases;
+	}
+}
+
+/// Run the REPL until `exit`, end of input or shutdown. Returns the shell's
+/// last exit code; the session itself stays alive.
+pub async fn run(
+	shell: &mut BrushShell,
+	terminal: InteractiveTerminal,
+	control: &InteractiveControl,
+) -> Result<i32> {
+	let saved = SavedOptions::enter(shell);
+	let result = repl(shell, &terminal, control).await;
+	saved.restore(shell);
+	let _ = shell.save_history();
+	result
+}
+
+async fn repl(
+	shell: &mut BrushShell,
+	terminal: &InteractiveTerminal,
+	control: &InteractiveControl,
+) -> Result<i32> {
+	let reader = terminal
+		.reader
+		.try_clone()
+		.and_then(AsyncFd::new)
+		.map_err(|err| Error::from_reason(format!("Failed to watch terminal: {err}")))?;
+	let mut status = 0;
+	let mut command = String::new();
+	let mut editor = LineEditor::default();
+
+	loop {
+		report_finished_jobs(shell, terminal);
+
+		let prompt = if command.is_empty() {
+			shell.compose_prompt().await
+		} else {
+			shell.compose_continuation_prompt().await
+		};
+		let prompt = prompt.unwrap_or_default();
+		terminal.write(&prompt);
+
+		let raw_mode = RawMode::enter(&terminal.input)
+			.map_err(|err| Error::from_reason(format!("Failed to configure terminal: {err}")))?;
+		let line = tokio::select! {
+			line = editor.read_line(shell, terminal, &reader, &prompt) => line,
+			() = control.prompt_interrupt.notified() => {
+				command.clear();
+				editor.clear();
+				terminal.write("\n");
+				continue;
+			},
+			() = control.shutdown.cancelled() => break,
+		};
+		drop(raw_mode);
+		let Some(line) =
+			line.map_err(|err| Error::from_reason(format!("Terminal read failed: {err}")))?
+		else {
+			terminal.write("exit\n");
+			break;
+		};
+
+		command.push_str(&line);
+		if parse::is_incomplete(&command, &shell.parser_options()) {
+			continue;
+		}
+		let _ = shell.add_to_history(&command);
+
+		let result = run_command(shell, terminal, control, std::mem::take(&mut command)).await;
+		match result {
+			Ok(result) => {
+				status = exit_code(&result);
+				if matches!(result.next_control_flow, ExecutionControlFlow::ExitShell) {
+					break;
+				}
+			},
+			Err(err) => terminal.write(&format!("{err}\n")),
+		}
+	}
+
+	Ok(status)
+}
+
+async fn run_command(
+	shell: &mut BrushShell,
+	terminal: &InteractiveTerminal,
+	control: &InteractiveControl,
+	command: String,
+) -> Result<ExecutionResult> {
+	let file = |file: &fs::File| {
+		file
+			.try_clone()
+			.map(OpenFile::from)
+			.map_err(|err| Error::from_reason(format!("Failed to clone terminal: {err}")))
+	};
+
+	let cancel_token = control.shutdown.child_token();
+	let mut params = shell.default_exec_params();
+	params.set_fd(OpenFiles::STDIN_FD, file(&terminal.input)?);
+	params.set_fd(OpenFiles::STDOUT_FD, file(&terminal.output)?);
+	params.set_fd(OpenFiles::STDERR_FD, file(&terminal.output)?);
+	params.process_group_policy = ProcessGroupPolicy::NewProcessGroup;
+	params.set_cancel_token(cancel_token.clone());
+
+	*control.command.lock() = Some(cancel_token.clone());
+	let result = shell.run_string(command, &params).await;
+	*control.command.lock() = None;
+
+	if cancel_token.is_cancelled() {
+		terminal.write("\n");
+	}
+	result.map_err(|err| Error::from_reason(err.to_string()))
+}
+
+/// Print `[n]+ Done ...` lines for background jobs that finished, as an
+/// interactive shell does before each prompt.
+fn report_finished_jobs(shell: &mut BrushShell, terminal: &InteractiveTerminal) {
+	let Ok(finished) = shell.jobs.poll() else {
+		return;
+	};
+	for (job, _) in finished {
+		terminal.write(&format!("{job}\n"));
+	}
+}
@@ -0,0 +1,380 @@
+//! Line editor for the interactive REPL.
+//!
+//! Reads keys from the terminal in raw mode and redraws the input line in
+//! place: cursor movement, deletion, history recall (Up/Down, Ctrl-P/N) and
+//! Tab completion through brush's completion engine. Input is assumed to fit
+//! on one terminal row; longer lines are not rewrapped.
+
+use std::{
+	fmt::Write as _,
+	fs,
+	io::{self, Read},
+	mem::MaybeUninit,
+	os::fd::AsRawFd,
+};
+
+use brush_core::{Shell as BrushShell, escape};
+use napi::tokio::io::unix::AsyncFd;
+use unicode_width::UnicodeWidthChar;
+
+use super::InteractiveTerminal;
+
+/// Raw mode on the terminal for as long as it is held; dropping it restores
+/// the previous settings, so commands see a canonical-mode terminal.
+pub struct RawMode<'a> {
+	file:  &'a fs::File,
+	saved: libc::termios,
+}
+
+impl<'a> RawMode<'a> {
+	pub fn enter(file: &'a fs::File) -> io::Result<Self> {
+		let fd = file.as_raw_fd();
+		let mut saved = MaybeUninit::<libc::termios>::uninit();
+		// SAFETY: `fd` is an open terminal and `saved` is valid for writes.
+		if unsafe { libc::tcgetattr(fd, saved.as_mut_ptr()) } != 0 {
+			return Err(io::Error::last_os_error());
+		}
+		// SAFETY: initialized by the successful `tcgetattr` above.
+		let saved = unsafe { saved.assume_init() };
+
+		// Keep CR-to-NL translation and output processing; keys such as
+		// Ctrl-C and Ctrl-D arrive as bytes.
+		let mut raw = saved;
+		raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+		raw.c_cc[libc::VMIN] = 1;
+		raw.c_cc[libc::VTIME] = 0;
+		// SAFETY: `fd` is an open terminal and `raw` a valid termios.
+		if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw const raw) } != 0 {
+			return Err(io::Error::last_os_error());
+		}
+		Ok(Self { file, saved })
+	}
+}
+
+impl Drop for RawMode<'_> {
+	fn drop(&mut self) {
+		// SAFETY: the terminal outlives the guard and `saved` came from it.
+		unsafe { libc::tcsetattr(self.file.as_raw_fd(), libc::TCSANOW, &raw const self.saved) };
+	}
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+	Char(char),
+	Enter,
+	Backspace,
+	Delete,
+	Left,
+	Right,
+	Home,
+	End,
+	Up,
+	Down,
+	Tab,
+	KillToStart,
+	KillToEnd,
+	KillWord,
+	Eof,
+	Clear,
+	Ignore,
+}
+
+/// Decode the key at the start of `bytes` and its length; `None` if more
+/// bytes are needed.
+fn parse_key(bytes: &[u8]) -> Option<(Key, usize)> {
+	let key = match *bytes.first()? {
+		b'\r' | b'\n' => Key::Enter,
+		0x7f | 0x08 => Key::Backspace,
+		0x01 => Key::Home,
+		0x02 => Key::Left,
+		0x04 => Key::Eof,
+		0x05 => Key::End,
+		0x06 => Key::Right,
+		0x09 => Key::Tab,
+		0x0b => Key::KillToEnd,
+		0x0c => Key::Clear,
+		0x0e => Key::Down,
+		0x10 => Key::Up,
+		0x15 => Key::KillToStart,
+		0x17 => Key::KillWord,
+		0x1b => return parse_escape(bytes),
+		byte if byte < 0x20 => Key::Ignore,
+		byte if byte < 0x80 => Key::Char(char::from(byte)),
+		byte => {
+			let len = match byte.leading_ones() {
+				2 => 2,
+				3 => 3,
+				4 => 4,
+				_ => return Some((Key::Ignore, 1)),
+			};
+			let encoded = bytes.get(..len)?;
+			return Some(
+				match std::str::from_utf8(encoded)
+					.ok()
+					.and_then(|s| s.chars().next())
+				{
+					Some(c) => (Key::Char(c), len),
+					None => (Key::Ignore, 1),
+				},
+			);
+		},
+	};
+	Some((key, 1))
+}
+
+/// Decode a CSI (`ESC [`) or SS3 (`ESC O`) key sequence.
+fn parse_escape(bytes: &[u8]) -> Option<(Key, usize)> {
+	if !matches!(*bytes.get(1)?, b'[' | b'O') {
+		// Alt-modified keys are not bound.
+		return Some((Key::Ignore, 2));
+	}
+	let end = 2 + bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b))?;
+	let key = match &bytes[2..=end] {
+		b"A" => Key::Up,
+		b"B" => Key::Down,
+		b"C" => Key::Right,
+		b"D" => Key::Left,
+		b"H" | b"1~" | b"7~" => Key::Home,
+		b"F" | b"4~" | b"8~" => Key::End,
+		b"3~" => Key::Delete,
+		_ => Key::Ignore,
+	};
+	Some((key, end + 1))
+}
+
+fn width(chars: &[char]) -> usize {
+	chars.iter().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// The line being edited, kept across prompts that are interrupted.
+#[derive(Default)]
+pub struct LineEditor {
+	line:          Vec<char>,
+	cursor:        usize,
+	/// Columns between the start of the input and the terminal cursor.
+	shown_cursor:  usize,
+	pending:       Vec<u8>,
+	/// Entries back from the newest history item while browsing; 0 when
+	/// editing `draft`.
+	history_index: usize,
+	draft:         Vec<char>,
+}
+
+impl LineEditor {
+	/// Read a line after `prompt` has been written, including its newline;
+	/// `None` on Ctrl-D at an empty line or when the terminal closes.
+	/// Cancellation safe: the partial line is kept.
+	pub async fn read_line(
+		&mut self,
+		shell: &mut BrushShell,
+		terminal: &InteractiveTerminal,
+		reader: &AsyncFd<fs::File>,
+		prompt: &str,
+	) -> io::Result<Option<String>> {
+		loop {
+			while let Some((key, len)) = parse_key(&self.pending) {
+				self.pending.drain(..len);
+				match key {
+					Key::Enter => {
+						terminal.write("\n");
+						let mut line: String = self.take().into_iter().collect();
+						line.push('\n');
+						return Ok(Some(line));
+					},
+					Key::Eof if self.line.is_empty() => return Ok(None),
+					Key::Eof | Key::Delete => {
+						if self.cursor < self.line.len() {
+							self.line.remove(self.cursor);
+						}
+					},
+					Key::Tab => self.complete(shell, terminal, prompt).await,
+					Key::Clear => {
+						terminal.write("\x1b[H\x1b[2J");
+						terminal.write(prompt.rsplit('\n').next().unwrap_or_default());
+						self.shown_cursor = 0;
+					},
+					key => self.edit(shell, key),
+				}
+				self.redraw(terminal);
+			}
+
+			let mut guard = reader.readable().await?;
+			let mut chunk = [0u8; 4096];
+			match guard.try_io(|fd| fd.get_ref().read(&mut chunk)) {
+				Ok(Ok(0)) => return Ok(None),
+				Ok(Ok(n)) => self.pending.extend_from_slice(&chunk[..n]),
+				Ok(Err(err)) if err.kind() == io::ErrorKind::Interrupted => {},
+				// The master side closed.
+				Ok(Err(err)) if err.raw_os_error() == Some(libc::EIO) => return Ok(None),
+				Ok(Err(err)) => return Err(err),
+				Err(_would_block) => {},
+			}
+		}
+	}
+
+	/// Discard the line being edited, as on Ctrl-C.
+	pub fn clear(&mut self) {
+		self.take();
+		self.pending.clear();
+	}
+
+	fn take(&mut self) -> Vec<char> {
+		self.cursor = 0;
+		self.shown_cursor = 0;
+		self.history_index = 0;
+		self.draft.clear();
+		std::mem::take(&mut self.line)
+	}
+
+	fn edit(&mut self, shell: &BrushShell, key: Key) {
+		match key {
+			Key::Char(c) => {
+				self.line.insert(self.cursor, c);
+				self.cursor += 1;
+			},
+			Key::Backspace if self.cursor > 0 => {
+				self.cursor -= 1;
+				self.line.remove(self.cursor);
+			},
+			Key::Left => self.cursor = self.cursor.saturating_sub(1),
+			Key::Right => self.cursor = (self.cursor + 1).min(self.line.len()),
+			Key::Home => self.cursor = 0,
+			Key::End => self.cursor = self.line.len(),
+			Key::KillToStart => {
+				self.line.drain(..self.cursor);
+				self.cursor = 0;
+			},
+			Key::KillToEnd => self.line.truncate(self.cursor),
+			Key::KillWord => {
+				let before = &self.line[..self.cursor];
+				let end = before
+					.iter()
+					.rposition(|c| !c.is_whitespace())
+					.map_or(0, |i| i + 1);
+				let start = before[..end]
+					.iter()
+					.rposition(|c| c.is_whitespace())
+					.map_or(0, |i| i + 1);
+				self.line.drain(start..self.cursor);
+				self.cursor = start;
+			},
+			Key::Up => self.recall(shell, self.history_index + 1),
+			Key::Down if self.history_index > 0 => self.recall(shell, self.history_index - 1),
+			_ => {},
+		}
+	}
+
+	/// Show the history entry `index` items back from the newest, or the
+	/// draft for 0.
+	fn recall(&mut self, shell: &BrushShell, index: usize) {
+		let count = shell.history().map_or(0, |history| history.count());
+		if index > count {
+			return;
+		}
+		if self.history_index == 0 {
+			self.draft = self.line.clone();
+		}
+		self.line = if index == 0 {
+			std::mem::take(&mut self.draft)
+		} else {
+			shell
+				.history()
+				.and_then(|history| history.get(count - index))
+				.map_or_else(Vec::new, |item| item.command_line.chars().collect())
+		};
+		self.history_index = index;
+		self.cursor = self.line.len();
+	}
+
+	async fn complete(
+		&mut self,
+		shell: &mut BrushShell,
+		terminal: &InteractiveTerminal,
+		prompt: &str,
+	) {
+		let input: String = self.line.iter().collect();
+		let position = self.line[..self.cursor].iter().map(|c| c.len_utf8()).sum();
+		let Ok(completions) = shell.complete(&input, position).await else {
+			return;
+		};
+		let Some(replaced) = input
+			.get(completions.insertion_index..completions.insertion_index + completions.delete_count)
+		else {
+			return;
+		};
+
+		let options = &completions.options;
+		let candidates: Vec<String> = completions
+			.candidates
+			.iter()
+			.map(|candidate| {
+				if !options.treat_as_filenames {
+					return candidate.clone();
+				}
+				let mut candidate = if options.no_autoquote_filenames {
+					candidate.clone()
+				} else {
+					escape::quote_if_needed(candidate, escape::QuoteMode::BackslashEscape).into_owned()
+				};
+				if shell.absolute_path(&candidate).is_dir() && !candidate.ends_with('/') {
+					candidate.push('/');
+				}
+				candidate
+			})
+			.collect();
+
+		let insertion = match candidates.as_slice() {
+			[] => {
+				terminal.write("\x07");
+				return;
+			},
+			[candidate] => {
+				let mut insertion = candidate.clone();
+				let at_end = completions.insertion_index + completions.delete_count == input.len();
+				if !(insertion.ends_with('/') || at_end && options.no_trailing_space_at_end_of_line) {
+					insertion.push(' ');
+				}
+				insertion
+			},
+			[first, rest @ ..] => {
+				let common = rest.iter().fold(first.as_str(), |common, candidate| {
+					let len = common
+						.char_indices()
+						.zip(candidate.chars())
+						.find(|((_, a), b)| a != b)
+						.map_or_else(|| common.len().min(candidate.len()), |((i, _), _)| i);
+					&common[..len]
+				});
+				if common.len() <= replaced.len() {
+					// Nothing more to insert; list the choices instead.
+					terminal.write(&format!("\n{}\n", candidates.join("  ")));
+					terminal.write(prompt.rsplit('\n').next().unwrap_or_default());
+					self.shown_cursor = 0;
+					return;
+				}
+				common.to_owned()
+			},
+		};
+
+		let before = &input[..completions.insertion_index];
+		let after = &input[completions.insertion_index + completions.delete_count..];
+		self.cursor = before.chars().count() + insertion.chars().count();
+		self.line = format!("{before}{insertion}{after}").chars().collect();
+	}
+
+	/// Rewrite the input after the prompt and place the cursor.
+	fn redraw(&mut self, terminal: &InteractiveTerminal) {
+		let mut out = String::new();
+		if self.shown_cursor > 0 {
+			let _ = write!(out, "\x1b[{}D", self.shown_cursor);
+		}
+		out.extend(&self.line);
+		out.push_str("\x1b[K");
+		let after = width(&self.line[self.cursor..]);
+		if after > 0 {
+			let _ = write!(out, "\x1b[{after}D");
+		}
+		terminal.write(&out);
+		self.shown_cursor = width(&self.line[..self.cursor]);
+	}
+}
@@ -0,0 +1,182 @@
+//! Background jobs of persistent brush shells.
+//!
+//! Lists the jobs tracked by brush-core's `JobManager`, keeps a snapshot of
+//! them that can be listed and signaled while a command holds the session,
+//! and captures the output of jobs started with `captureJobOutput` into
+//! per-job buffers, so it outlives the command that started them.
+
+use std::{
+	collections::{HashMap, VecDeque},
+	fs,
+	io::{self, Read},
+	sync::Arc,
+};
+
+use brush_core::{
+	interfaces::JobOutputCapture,
+	jobs::{Job, JobManager, JobSignaler, JobState},
+	openfiles::OpenFile,
+};
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+/// Bytes of captured output kept per job; older output is discarded.
+const MAX_CAPTURED_BYTES: usize = 1024 * 1024;
+
+/// A background job of a shell session.
+#[napi(object)]
+#[derive(Clone)]
+pub struct ShellJob {
+	/// Job ID, as used by `%1` job specs.
+	pub id:        u32,
+	/// Process group ID of the job, when the shell created one.
+	pub pgid:      Option<i32>,
+	/// IDs of the job's running processes.
+	pub pids:      Vec<i32>,
+	/// Command line that started the job.
+	pub command:   String,
+	/// One of `running`, `stopped`, `done` or `unknown`.
+	pub state:     String,
+	/// Exit code once the job is done.
+	pub exit_code: Option<i32>,
+	/// Most recent captured output (up to 1 MiB), for jobs started with
+	/// `captureJobOutput`.
+	pub output:    Option<String>,
+}
+
+impl ShellJob {
+	/// Describe a job, with its exit code once finished.
+	pub fn new(job: &Job, exit_code: Option<i32>, output: Option<&JobOutputStore>) -> Self {
+		Self {
+			id: job.id as u32,
+			pgid: job.process_group_id(),
+			pids: job.process_ids(),
+			command: job.command_line.clone(),
+			state: state_name(&job.state).to_string(),
+			exit_code,
+			output: output.and_then(|store| store.output(job.id)),
+		}
+	}
+}
+
+const fn state_name(state: &JobState) -> &'static str {
+	match state {
+		JobState::Unknown => "unknown",
+		JobState::Running => "running",
+		JobState::Stopped => "stopped",
+		JobState::Done => "done",
+	}
+}
+
+/// The jobs of a session as of the last time it was released.
+///
+/// Running commands hold the session for their whole duration, so listing
+/// and signaling jobs from this table keeps them responsive meanwhile.
+#[derive(Default)]
+pub struct JobTable {
+	jobs: Mutex<Vec<(ShellJob, JobSignaler)>>,
+}
+
+impl JobTable {
+	/// Replace the table with the jobs `manager` currently tracks.
+	pub fn update(&self, manager: &JobManager) {
+		*self.jobs.lock() = manager
+			.jobs
+			.iter()
+			.map(|job| (ShellJob::new(job, None, None), job.signaler()))
+			.collect();
+	}
+
+	/// Forget every job, e.g. when the session is discarded.
+	pub fn clear(&self) {
+		self.jobs.lock().clear();
+	}
+
+	/// The tabled jobs ordered by ID, with their latest captured output.
+	pub fn list(&self, output: &JobOutputStore) -> Vec<ShellJob> {
+		let mut jobs: Vec<ShellJob> = self
+			.jobs
+			.lock()
+			.iter()
+			.map(|(job, _)| ShellJob { output: output.output(job.id as usize), ..job.clone() })
+			.collect();
+		jobs.sort_by_key(|job| job.id);
+		jobs
+	}
+
+	/// A handle to signal the job with the given ID.
+	pub fn signaler(&self, id: u32) -> Option<JobSignaler> {
+		self
+			.jobs
+			.lock()
+			.iter()
+			.find(|(job, _)| job.id == id)
+			.map(|(_, signaler)| signaler.clone())
+	}
+}
+
+/// Captured output of a single job, trimmed to the most recent bytes.
+#[derive(Default)]
+struct JobOutputBuffer {
+	bytes: VecDeque<u8>,
+}
+
+impl JobOutputBuffer {
+	fn append(&mut self, data: &[u8]) {
+		self.bytes.extend(data);
+		let excess = self.bytes.len().saturating_sub(MAX_CAPTURED_BYTES);
+		self.bytes.drain(..excess);
+	}
+}
+
+/// Output buffers of a shell's background jobs, keyed by job ID.
+#[derive(Default)]
+pub struct JobOutputStore {
+	buffers: Mutex<HashMap<usize, Arc<Mutex<JobOutputBuffer>>>>,
+}
+
+impl JobOutputStore {
+	/// Captured output of a job, if it was started with capture enabled.
+	pub fn output(&self, job_id: usize) -> Option<String> {
+		let buffer = self.buffers.lock().get(&job_id).cloned()?;
+		let mut buffer = buffer.lock();
+		Some(String::from_utf8_lossy(buffer.bytes.make_contiguous()).into_owned())
+	}
+
+	/// Drop the output of a job that has been reported as finished.
+	pub fn remove(&self, job_id: usize) {
+		self.buffers.lock().remove(&job_id);
+	}
+}
+
+impl JobOutputCapture for JobOutputStore {
+	fn open(&self, job_id: usize, _command_line: &str) -> Option<(OpenFile, OpenFile)> {
+		let (reader, writer) = super::pipe_to_files("job output").ok()?;
+		let stderr = writer.try_clone().ok()?;
+
+		// Job IDs are reused once a job is reaped, so start from an empty buffer.
+		let buffer = Arc::new(Mutex::new(JobOutputBuffer::default()));
+		self.buffers.lock().insert(job_id, buffer.clone());
+
+		// Jobs can run for the life of the session (e.g. dev servers), so read
+		// on a dedicated thread rather than tying up the blocking pool. The
+		// thread exits once every process holding the pipe has exited.
+		std::thread::Builder::new()
+			.name(format!("shell-job-{job_id}-output"))
+			.spawn(move || drain(reader, &buffer))
+			.ok()?;
+		Some((OpenFile::from(writer), OpenFile::from(stderr)))
+	}
+}
+
+fn drain(mut reader: fs::File, buffer: &Mutex<JobOutputBuffer>) {
+	let mut chunk = [0u8; 8192];
+	loop {
+		match reader.read(&mut chunk) {
+			Ok(0) => break,
+			Ok(n) => buffer.lock().append(&chunk[..n]),
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+			Err(_) => break,
+		}
+	}
+}
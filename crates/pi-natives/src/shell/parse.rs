@@ -0,0 +1,443 @@
+//! Static parsing of shell commands without executing them.
+//!
+//! Parses a command string with brush-parser and flattens the AST into a
+//! summary of simple commands, pipelines, redirections and heredocs, descending
+//! into subshells, functions, and command and process substitutions.
+
+use brush_parser::{
+	ParseError, Parser, ParserOptions, SourceInfo,
+	ast::{self, SourceLocation},
+	word::{self, WordPiece, WordPieceWithSource},
+};
+use napi_derive::napi;
+
+/// A syntax error found while parsing.
+#[napi(object)]
+pub struct ShellSyntaxError {
+	/// Error message.
+	pub message: String,
+	/// 1-based line of the error.
+	pub line:    u32,
+	/// 1-based column of the error.
+	pub column:  u32,
+}
+
+/// An I/O redirection attached to a command.
+#[napi(object)]
+pub struct ShellParsedRedirect {
+	/// Explicit file descriptor, if any (e.g. `2` in `2>file`).
+	pub fd:       Option<i32>,
+	/// Redirection operator, e.g. `>`, `>>`, `<`, `<<`, `<<<`, `&>`.
+	pub operator: String,
+	/// Unexpanded target word, heredoc delimiter or file descriptor.
+	pub target:   String,
+}
+
+/// A simple command found in the source.
+#[napi(object)]
+pub struct ShellParsedCommand {
+	/// Unexpanded command name and arguments.
+	pub words:       Vec<String>,
+	/// Variable assignments preceding or accompanying the command.
+	pub assignments: Vec<String>,
+	/// Redirections applied to the command.
+	pub redirects:   Vec<ShellParsedRedirect>,
+	/// Index into `pipelines` of the pipeline containing the command.
+	pub pipeline:    u32,
+	/// Enclosing constructs, outermost first: `subshell`, `function`,
+	/// `commandSubstitution` or `processSubstitution`.
+	pub context:     Vec<String>,
+	/// 1-based line; absent inside command substitutions.
+	pub line:        Option<u32>,
+	/// 1-based column; absent inside command substitutions.
+	pub column:      Option<u32>,
+}
+
+/// A pipeline found in the source; single commands form one-element
+/// pipelines.
+#[napi(object)]
+pub struct ShellParsedPipeline {
+	/// Indices into `commands` of the pipeline's simple commands.
+	pub commands:   Vec<u32>,
+	/// Number of pipeline stages, including compound commands.
+	pub length:     u32,
+	/// Whether the pipeline is negated with `!`.
+	pub negated:    bool,
+	/// Whether the pipeline runs in the background with `&`.
+	pub background: bool,
+	/// Operator joining this pipeline to the previous one: `&&` or `||`.
+	pub operator:   Option<String>,
+	/// Enclosing constructs, as for commands.
+	pub context:    Vec<String>,
+}
+
+/// A here-document found in the source.
+#[napi(object)]
+pub struct ShellParsedHeredoc {
+	/// Delimiter word.
+	pub delimiter: String,
+	/// Unexpanded document body.
+	pub body:      String,
+	/// Whether the body undergoes expansion (unquoted delimiter).
+	pub expands:   bool,
+	/// Index into `commands` of the command it feeds, if a simple command.
+	pub command:   Option<u32>,
+}
+
+/// Result of [`parse_shell`].
+#[napi(object)]
+pub struct ShellParseResult {
+	/// Syntax error; when set, the summary fields are empty.
+	pub error:     Option<ShellSyntaxError>,
+	/// Simple commands in source order.
+	pub commands:  Vec<ShellParsedCommand>,
+	/// Pipelines in source order.
+	pub pipelines: Vec<ShellParsedPipeline>,
+	/// Here-documents in source order.
+	pub heredocs:  Vec<ShellParsedHeredoc>,
+	/// Names of functions defined.
+	pub functions: Vec<String>,
+}
+
+/// Parse a shell command without executing it.
+///
+/// Returns a syntax error with its position, or a summary of the commands,
+/// pipelines and heredocs in the source.
+#[napi(js_name = "parseShell")]
+pub fn parse_shell(command: String) -> ShellParseResult {
+	let mut summary = Summary::default();
+	match parse_program(&command) {
+		Ok(program) => summary.program(&program),
+		Err(err) => summary.error = Some(syntax_error(&err, &command)),
+	}
+	summary.into_result()
+}
+
+/// Whether `source` ends before its command does (e.g. inside quotes or an
+/// unfinished `if`), so more input is needed.
+#[cfg(unix)]
+pub fn is_incomplete(source: &str, options: &ParserOptions) -> bool {
+	let source_info = SourceInfo { source: String::from("interactive") };
+	match Parser::new(source.as_bytes(), options, &source_info).parse_program() {
+		Err(ParseError::ParsingAtEndOfInput) => true,
+		Err(ParseError::Tokenizing { inner, .. }) => inner.is_incomplete(),
+		_ => false,
+	}
+}
+
+fn parse_program(source: &str) -> Result<ast::Program, ParseError> {
+	let source_info = SourceInfo { source: String::from("parseShell") };
+	Parser::new(source.as_bytes(), &ParserOptions::default(), &source_info).parse_program()
+}
+
+fn syntax_error(err: &ParseError, source: &str) -> ShellSyntaxError {
+	let (line, column) = match err {
+		ParseError::ParsingNearToken(token) => {
+			let start = &token.location().start;
+			(start.line, start.column)
+		},
+		ParseError::Tokenizing { position: Some(position), .. } => (position.line, position.column),
+		ParseError::Tokenizing { position: None, .. } | ParseError::ParsingAtEndOfInput => {
+			end_position(source)
+		},
+	};
+	ShellSyntaxError { message: err.to_string(), line: line as u32, column: column as u32 }
+}
+
+/// 1-based line and column just past the end of `source`.
+fn end_position(source: &str) -> (usize, usize) {
+	let line = source.matches('\n').count() + 1;
+	let last_line = source.rsplit('\n').next().unwrap_or_default();
+	(line, last_line.chars().count() + 1)
+}
+
+#[derive(Default)]
+struct Summary {
+	error:     Option<ShellSyntaxError>,
+	commands:  Vec<ShellParsedCommand>,
+	pipelines: Vec<ShellParsedPipeline>,
+	heredocs:  Vec<ShellParsedHeredoc>,
+	functions: Vec<String>,
+	/// Enclosing constructs of the node being visited.
+	context:   Vec<&'static str>,
+	/// Depth of re-parsed command substitutions; positions are meaningless
+	/// inside them.
+	reparsed:  usize,
+}
+
+impl Summary {
+	fn into_result(self) -> ShellParseResult {
+		ShellParseResult {
+			error:     self.error,
+			commands:  self.commands,
+			pipelines: self.pipelines,
+			heredocs:  self.heredocs,
+			functions: self.functions,
+		}
+	}
+
+	fn nested(&mut self, context: &'static str, visit: impl FnOnce(&mut Self)) {
+		self.context.push(context);
+		visit(self);
+		self.context.pop();
+	}
+
+	fn context(&self) -> Vec<String> {
+		self.context.iter().map(|c| (*c).to_string()).collect()
+	}
+
+	fn program(&mut self, program: &ast::Program) {
+		for list in &program.complete_commands {
+			self.compound_list(list);
+		}
+	}
+
+	fn compound_list(&mut self, list: &ast::CompoundList) {
+		for ast::CompoundListItem(and_or, separator) in &list.0 {
+			let background = matches!(separator, ast::SeparatorOperator::Async);
+			self.pipeline(&and_or.first, background, None);
+			for next in &and_or.additional {
+				match next {
+					ast::AndOr::And(pipeline) => self.pipeline(pipeline, background, Some("&&")),
+					ast::AndOr::Or(pipeline) => self.pipeline(pipeline, background, Some("||")),
+				}
+			}
+		}
+	}
+
+	fn pipeline(&mut self, pipeline: &ast::Pipeline, background: bool, operator: Option<&str>) {
+		let index = self.pipelines.len();
+		self.pipelines.push(ShellParsedPipeline {
+			commands: Vec::new(),
+			length: pipeline.seq.len() as u32,
+			negated: pipeline.bang,
+			background,
+			operator: operator.map(str::to_string),
+			context: self.context(),
+		});
+		for command in &pipeline.seq {
+			if let Some(command) = self.command(command, index as u32) {
+				self.pipelines[index].commands.push(command);
+			}
+		}
+	}
+
+	/// Visit a pipeline stage, returning its index in `commands` if simple.
+	fn command(&mut self, command: &ast::Command, pipeline: u32) -> Option<u32> {
+		match command {
+			ast::Command::Simple(simple) => Some(self.simple_command(simple, pipeline)),
+			ast::Command::Compound(compound, redirects) => {
+				self.compound_command(compound);
+				for redirect in redirects.iter().flat_map(|list| &list.0) {
+					self.redirect(redirect, None);
+				}
+				None
+			},
+			ast::Command::Function(function) => {
+				self.functions.push(function.fname.value.clone());
+				let ast::FunctionBody(body, redirects) = &function.body;
+				self.nested("function", |s| {
+					s.compound_command(body);
+					for redirect in redirects.iter().flat_map(|list| &list.0) {
+						s.redirect(redirect, None);
+					}
+				});
+				None
+			},
+			ast::Command::ExtendedTest(test) => {
+				self.extended_test(&test.expr);
+				None
+			},
+		}
+	}
+
+	fn simple_command(&mut self, command: &ast::SimpleCommand, pipeline: u32) -> u32 {
+		let index = self.commands.len() as u32;
+		let position = if self.reparsed == 0 {
+			command.location()
+		} else {
+			None
+		};
+		self.commands.push(ShellParsedCommand {
+			words: Vec::new(),
+			assignments: Vec::new(),
+			redirects: Vec::new(),
+			pipeline,
+			context: self.context(),
+			line: position.as_ref().map(|loc| loc.start.line as u32),
+			column: position.as_ref().map(|loc| loc.start.column as u32),
+		});
+
+		let items = command
+			.prefix
+			.iter()
+			.flat_map(|prefix| &prefix.0)
+			.chain(command.suffix.iter().flat_map(|suffix| &suffix.0));
+		if let Some(name) = &command.word_or_name {
+			self.commands[index as usize].words.push(name.value.clone());
+			self.word(&name.value);
+		}
+		for item in items {
+			match item {
+				ast::CommandPrefixOrSuffixItem::Word(word) => {
+					self.commands[index as usize].words.push(word.value.clone());
+					self.word(&word.value);
+				},
+				ast::CommandPrefixOrSuffixItem::AssignmentWord(_, word) => {
+					self.commands[index as usize]
+						.assignments
+						.push(word.value.clone());
+					self.word(&word.value);
+				},
+				ast::CommandPrefixOrSuffixItem::IoRedirect(redirect) => {
+					let redirect = self.redirect(redirect, Some(index));
+					self.commands[index as usize].redirects.push(redirect);
+				},
+				ast::CommandPrefixOrSuffixItem::ProcessSubstitution(kind, subshell) => {
+					self.commands[index as usize]
+						.words
+						.push(format!("{kind}{subshell}"));
+					self.nested("processSubstitution", |s| s.compound_list(&subshell.list));
+				},
+			}
+		}
+		index
+	}
+
+	fn compound_command(&mut self, command: &ast::CompoundCommand) {
+		match command {
+			ast::CompoundCommand::Arithmetic(_) | ast::CompoundCommand::ArithmeticForClause(_) => {},
+			ast::CompoundCommand::BraceGroup(group) => self.compound_list(&group.list),
+			ast::CompoundCommand::Subshell(subshell) => {
+				self.nested("subshell", |s| s.compound_list(&subshell.list));
+			},
+			ast::CompoundCommand::ForClause(for_clause) => {
+				for value in for_clause.values.iter().flatten() {
+					self.word(&value.value);
+				}
+				self.compound_list(&for_clause.body.list);
+			},
+			ast::CompoundCommand::CaseClause(case) => {
+				self.word(&case.value.value);
+				for item in &case.cases {
+					if let Some(list) = &item.cmd {
+						self.compound_list(list);
+					}
+				}
+			},
+			ast::CompoundCommand::IfClause(if_clause) => {
+				self.compound_list(&if_clause.condition);
+				self.compound_list(&if_clause.then);
+				for else_clause in if_clause.elses.iter().flatten() {
+					if let Some(condition) = &else_clause.condition {
+						self.compound_list(condition);
+					}
+					self.compound_list(&else_clause.body);
+				}
+			},
+			ast::CompoundCommand::WhileClause(clause) | ast::CompoundCommand::UntilClause(clause) => {
+				self.compound_list(&clause.0);
+				self.compound_list(&clause.1.list);
+			},
+		}
+	}
+
+	fn extended_test(&mut self, expr: &ast::ExtendedTestExpr) {
+		match expr {
+			ast::ExtendedTestExpr::And(left, right) | ast::ExtendedTestExpr::Or(left, right) => {
+				self.extended_test(left);
+				self.extended_test(right);
+			},
+			ast::ExtendedTestExpr::Not(inner) | ast::ExtendedTestExpr::Parenthesized(inner) => {
+				self.extended_test(inner);
+			},
+			ast::ExtendedTestExpr::UnaryTest(_, word) => self.word(&word.value),
+			ast::ExtendedTestExpr::BinaryTest(_, left, right) => {
+				self.word(&left.value);
+				self.word(&right.value);
+			},
+		}
+	}
+
+	fn redirect(&mut self, redirect: &ast::IoRedirect, command: Option<u32>) -> ShellParsedRedirect {
+		match redirect {
+			ast::IoRedirect::File(fd, kind, target) => {
+				match target {
+					ast::IoFileRedirectTarget::Filename(word)
+					| ast::IoFileRedirectTarget::Duplicate(word) => self.word(&word.value),
+					ast::IoFileRedirectTarget::ProcessSubstitution(_, subshell) => {
+						self.nested("processSubstitution", |s| s.compound_list(&subshell.list));
+					},
+					ast::IoFileRedirectTarget::Fd(_) => {},
+				}
+				ShellParsedRedirect {
+					fd:       *fd,
+					operator: kind.to_string(),
+					target:   target.to_string(),
+				}
+			},
+			ast::IoRedirect::HereDocument(fd, heredoc) => {
+				if heredoc.requires_expansion {
+					self.word(&heredoc.doc.value);
+				}
+				self.heredocs.push(ShellParsedHeredoc {
+					delimiter: heredoc.here_end.value.clone(),
+					body: heredoc.doc.value.clone(),
+					expands: heredoc.requires_expansion,
+					command,
+				});
+				ShellParsedRedirect {
+					fd:       *fd,
+					operator: if heredoc.remove_tabs { "<<-" } else { "<<" }.to_string(),
+					target:   heredoc.here_end.value.clone(),
+				}
+			},
+			ast::IoRedirect::HereString(fd, word) => {
+				self.word(&word.value);
+				ShellParsedRedirect {
+					fd:       *fd,
+					operator: "<<<".to_string(),
+					target:   word.value.clone(),
+				}
+			},
+			ast::IoRedirect::OutputAndError(word, append) => {
+				self.word(&word.value);
+				ShellParsedRedirect {
+					fd:       None,
+					operator: if *append { "&>>" } else { "&>" }.to_string(),
+					target:   word.value.clone(),
+				}
+			},
+		}
+	}
+
+	/// Visit command substitutions inside an unexpanded word.
+	fn word(&mut self, text: &str) {
+		if !text.contains("$(") && !text.contains('`') {
+			return;
+		}
+		if let Ok(pieces) = word::parse(text, &ParserOptions::default()) {
+			self.word_pieces(&pieces);
+		}
+	}
+
+	fn word_pieces(&mut self, pieces: &[WordPieceWithSource]) {
+		for piece in pieces {
+			match &piece.piece {
+				WordPiece::DoubleQuotedSequence(inner)
+				| WordPiece::GettextDoubleQuotedSequence(inner) => {
+					self.word_pieces(inner);
+				},
+				WordPiece::CommandSubstitution(source)
+				| WordPiece::BackquotedCommandSubstitution(source) => {
+					if let Ok(program) = parse_program(source) {
+						self.reparsed += 1;
+						self.nested("commandSubstitution", |s| s.program(&program));
+						self.reparsed -= 1;
+					}
+				},
+				_ => {},
+			}
+		}
+	}
+}
@@ -0,0 +1,211 @@
+//! Command policy for persistent brush shells.
+//!
+//! A deny list plus an optional JS callback, consulted by brush-core before
+//! every simple command, including those run from functions, `eval` and
+//! subshells.
+
+use std::{future::Future, path::Path, pin::Pin, sync::Arc};
+
+use brush_core::interfaces::{
+	CommandKind, CommandPolicy, CommandPolicyDecision, CommandPolicyRequest,
+};
+use napi::{Status, bindgen_prelude::*, threadsafe_function::ThreadsafeFunction};
+use napi_derive::napi;
+use parking_lot::RwLock;
+
+/// Exit code reported for blocked commands unless the policy overrides it.
+const DEFAULT_DENY_EXIT_CODE: u8 = 126;
+
+/// Static rules for a shell's command policy.
+#[napi(object)]
+pub struct ShellCommandPolicy {
+	/// Commands to block, as a command name (`"sudo"`) or an argv prefix
+	/// (`"rm -rf /"`). Names also match paths ending in that name.
+	pub deny:      Option<Vec<String>>,
+	/// Exit code for commands blocked by `deny` (default 126).
+	#[napi(js_name = "exitCode")]
+	pub exit_code: Option<u32>,
+}
+
+/// A command passed to the policy callback.
+#[napi(object)]
+pub struct ShellCommandInfo {
+	/// Expanded arguments, starting with the command name.
+	pub args: Vec<String>,
+	/// How the name resolved: `builtin`, `function`, `external` or `notFound`.
+	pub kind: String,
+	/// Shell working directory.
+	pub cwd:  String,
+}
+
+/// Verdict returned by the policy callback.
+#[napi(object)]
+pub struct ShellPolicyDecision {
+	/// One of `allow`, `deny` or `rewrite`.
+	pub action:    String,
+	/// Exit code for `deny` (default 126).
+	#[napi(js_name = "exitCode")]
+	pub exit_code: Option<u32>,
+	/// Message written to stderr for `deny`.
+	pub message:   Option<String>,
+	/// Replacement arguments for `rewrite`.
+	pub args:      Option<Vec<String>>,
+}
+
+type PolicyCallbackReturn =
+	Either<Promise<Option<ShellPolicyDecision>>, Option<ShellPolicyDecision>>;
+
+/// JS callback consulted for commands not blocked by the deny list.
+///
+/// Weak, so a registered policy does not keep the Node.js process alive.
+pub type PolicyCallback = ThreadsafeFunction<
+	ShellCommandInfo,
+	PolicyCallbackReturn,
+	ShellCommandInfo,
+	Status,
+	false,
+	true,
+>;
+
+/// Compiled policy rules and callback.
+struct ActivePolicy {
+	deny:           Vec<Vec<String>>,
+	deny_exit_code: u8,
+	callback:       Option<PolicyCallback>,
+}
+
+/// Policy slot installed in every session of a `Shell`; replacing the
+/// contents takes effect for the next command.
+#[derive(Default)]
+pub struct CommandPolicySlot {
+	active: RwLock<Option<Arc<ActivePolicy>>>,
+}
+
+impl CommandPolicySlot {
+	/// Replace the policy; with neither rules nor a callback, all commands run.
+	pub fn set(&self, policy: Option<ShellCommandPolicy>, callback: Option<PolicyCallback>) {
+		let active = if policy.is_none() && callback.is_none() {
+			None
+		} else {
+			let policy = policy.unwrap_or(ShellCommandPolicy { deny: None, exit_code: None });
+			Some(Arc::new(ActivePolicy {
+				deny: policy
+					.deny
+					.unwrap_or_default()
+					.iter()
+					.map(|rule| {
+						rule
+							.split_whitespace()
+							.map(str::to_string)
+							.collect::<Vec<_>>()
+					})
+					.filter(|rule| !rule.is_empty())
+					.collect(),
+				deny_exit_code: exit_code_or_default(policy.exit_code),
+				callback,
+			}))
+		};
+		*self.active.write() = active;
+	}
+}
+
+impl CommandPolicy for CommandPolicySlot {
+	fn check<'a>(
+		&'a self,
+		request: CommandPolicyRequest<'a>,
+	) -> Pin<Box<dyn Future<Output = CommandPolicyDecision> + Send + 'a>> {
+		let active = self.active.read().clone();
+		Box::pin(async move {
+			match active {
+				Some(active) => active.check(request).await,
+				None => CommandPolicyDecision::Allow,
+			}
+		})
+	}
+}
+
+impl ActivePolicy {
+	async fn check(&self, request: CommandPolicyRequest<'_>) -> CommandPolicyDecision {
+		if self
+			.deny
+			.iter()
+			.any(|rule| rule_matches(rule, request.args))
+		{
+			let name = request.args.first().map_or("", String::as_str);
+			return CommandPolicyDecision::Deny {
+				exit_code: self.deny_exit_code,
+				message:   Some(format!("{name}: blocked by shell policy")),
+			};
+		}
+
+		let Some(callback) = self.callback.as_ref() else {
+			return CommandPolicyDecision::Allow;
+		};
+		let info = ShellCommandInfo {
+			args: request.args.to_vec(),
+			kind: kind_name(request.kind).to_string(),
+			cwd:  request.working_dir.to_string_lossy().into_owned(),
+		};
+		// Fail closed: a throwing or malformed callback blocks the command.
+		match call_policy(callback, info).await {
+			Ok(decision) => decision,
+			Err(err) => CommandPolicyDecision::Deny {
+				exit_code: DEFAULT_DENY_EXIT_CODE,
+				message:   Some(format!("shell policy callback failed: {err}")),
+			},
+		}
+	}
+}
+
+async fn call_policy(
+	callback: &PolicyCallback,
+	info: ShellCommandInfo,
+) -> Result<CommandPolicyDecision> {
+	let decision = match callback.call_async(info).await? {
+		Either::A(promise) => promise.await?,
+		Either::B(decision) => decision,
+	};
+	let Some(decision) = decision else {
+		return Ok(CommandPolicyDecision::Allow);
+	};
+	match decision.action.as_str() {
+		"allow" => Ok(CommandPolicyDecision::Allow),
+		"deny" => Ok(CommandPolicyDecision::Deny {
+			exit_code: exit_code_or_default(decision.exit_code),
+			message:   decision.message,
+		}),
+		"rewrite" => match decision.args {
+			Some(args) if !args.is_empty() => Ok(CommandPolicyDecision::Rewrite(args)),
+			_ => Err(Error::from_reason("rewrite requires non-empty args")),
+		},
+		action => Err(Error::from_reason(format!("unknown policy action: {action}"))),
+	}
+}
+
+/// Match an argv prefix rule; the first word also matches a path's file name.
+fn rule_matches(rule: &[String], args: &[String]) -> bool {
+	let (Some((rule_name, rule_rest)), Some((name, rest))) =
+		(rule.split_first(), args.split_first())
+	else {
+		return false;
+	};
+	let name_matches = name == rule_name
+		|| Path::new(name)
+			.file_name()
+			.is_some_and(|file_name| file_name == rule_name.as_str());
+	name_matches && rest.len() >= rule_rest.len() && rest.iter().zip(rule_rest).all(|(a, b)| a == b)
+}
+
+fn exit_code_or_default(exit_code: Option<u32>) -> u8 {
+	exit_code.map_or(DEFAULT_DENY_EXIT_CODE, |code| u8::try_from(code).unwrap_or(u8::MAX))
+}
+
+/// Name of a command kind as exposed to JS.
+pub const fn kind_name(kind: CommandKind) -> &'static str {
+	match kind {
+		CommandKind::Builtin => "builtin",
+		CommandKind::Function => "function",
+		CommandKind::External => "external",
+		CommandKind::NotFound => "notFound",
+	}
+}
@@ -0,0 +1,272 @@
+//! Native session snapshots for persistent brush shells.
+//!
+//! Captures variables (with attributes and arrays), functions, aliases,
+//! `set -o`/`shopt` options and the working directory straight from the
+//! brush-core shell, and restores them without sourcing shell code.
+
+use std::collections::{BTreeMap, HashMap};
+
+use brush_core::{
+	Shell as BrushShell, ShellValue, ShellVariable,
+	namedoptions::{self, ShellOptionKind},
+	variables::{ShellValueUnsetType, ShellVariableUpdateTransform},
+};
+use napi::{Error, Result};
+use napi_derive::napi;
+
+use super::should_skip_env_var;
+
+/// Snapshot format version written by [`capture`].
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Serializable state of a shell session.
+#[derive(Clone)]
+#[napi(object)]
+pub struct ShellSnapshot {
+	/// Snapshot format version.
+	pub version:       u32,
+	/// Working directory.
+	pub cwd:           String,
+	/// Shell variables, excluding dynamic and shell-managed ones.
+	pub variables:     Vec<ShellSnapshotVariable>,
+	/// Function definitions.
+	pub functions:     Vec<ShellSnapshotFunction>,
+	/// Aliases keyed by name.
+	pub aliases:       HashMap<String, String>,
+	/// `set -o` options keyed by name.
+	pub set_options:   HashMap<String, bool>,
+	/// `shopt` options keyed by name.
+	pub shopt_options: HashMap<String, bool>,
+	/// Directory stack from `pushd`, oldest first.
+	pub dir_stack:     Vec<String>,
+}
+
+/// A variable captured in a [`ShellSnapshot`].
+#[derive(Clone)]
+#[napi(object)]
+pub struct ShellSnapshotVariable {
+	/// Variable name.
+	pub name:       String,
+	/// Attribute flags as printed by `declare -p` (e.g. `"ax"`).
+	pub attributes: String,
+	/// Scalar value; absent for arrays and declared-but-unset variables.
+	pub value:      Option<String>,
+	/// Array elements keyed by index or key; absent for scalars.
+	pub elements:   Option<HashMap<String, String>>,
+}
+
+/// A function captured in a [`ShellSnapshot`].
+#[derive(Clone)]
+#[napi(object)]
+pub struct ShellSnapshotFunction {
+	/// Function name.
+	pub name:     String,
+	/// Function body, starting at the compound command.
+	pub body:     String,
+	/// Whether the function is exported to child shells.
+	pub exported: bool,
+}
+
+/// Capture the current state of `shell`.
+pub fn capture(shell: &BrushShell) -> ShellSnapshot {
+	let mut variables: Vec<ShellSnapshotVariable> = shell
+		.env
+		.iter()
+		.filter(|(name, var)| var.is_enumerable() && !should_skip_env_var(name))
+		.filter_map(|(name, var)| capture_variable(shell, name, var))
+		.collect();
+	variables.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+	let mut functions: Vec<ShellSnapshotFunction> = shell
+		.funcs()
+		.iter()
+		.map(|(name, registration)| ShellSnapshotFunction {
+			name:     name.clone(),
+			body:     registration.definition().body.to_string(),
+			exported: registration.is_exported(),
+		})
+		.collect();
+	functions.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+	ShellSnapshot {
+		version: SNAPSHOT_VERSION,
+		cwd: shell.working_dir().to_string_lossy().into_owned(),
+		variables,
+		functions,
+		aliases: shell.aliases.clone(),
+		set_options: capture_options(shell, ShellOptionKind::SetO),
+		shopt_options: capture_options(shell, ShellOptionKind::Shopt),
+		dir_stack: shell
+			.directory_stack
+			.iter()
+			.map(|dir| dir.to_string_lossy().into_owned())
+			.collect(),
+	}
+}
+
+fn capture_variable(
+	shell: &BrushShell,
+	name: &str,
+	var: &ShellVariable,
+) -> Option<ShellSnapshotVariable> {
+	let (value, elements) = match var.value() {
+		ShellValue::Dynamic { .. } => return None,
+		ShellValue::Unset(_) => (None, None),
+		ShellValue::String(value) => (Some(value.clone()), None),
+		ShellValue::IndexedArray(values) => (
+			None,
+			Some(
+				values
+					.iter()
+					.map(|(k, v)| (k.to_string(), v.clone()))
+					.collect(),
+			),
+		),
+		ShellValue::AssociativeArray(values) => (None, Some(values.clone().into_iter().collect())),
+	};
+	Some(ShellSnapshotVariable {
+		name: name.to_string(),
+		attributes: var.attribute_flags(shell),
+		value,
+		elements,
+	})
+}
+
+fn capture_options(shell: &BrushShell, kind: ShellOptionKind) -> HashMap<String, bool> {
+	namedoptions::options(kind)
+		.iter()
+		.map(|option| (option.name.to_string(), option.definition.get(&shell.options)))
+		.collect()
+}
+
+/// Restore `snapshot` into `shell`, overriding any existing definitions.
+///
+/// Variables that are already readonly in `shell` (for example, set by the
+/// session's `snapshotPath` script) keep their current value, as they would
+/// for an assignment; snapshot variables marked `r` are restored readonly. A
+/// working directory that no longer exists is skipped rather than treated as
+/// an error.
+pub fn restore(shell: &mut BrushShell, snapshot: &ShellSnapshot) -> Result<()> {
+	if snapshot.version != SNAPSHOT_VERSION {
+		return Err(Error::from_reason(format!(
+			"Unsupported shell snapshot version: {}",
+			snapshot.version
+		)));
+	}
+
+	restore_options(shell, ShellOptionKind::SetO, &snapshot.set_options);
+	restore_options(shell, ShellOptionKind::Shopt, &snapshot.shopt_options);
+
+	for variable in &snapshot.variables {
+		if should_skip_env_var(&variable.name)
+			|| shell
+				.env
+				.get(&variable.name)
+				.is_some_and(|(_, existing)| existing.is_readonly())
+		{
+			continue;
+		}
+		shell
+			.env
+			.set_global(variable.name.as_str(), restore_variable(variable)?)
+			.map_err(|err| {
+				Error::from_reason(format!("Failed to restore variable {}: {err}", variable.name))
+			})?;
+	}
+
+	for function in &snapshot.functions {
+		shell
+			.define_func_from_str(function.name.as_str(), &format!("() {}", function.body))
+			.map_err(|err| {
+				Error::from_reason(format!("Failed to restore function {}: {err}", function.name))
+			})?;
+		if function.exported
+			&& let Some(registration) = shell.func_mut(&function.name)
+		{
+			registration.export();
+		}
+	}
+
+	shell.aliases.extend(
+		snapshot
+			.aliases
+			.iter()
+			.map(|(name, value)| (name.clone(), value.clone())),
+	);
+	shell.directory_stack = snapshot.dir_stack.iter().map(Into::into).collect();
+	let _ = shell.set_working_dir(&snapshot.cwd);
+
+	Ok(())
+}
+
+fn restore_options(shell: &mut BrushShell, kind: ShellOptionKind, values: &HashMap<String, bool>) {
+	let options = namedoptions::options(kind);
+	for (name, &value) in values {
+		if let Some(definition) = options.get(name) {
+			definition.set(&mut shell.options, value);
+		}
+	}
+}
+
+fn restore_variable(variable: &ShellSnapshotVariable) -> Result<ShellVariable> {
+	let flags = variable.attributes.as_str();
+	let value = if flags.contains('A') {
+		match &variable.elements {
+			Some(elements) => ShellValue::AssociativeArray(
+				elements
+					.iter()
+					.map(|(k, v)| (k.clone(), v.clone()))
+					.collect(),
+			),
+			None => ShellValue::Unset(ShellValueUnsetType::AssociativeArray),
+		}
+	} else if flags.contains('a') {
+		match &variable.elements {
+			Some(elements) => {
+				let mut values = BTreeMap::new();
+				for (index, value) in elements {
+					let index = index.parse::<u64>().map_err(|_| {
+						Error::from_reason(format!(
+							"Invalid array index {index:?} for variable {}",
+							variable.name
+						))
+					})?;
+					values.insert(index, value.clone());
+				}
+				ShellValue::IndexedArray(values)
+			},
+			None => ShellValue::Unset(ShellValueUnsetType::IndexedArray),
+		}
+	} else {
+		match &variable.value {
+			Some(value) => ShellValue::String(value.clone()),
+			None => ShellValue::Unset(ShellValueUnsetType::Untyped),
+		}
+	};
+
+	let mut var = ShellVariable::new(value);
+	for flag in flags.chars() {
+		match flag {
+			'c' => var.set_update_transform(ShellVariableUpdateTransform::Capitalize),
+			'l' => var.set_update_transform(ShellVariableUpdateTransform::Lowercase),
+			'u' => var.set_update_transform(ShellVariableUpdateTransform::Uppercase),
+			'i' => {
+				var.treat_as_integer();
+			},
+			'n' => {
+				var.treat_as_nameref();
+			},
+			'r' => {
+				var.set_readonly();
+			},
+			't' => {
+				var.enable_trace();
+			},
+			'x' => {
+				var.export();
+			},
+			_ => {},
+		}
+	}
+	Ok(var)
+}
@@ -0,0 +1,60 @@
+//! Structured execution traces for shell runs.
+//!
+//! Converts the brush-core [`CommandTrace`] recorded for a run into plain
+//! objects for JS.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use brush_core::commandtrace::{CommandTrace, TracedCommand};
+use napi_derive::napi;
+
+use super::policy::kind_name;
+
+/// A simple command executed during a traced run.
+#[napi(object)]
+pub struct ShellTraceEntry {
+	/// Expanded arguments, starting with the command name.
+	pub args:            Vec<String>,
+	/// How the name resolved: `builtin`, `function`, `external` or `notFound`.
+	pub kind:            String,
+	/// Resolved executable path for external commands.
+	pub path:            Option<String>,
+	/// Process ID for external commands.
+	pub pid:             Option<i32>,
+	/// Zero-based position within the command's pipeline.
+	pub pipeline_index:  u32,
+	/// Number of commands in the pipeline.
+	pub pipeline_length: u32,
+	/// Start time in milliseconds since the Unix epoch.
+	pub start_ms:        f64,
+	/// End time in milliseconds since the Unix epoch; absent if the command
+	/// never completed.
+	pub end_ms:          Option<f64>,
+	/// Exit code; absent if the command failed to start or never completed.
+	pub exit_code:       Option<i32>,
+}
+
+/// Convert every command recorded so far.
+pub fn entries(trace: &CommandTrace) -> Vec<ShellTraceEntry> {
+	trace.commands().into_iter().map(entry).collect()
+}
+
+fn entry(command: TracedCommand) -> ShellTraceEntry {
+	ShellTraceEntry {
+		args:            command.args,
+		kind:            kind_name(command.kind).to_string(),
+		path:            command.path.map(|path| path.to_string_lossy().into_owned()),
+		pid:             command.pid,
+		pipeline_index:  command.pipeline_index as u32,
+		pipeline_length: command.pipeline_len as u32,
+		start_ms:        epoch_ms(command.start),
+		end_ms:          command.end.map(epoch_ms),
+		exit_code:       command.exit_code.map(i32::from),
+	}
+}
+
+fn epoch_ms(time: SystemTime) -> f64 {
+	time
+		.duration_since(UNIX_EPOCH)
+		.map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
@@ -11,6 +11,7 @@
 import * as fs from "node:fs/promises";
 import type { AgentTool, AgentToolContext, AgentToolResult, AgentToolUpdateCallback } from "@oh-my-pi/pi-agent-core";
 import { StringEnum } from "@oh-my-pi/pi-ai";
+import { recordFileAccess } from "@oh-my-pi/pi-natives";
 import { type Static, Type } from "@sinclair/typebox";
 import { renderPromptTemplate } from "../config/prompt-templates";
 import {
@@ -24,11 +25,6 @@ import hashlineDescription from "../prompts/tools/hashline.md" with { type: "tex
 import patchDescription from "../prompts/tools/patch.md" with { type: "text" };
 import replaceDescription from "../prompts/tools/replace.md" with { type: "text" };
 import type { ToolSession } from "../tools";
-import {
-	invalidateFsScanAfterDelete,
-	invalidateFsScanAfterRename,
-	invalidateFsScanAfterWrite,
-} from "../tools/fs-cache-invalidation";
 import { outputMeta } from "../tools/output-meta";
 import { enforcePlanModeWrite, resolvePlanPath } from "../tools/plan-mode-guard";
 import { applyPatch } from "./applicator";
@@ -535,7 +531,7 @@ export class EditTool implements AgentTool<TInput> {
 
 			const finalContent = bom + restoreLineEndings(result.content, originalEnding);
 			const diagnostics = await this.#writethrough(absolutePath, finalContent, signal, file, batchRequest);
-			invalidateFsScanAfterWrite(absolutePath);
+			recordFileAccess(absolutePath);
 			const diffResult = generateDiffString(originalNormalized, result.content);
 
 			const normative = buildNormativeUpdateInput({
@@ -594,11 +590,9 @@ export class EditTool implements AgentTool<TInput> {
 				allowFuzzy: this.#allowFuzzy,
 			});
 			if (resolvedRename) {
-				invalidateFsScanAfterRename(resolvedPath, resolvedRename);
-			} else if (result.change.type === "delete") {
-				invalidateFsScanAfterDelete(resolvedPath);
-			} else {
-				invalidateFsScanAfterWrite(resolvedPath);
+				recordFileAccess(resolvedRename);
+			} else if (result.change.type !== "delete") {
+				recordFileAccess(resolvedPath);
 			}
 			const effRename = result.change.newPath ? rename : undefined;
 
@@ -722,7 +716,7 @@ export class EditTool implements AgentTool<TInput> {
 
 		const finalContent = bom + restoreLineEndings(result.content, originalEnding);
 		const diagnostics = await this.#writethrough(absolutePath, finalContent, signal, file, batchRequest);
-		invalidateFsScanAfterWrite(absolutePath);
+		recordFileAccess(absolutePath);
 		const diffResult = generateDiffString(normalizedContent, result.content);
 
 		const resultText =
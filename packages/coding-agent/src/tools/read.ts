@@ -2,7 +2,7 @@ import * as fs from "node:fs/promises";
 import path from "node:path";
 import type { AgentTool, AgentToolContext, AgentToolResult, AgentToolUpdateCallback } from "@oh-my-pi/pi-agent-core";
 import type { ImageContent, TextContent } from "@oh-my-pi/pi-ai";
-import { FileType, glob } from "@oh-my-pi/pi-natives";
+import { FileType, glob, recordFileAccess } from "@oh-my-pi/pi-natives";
 import type { Component } from "@oh-my-pi/pi-tui";
 import { Text } from "@oh-my-pi/pi-tui";
 import { ptree, untilAborted } from "@oh-my-pi/pi-utils";
@@ -606,6 +606,10 @@ export class ReadTool implements AgentTool<typeof readSchema, ReadToolDetails> {
 			throw error;
 		}
 
+		if (!isRemoteMountPath(absolutePath)) {
+			recordFileAccess(absolutePath);
+		}
+
 		if (isDirectory) {
 			return this.#readDirectory(absolutePath, limit, signal);
 		}
@@ -8,7 +8,7 @@
 import * as fs from "node:fs/promises";
 import path from "node:path";
 import type { AgentMessage } from "@oh-my-pi/pi-agent-core";
-import { glob } from "@oh-my-pi/pi-natives";
+import { glob, recordFileAccess } from "@oh-my-pi/pi-natives";
 import { formatHashLines } from "../patch/hashline";
 import type { FileMentionMessage } from "../session/messages";
 import { resolveReadPath } from "../tools/path-utils";
@@ -302,6 +302,7 @@ export async function generateFileMentionMessages(
 		const absolutePath = resolveReadPath(resolvedPath, cwd);
 		try {
 			const stat = await Bun.file(absolutePath).stat();
+			recordFileAccess(absolutePath);
 			if (stat.isDirectory()) {
 				const { output, lineCount } = await buildDirectoryListing(absolutePath);
 				files.push({ path: resolvedPath, content: output, lineCount });
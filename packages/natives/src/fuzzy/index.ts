@@ -0,0 +1,20 @@
+/**
+ * In-memory fuzzy ranking with fzf-style scoring.
+ */
+
+import { native } from "../native";
+
+export type {
+	FuzzyCandidate,
+	FuzzyCandidatesConstructor,
+	FuzzyMatch,
+	FuzzyMatchOptions,
+	FuzzyMatchResult,
+} from "./types";
+
+/**
+ * Rank candidates against a fuzzy query, best first, with match positions.
+ * Pass a `FuzzyCandidates` handle to reuse a large list across keystrokes.
+ */
+export const { FuzzyCandidates, fuzzyMatch } = native;
+export type FuzzyCandidates = import("./types").FuzzyCandidates;
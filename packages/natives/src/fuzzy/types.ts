@@ -0,0 +1,75 @@
+/**
+ * Types for in-memory fuzzy ranking.
+ */
+
+/** A candidate with a caller-defined key returned alongside its match. */
+export interface FuzzyCandidate {
+	/** Text to match against. */
+	text: string;
+	/** Identifier returned with the match. */
+	key?: string;
+}
+
+/** Options for ranking candidates. */
+export interface FuzzyMatchOptions {
+	/** Maximum number of matches to return (default: 100). */
+	maxResults?: number;
+	/** Treat candidates as paths: `/` separates words and matches within the file name rank higher (default: false). */
+	paths?: boolean;
+}
+
+/** A ranked candidate. */
+export interface FuzzyMatch {
+	/** Candidate text. */
+	text: string;
+	/** Candidate key, if one was given. */
+	key?: string;
+	/** Index of the candidate in the input list. */
+	index: number;
+	/** Match quality score (higher is better). */
+	score: number;
+	/** Positions of the matched characters in `text`, as sorted string indices (for highlighting). */
+	positions: number[];
+}
+
+/** Result of ranking candidates. */
+export interface FuzzyMatchResult {
+	/** Best matches, best first (up to `maxResults`). */
+	matches: FuzzyMatch[];
+	/** Total number of matching candidates. */
+	totalMatches: number;
+}
+
+/**
+ * A candidate list prepared once and ranked on every keystroke.
+ * While the query only grows, only the previous matches are rescanned.
+ */
+export interface FuzzyCandidates {
+	/** Number of candidates. */
+	readonly length: number;
+	/** Rank the candidates against `query` on a worker thread. */
+	match(query: string, options?: FuzzyMatchOptions): Promise<FuzzyMatchResult>;
+}
+
+/** Native fuzzy candidate list constructor. */
+export interface FuzzyCandidatesConstructor {
+	new (candidates: Array<string | FuzzyCandidate>): FuzzyCandidates;
+}
+
+declare module "../bindings" {
+	interface NativeBindings {
+		/** Candidate list handle for repeated `fuzzyMatch()` calls. */
+		FuzzyCandidates: FuzzyCandidatesConstructor;
+		/**
+		 * Rank candidates against a fuzzy query on a worker thread; whitespace-separated words must all match.
+		 * @param query Fuzzy query (case-insensitive).
+		 * @param candidates A `FuzzyCandidates` handle, or strings / `{ text, key }` objects to rank once.
+		 * @param options Result limit and path matching.
+		 */
+		fuzzyMatch(
+			query: string,
+			candidates: FuzzyCandidates | Array<string | FuzzyCandidate>,
+			options?: FuzzyMatchOptions,
+		): Promise<FuzzyMatchResult>;
+	}
+}
@@ -4,21 +4,30 @@
 
 import * as path from "node:path";
 import { native } from "../native";
-import type { GlobMatch, GlobOptions, GlobResult } from "./types";
+import type { FsWatcher, GlobMatch, GlobOptions, GlobResult, WatchEvent, WatchOptions } from "./types";
 
-export type { GlobMatch, GlobOptions, GlobResult } from "./types";
+export type {
+	FsWatcher,
+	GlobMatch,
+	GlobOptions,
+	GlobResult,
+	TypeFilterOptions,
+	WatchEvent,
+	WatchOptions,
+} from "./types";
 export { FileType } from "./types";
 
 /**
- * Find files matching a glob pattern.
+ * Find files matching glob patterns.
  * Respects .gitignore by default.
  */
 export async function glob(options: GlobOptions, onMatch?: (match: GlobMatch) => void): Promise<GlobResult> {
 	const searchPath = path.resolve(options.path);
-	const pattern = options.pattern || "*";
+	const pattern = options.pattern || (options.patterns?.length ? undefined : "*");
 
 	// Convert simple patterns to recursive globs if needed
-	const globPattern = pattern.includes("/") || pattern.startsWith("**") ? pattern : `**/${pattern}`;
+	const globPattern =
+		pattern === undefined || pattern.includes("/") || pattern.startsWith("**") ? pattern : `**/${pattern}`;
 
 	// napi-rs ThreadsafeFunction passes (error, value) - skip callback on error
 	const cb = onMatch ? (err: Error | null, m: GlobMatch) => !err && onMatch(m) : undefined;
@@ -28,6 +37,7 @@ export async function glob(options: GlobOptions, onMatch?: (match: GlobMatch) =>
 			...options,
 			path: searchPath,
 			pattern: globPattern,
+			ignoreFiles: options.ignoreFiles?.map(file => path.resolve(file)),
 			hidden: options.hidden ?? false,
 			gitignore: options.gitignore ?? true,
 		},
@@ -38,9 +48,33 @@ export async function glob(options: GlobOptions, onMatch?: (match: GlobMatch) =>
 /**
  * Invalidate the filesystem scan cache.
  *
- * When called with a path, removes entries for roots containing that path.
- * When called without a path, clears the entire cache.
+ * Watched roots pick up changes on their own; call this to see a change at once. With a path, watched roots
+ * containing it rescan just that path and other entries for those roots are removed; without one, watched roots
+ * are rescanned and everything else is cleared.
  */
 export function invalidateFsScanCache(path?: string): void {
 	native.invalidateFsScanCache(path);
 }
+
+/**
+ * Record that a file or directory was read, edited or mentioned.
+ *
+ * `glob()` and `fuzzyFind()` with `frecency` rank recorded paths higher. Scores decay over a few days and are
+ * persisted per project root. Returns at once; the access is applied in the background, before any later search.
+ */
+export function recordFileAccess(path: string): void {
+	native.recordFileAccess(path);
+}
+
+/**
+ * Watch a directory for created, modified and removed entries.
+ * Uses the same hidden and .gitignore rules as `glob()`.
+ */
+export function watch(
+	dir: string,
+	onEvent: (event: WatchEvent) => void,
+	options?: WatchOptions,
+): Promise<FsWatcher> {
+	// napi-rs ThreadsafeFunction passes (error, value) - skip callback on error
+	return native.watch(path.resolve(dir), (err, event) => !err && onEvent(event), options);
+}
@@ -13,10 +13,35 @@ export const enum FileType {
 	Symlink = 3,
 }
 
+/**
+ * File type filter shared by `glob()`, `grep()` and `fuzzyFind()`, using ripgrep's type list (`rg --type-list`).
+ * Types match file names only; directories are never filtered out.
+ */
+export interface TypeFilterOptions {
+	/** Only include files of this type (e.g. "rust", "ts", "proto"). Unknown names match as a file extension. */
+	type?: string;
+	/** Extra type definitions, as `name:glob` or `name:include:a,b` (like `rg --type-add`). Names are case-insensitive. */
+	typeAdd?: string[];
+	/** Exclude files of these types (like `rg --type-not`). Unknown names are rejected. */
+	typeNot?: string[];
+}
+
 /** Options for discovering files and directories. */
-export interface GlobOptions extends Cancellable {
-	/** Glob pattern to match (e.g., `*.ts`). */
-	pattern: string;
+export interface GlobOptions extends Cancellable, TypeFilterOptions {
+	/** Glob pattern to match (e.g., `*.ts`). Without `pattern` or `patterns`, every entry matches. */
+	pattern?: string;
+	/**
+	 * More glob patterns, tried after `pattern`. A `!` prefix drops matches of earlier patterns; the last matching
+	 * pattern decides (e.g. `["*.ts", "*.tsx", "!*.test.ts"]`).
+	 */
+	patterns?: string[];
+	/**
+	 * Gitignore-style rules, relative to the search path, for entries to leave out (`!` re-includes). Excluding a
+	 * directory excludes its contents (e.g. `["generated/", "**\/__tests__/"]`).
+	 */
+	exclude?: string[];
+	/** Extra gitignore-style files (paths relative to the working directory) whose rules apply like `exclude`. */
+	ignoreFiles?: string[];
 	/** Directory to search. */
 	path: string;
 	/** Filter by file type: "file", "dir", or "symlink". */
@@ -31,6 +56,11 @@ export interface GlobOptions extends Cancellable {
 	cache?: boolean;
 	/** Sort results by mtime (most recent first) before applying limit. */
 	sortByMtime?: boolean;
+	/**
+	 * Sort results by frecency before applying limit: entries recorded with `recordFileAccess()` first (more often and
+	 * more recently accessed ranks higher), then recently modified ones.
+	 */
+	frecency?: boolean;
 	/** Include node_modules entries even when pattern does not mention node_modules. */
 	includeNodeModules?: boolean;
 }
@@ -53,15 +83,50 @@ export interface GlobResult {
 	totalMatches: number;
 }
 
+/** Options for watching a directory. */
+export interface WatchOptions {
+	/** Include hidden files (default: false). */
+	hidden?: boolean;
+	/** Respect .gitignore files (default: true). */
+	gitignore?: boolean;
+}
+
+/** A change reported by `watch()`. */
+export interface WatchEvent {
+	/** What happened to the entry. */
+	kind: "create" | "modify" | "remove";
+	/** Relative path from the watched root. */
+	path: string;
+	/** Filesystem type; for removals, the last known type. */
+	fileType: FileType;
+	/** Modification time in milliseconds since epoch, if available. */
+	mtime?: number;
+}
+
+/** Handle for a running `watch()`. */
+export interface FsWatcher {
+	/** Stop watching. Events already queued may still be delivered. */
+	close(): void;
+}
+
 declare module "../bindings" {
 	interface NativeBindings {
 		/**
-		 * Find filesystem entries matching a glob pattern.
+		 * Find filesystem entries matching glob patterns.
 		 * @param options Search options that control globbing and filters.
 		 * @param onMatch Optional callback for streaming matches as they are found.
 		 */
 		glob(options: GlobOptions, onMatch?: TsFunc<GlobMatch>): Promise<GlobResult>;
 		/** Invalidate the filesystem scan cache for the given path (or all caches if omitted). */
 		invalidateFsScanCache(path?: string): void;
+		/** Record that a file or directory was read, edited or mentioned, for `frecency` ranking. */
+		recordFileAccess(path: string): void;
+		/**
+		 * Watch a directory for created, modified and removed entries.
+		 * @param path Directory to watch.
+		 * @param onEvent Called for each change once a burst of changes settles.
+		 * @param options Hidden file and gitignore handling.
+		 */
+		watch(path: string, onEvent: TsFunc<WatchEvent>, options?: WatchOptions): Promise<FsWatcher>;
 	}
 }
@@ -11,9 +11,15 @@ import type {
 	GrepMatch,
 	GrepOptions,
 	GrepResult,
+	GrepSkippedFile,
 	GrepSummary,
+	PatternOptions,
+	ReplaceFileResult,
+	ReplaceOptions,
+	ReplaceResult,
 	SearchOptions,
 	SearchResult,
+	Submatch,
 } from "./types";
 
 export type {
@@ -24,9 +30,15 @@ export type {
 	GrepMatch,
 	GrepOptions,
 	GrepResult,
+	GrepSkippedFile,
 	GrepSummary,
+	PatternOptions,
+	ReplaceFileResult,
+	ReplaceOptions,
+	ReplaceResult,
 	SearchOptions,
 	SearchResult,
+	Submatch,
 };
 
 /**
@@ -38,6 +50,36 @@ export async function grep(options: GrepOptions, onMatch?: (match: GrepMatch) =>
 	return native.grep(options, cb);
 }
 
+/**
+ * Search files like `grep()`, yielding ripgrep `--json` messages: `begin`, `match`, `context` and `end` for each file
+ * with matches, then a `summary`. Each string is one JSON document, so joining them with newlines gives output that
+ * tools reading `rg --json` accept.
+ *
+ * Files are searched in parallel unless `maxCount` or `offset` is set, so their order varies. The search pauses
+ * while the consumer falls behind, keeping memory flat, and stops when the loop exits early. `mode` is ignored.
+ */
+export async function* grepJson(options: GrepOptions): AsyncGenerator<string, void, undefined> {
+	const stream = native.grepJson(options);
+	try {
+		for (let batch = await stream.next(); batch; batch = await stream.next()) {
+			yield* batch;
+		}
+	} finally {
+		stream.close();
+	}
+}
+
+/**
+ * Replace regex matches across files.
+ *
+ * With `dryRun`, returns a unified diff per file instead of writing. Otherwise
+ * each changed file is written atomically and evicted from the scan cache.
+ */
+export async function replace(options: ReplaceOptions): Promise<ReplaceResult> {
+	const { replacement, dryRun, ...grepOptions } = options;
+	return native.replace(grepOptions, { replacement, dryRun });
+}
+
 /**
  * Search a single file's content for a pattern.
  * Lower-level API for when you already have file content.
@@ -3,21 +3,51 @@
  */
 
 import type { Cancellable, TsFunc } from "../bindings";
+import type { TypeFilterOptions } from "../glob/types";
 
-/** Options for searching files. */
-export interface GrepOptions extends Cancellable {
+/**
+ * Patterns and matching flags shared by `grep()` and `searchContent()`.
+ *
+ * At least one of `pattern`, `patterns` or `patternFile` is required. Patterns are numbered in that order for
+ * `Submatch.pattern`.
+ */
+export interface PatternOptions {
 	/** Regex pattern to search for */
-	pattern: string;
-	/** Directory or file to search */
-	path: string;
-	/** Glob filter for filenames (e.g., "*.ts") */
-	glob?: string;
-	/** Filter by file type (e.g., "js", "py", "rust") */
-	type?: string;
+	pattern?: string;
+	/** Additional patterns; a line matches if any pattern does */
+	patterns?: string[];
+	/** File with one pattern per line, used after `patterns` */
+	patternFile?: string;
 	/** Case-insensitive search */
 	ignoreCase?: boolean;
+	/** Case-insensitive unless a pattern contains an uppercase letter */
+	smartCase?: boolean;
 	/** Enable multiline matching */
 	multiline?: boolean;
+	/** Match patterns as literal strings instead of regexes */
+	fixedStrings?: boolean;
+	/** Only match whole words */
+	wordRegexp?: boolean;
+	/** Select lines that do not match */
+	invertMatch?: boolean;
+	/**
+	 * Regex engine. `default` is linear-time but rejects look-around and backreferences; `fancy` supports them by
+	 * backtracking. `auto` (default) uses `fancy` only for patterns `default` rejects.
+	 */
+	engine?: "auto" | "default" | "fancy";
+	/**
+	 * Encoding to transcode from before matching, as a WHATWG label such as `utf-16le`, `utf-16be`, `latin1` or
+	 * `shift_jis`. `auto` (default) reads UTF-8 unless a byte-order mark says otherwise; a BOM always wins.
+	 */
+	encoding?: string;
+}
+
+/** Options for searching files. */
+export interface GrepOptions extends Cancellable, PatternOptions, TypeFilterOptions {
+	/** Directory or file to search */
+	path: string;
+	/** Glob filter for filenames (e.g., "*.ts") */
+	glob?: string;
 	/** Include hidden files (default: true) */
 	hidden?: boolean;
 	/** Enable shared filesystem scan cache (default: false). */
@@ -36,6 +66,21 @@ export interface GrepOptions extends Cancellable {
 	maxColumns?: number;
 	/** Output mode */
 	mode?: "content" | "filesWithMatches" | "count";
+	/** Only search the first N bytes of larger files (default: no limit) */
+	maxFileBytes?: number;
+	/**
+	 * Skip files that cannot match using a persistent trigram index of the repository (default: false). The index is
+	 * kept under `GREP_INDEX_DIR` and refreshed from file mtimes and `invalidateFsScanCache()`. Files with a UTF-16
+	 * byte-order mark are always searched, and the index is not used when `encoding` names a non-UTF-8 encoding.
+	 */
+	index?: boolean;
+	/**
+	 * Search the decompressed content of `.gz`, `.zst`, `.bz2` and `.xz` files and the members of tar (`.tar`,
+	 * `.tar.gz`, `.tgz`, ...) and zip (`.zip`, `.jar`, `.war`, `.whl`) archives (default: false). `maxFileBytes` then
+	 * caps decompressed bytes per file or member, and defaults to 256 MiB. At most 1 GiB is decompressed from one file
+	 * across all of its members; past that it is listed in `skippedFiles` as `truncated`.
+	 */
+	searchCompressed?: boolean;
 }
 
 /** A context line returned around a match. */
@@ -46,6 +91,20 @@ export interface ContextLine {
 	line: string;
 }
 
+/** One regex match within a matched line. Offsets are UTF-16 code units into `line`. */
+export interface Submatch {
+	/** Start offset (inclusive). */
+	start: number;
+	/** End offset (exclusive). */
+	end: number;
+	/** The matched text. */
+	text: string;
+	/** Capture groups in pattern order (group 1 first); `null` for groups that did not participate. */
+	groups?: (string | null)[];
+	/** Index of the pattern that matched when several were given (see `PatternOptions`). */
+	pattern?: number;
+}
+
 /** A single grep match or per-file count entry. */
 export interface GrepMatch {
 	/** File path for the match (relative for directory searches). */
@@ -60,6 +119,12 @@ export interface GrepMatch {
 	contextAfter?: ContextLine[];
 	/** Whether the line was truncated. */
 	truncated?: boolean;
+	/** Every match on the line (content mode only). */
+	submatches?: Submatch[];
+	/** Archive member containing the match, when `path` is an archive. */
+	member?: string;
+	/** Encoding the file was decoded from (e.g. `UTF-16LE`), when not UTF-8. */
+	encoding?: string;
 	/** Per-file match count (count mode only). */
 	matchCount?: number;
 }
@@ -74,6 +139,23 @@ export interface GrepSummary {
 	filesSearched: number;
 	/** Whether the limit/offset stopped the search early. */
 	limitReached?: boolean;
+	/** Files that were truncated by `maxFileBytes`, binary, or could not be read. */
+	skippedFiles?: GrepSkippedFile[];
+}
+
+/** A file that was not searched in full. */
+export interface GrepSkippedFile {
+	/** File path (relative for directory searches). */
+	path: string;
+	/** Archive member, when only that member was affected. */
+	member?: string;
+	/**
+	 * `truncated`: only the first `maxFileBytes` were searched; `binary`: the search stopped at a NUL byte (matches before
+	 * it are kept); `tooLarge`: `replace()` left a file over `maxFileBytes` unchanged; `unreadable`: open or read failed.
+	 */
+	reason: "truncated" | "binary" | "tooLarge" | "unreadable";
+	/** Detail such as the file size or the read error. */
+	message?: string;
 }
 
 /** Full grep result including matches and summary counts. */
@@ -82,14 +164,68 @@ export interface GrepResult extends GrepSummary {
 	matches: GrepMatch[];
 }
 
+/** Native handle behind `grepJson()`; the search runs ahead while fewer than 64 files' messages are buffered. */
+export interface GrepJsonStream {
+	/** Next messages, one JSON document each, or `null` after the `summary` message. Rejects if the search failed. */
+	next(): Promise<string[] | null>;
+	/** Stop the search and drop buffered messages; later `next()` calls resolve to `null`. */
+	close(): void;
+}
+
+/**
+ * Options for replacing matches in files. Files and patterns are selected as in `grep()`, including `index`,
+ * `maxFileBytes` (larger files are skipped) and `encoding` (files are written back in the encoding they were read
+ * in). Output options do not apply, and compressed files and archives are never rewritten.
+ */
+export interface ReplaceOptions
+	extends Omit<
+		GrepOptions,
+		| "invertMatch"
+		| "maxCount"
+		| "offset"
+		| "contextBefore"
+		| "contextAfter"
+		| "context"
+		| "maxColumns"
+		| "mode"
+		| "searchCompressed"
+	> {
+	/** Replacement template; `$1`/`${1}` and `$name`/`${name}` insert capture groups, `$$` a literal `$`. */
+	replacement: string;
+	/** Return diffs without writing any file (default: false). */
+	dryRun?: boolean;
+}
+
+/** Replacements made (or planned) in a single file. */
+export interface ReplaceFileResult {
+	/** File path (relative for directory searches). */
+	path: string;
+	/** Number of matches rewritten. */
+	replacements: number;
+	/** Unified diff of the change (dry runs only). */
+	diff?: string;
+	/** Why the file could not be written, if it failed. */
+	error?: string;
+}
+
+/** Result of a replace run. */
+export interface ReplaceResult {
+	/** Files with at least one match, sorted by path. */
+	files: ReplaceFileResult[];
+	/** Total matches rewritten across written (or, for dry runs, all) files. */
+	totalReplacements: number;
+	/** Number of files written (or that would be written on a dry run). */
+	filesChanged: number;
+	/** Number of files searched. */
+	filesSearched: number;
+	/** Whether this was a dry run. */
+	dryRun: boolean;
+	/** Files left unchanged because they were binary, over `maxFileBytes`, or could not be read. */
+	skippedFiles?: GrepSkippedFile[];
+}
+
 /** Options for searching in-memory content. */
-export interface SearchOptions {
-	/** Regex pattern to search for */
-	pattern: string;
-	/** Case-insensitive search */
-	ignoreCase?: boolean;
-	/** Enable multiline matching */
-	multiline?: boolean;
+export interface SearchOptions extends PatternOptions {
 	/** Maximum number of matches to return */
 	maxCount?: number;
 	/** Skip first N matches */
@@ -118,6 +254,8 @@ export interface SearchMatch {
 	contextAfter?: ContextLine[];
 	/** Whether the line was truncated. */
 	truncated?: boolean;
+	/** Every match on the line (those within the truncated prefix, if truncated). */
+	submatches: Submatch[];
 }
 
 /** Result of searching in-memory content. */
@@ -128,13 +266,15 @@ export interface SearchResult {
 	matchCount: number;
 	/** Whether the limit was reached. */
 	limitReached: boolean;
+	/** Encoding the content was decoded from, when not UTF-8. */
+	encoding?: string;
 	/** Error message, if any. */
 	error?: string;
 }
 
 /** Options for fuzzy file path search. */
-export interface FuzzyFindOptions extends Cancellable {
-	/** Fuzzy query to match against file paths (case-insensitive). */
+export interface FuzzyFindOptions extends Cancellable, TypeFilterOptions {
+	/** Fuzzy query to match against file paths (case-insensitive); whitespace-separated words must all match. */
 	query: string;
 	/** Directory to search. */
 	path: string;
@@ -144,6 +284,8 @@ export interface FuzzyFindOptions extends Cancellable {
 	gitignore?: boolean;
 	/** Enable shared filesystem scan cache (default: false). */
 	cache?: boolean;
+	/** Boost entries recorded with `recordFileAccess()` and, slightly, recently modified files (default: false). */
+	frecency?: boolean;
 	/** Maximum number of matches to return (default: 100). */
 	maxResults?: number;
 }
@@ -156,6 +298,8 @@ export interface FuzzyFindMatch {
 	isDirectory: boolean;
 	/** Match quality score (higher is better). */
 	score: number;
+	/** Positions of the matched characters in `path`, as sorted string indices (for highlighting). */
+	positions: number[];
 }
 
 /** Result of fuzzy file path search. */
@@ -172,6 +316,13 @@ declare module "../bindings" {
 		fuzzyFind(options: FuzzyFindOptions): Promise<FuzzyFindResult>;
 		/** Search files for a regex pattern. */
 		grep(options: GrepOptions, onMatch?: TsFunc<GrepMatch>): Promise<GrepResult>;
+		/** Search files, streaming ripgrep `--json` messages. */
+		grepJson(options: GrepOptions): GrepJsonStream;
+		/** Replace regex matches in files, or preview the change with `dryRun`. */
+		replace(
+			options: Omit<ReplaceOptions, "replacement" | "dryRun">,
+			edit: { replacement: string; dryRun?: boolean },
+		): Promise<ReplaceResult>;
 		/** Search in-memory content for a regex pattern. */
 		search(content: string | Uint8Array, options: SearchOptions): SearchResult;
 		/** Quick check if content matches a pattern. */
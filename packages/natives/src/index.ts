@@ -21,23 +21,50 @@ export {
 	type GrepMatch,
 	type GrepOptions,
 	type GrepResult,
+	type GrepSkippedFile,
 	type GrepSummary,
 	grep,
+	grepJson,
 	hasMatch,
+	type PatternOptions,
+	type ReplaceFileResult,
+	type ReplaceOptions,
+	type ReplaceResult,
+	replace,
 	searchContent,
+	type Submatch,
 } from "./grep";
 
+// =============================================================================
+// Fuzzy ranking
+// =============================================================================
+
+export {
+	type FuzzyCandidate,
+	FuzzyCandidates,
+	type FuzzyMatch,
+	type FuzzyMatchOptions,
+	type FuzzyMatchResult,
+	fuzzyMatch,
+} from "./fuzzy";
+
 // =============================================================================
 // Glob (file discovery)
 // =============================================================================
 
 export {
 	FileType,
+	type FsWatcher,
 	type GlobMatch,
 	type GlobOptions,
 	type GlobResult,
 	glob,
 	invalidateFsScanCache,
+	recordFileAccess,
+	type TypeFilterOptions,
+	type WatchEvent,
+	type WatchOptions,
+	watch,
 } from "./glob";
 
 // =============================================================================
@@ -105,19 +132,38 @@ export { getSystemInfo, type SystemInfo } from "./system-info";
 
 export {
 	executeShell,
+	parseShell,
 	Shell,
+	type ShellCommandInfo,
+	type ShellCommandPolicy,
 	type ShellExecuteOptions,
 	type ShellExecuteResult,
+	type ShellJob,
+	type ShellLimits,
 	type ShellOptions,
+	type ShellParseContext,
+	type ShellParsedCommand,
+	type ShellParsedHeredoc,
+	type ShellParsedPipeline,
+	type ShellParsedRedirect,
+	type ShellParseResult,
+	type ShellPolicyDecision,
 	type ShellRunOptions,
 	type ShellRunResult,
+	type ShellSnapshot,
+	type ShellSnapshotFunction,
+	type ShellSnapshotVariable,
+	type ShellState,
+	type ShellSyntaxError,
+	type ShellTraceEntry,
+	type ShellWaitOptions,
 } from "./shell";
 
 // =============================================================================
 // PTY execution
 // =============================================================================
 
-export { type PtyRunResult, PtySession, type PtyStartOptions } from "./pty";
+export { type PtyRunResult, PtySession, type PtyShellOptions, type PtyStartOptions } from "./pty";
 // =============================================================================
 // Process management
 // =============================================================================
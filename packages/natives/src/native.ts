@@ -17,6 +17,7 @@ import { embeddedAddon } from "./embedded-addon";
 
 // Import types to trigger declaration merging
 import "./clipboard/types";
+import "./fuzzy/types";
 import "./glob/types";
 import "./grep/types";
 import "./highlight/types";
@@ -4,7 +4,7 @@
 
 import { native } from "../native";
 
-export type { PtyRunResult, PtySessionConstructor, PtyStartOptions } from "./types";
+export type { PtyRunResult, PtySessionConstructor, PtyShellOptions, PtyStartOptions } from "./types";
 
 export const { PtySession } = native;
 export type PtySession = import("./types").PtySession;
@@ -3,6 +3,7 @@
  */
 
 import type { Cancellable, TsFunc } from "../bindings";
+import type { Shell } from "../shell/types";
 
 /**
  * Options for starting a command in a pseudo-terminal session.
@@ -20,6 +21,18 @@ export interface PtyStartOptions extends Cancellable {
 	rows?: number;
 }
 
+/**
+ * Options for taking over a shell session in a pseudo-terminal.
+ */
+export interface PtyShellOptions {
+	/** Abort signal that ends the interactive shell. */
+	signal?: AbortSignal;
+	/** PTY column count. */
+	cols?: number;
+	/** PTY row count. */
+	rows?: number;
+}
+
 /**
  * Result of a PTY command run.
  */
@@ -36,6 +49,14 @@ export interface PtyRunResult {
 export interface PtySession {
 	/** Start command execution and stream output while it runs. */
 	start(options: PtyStartOptions, onChunk?: TsFunc<string>): Promise<PtyRunResult>;
+	/**
+	 * Run an interactive brush shell on the session's variables, functions and
+	 * working directory, with prompts, job control and history. Lines are
+	 * edited in place, with Up/Down history recall and Tab completion. Ctrl-C
+	 * interrupts the running command. Resolves when the user exits; the shell
+	 * keeps the state they left behind. Unix only.
+	 */
+	startShell(shell: Shell, options?: PtyShellOptions, onChunk?: TsFunc<string>): Promise<PtyRunResult>;
 	/** Write raw input bytes to PTY stdin. */
 	write(data: string): void;
 	/** Resize active PTY. */
@@ -5,9 +5,33 @@
 import { native } from "../native";
 import type { ShellExecuteOptions, ShellExecuteResult } from "./types";
 
-export type { ShellExecuteOptions, ShellExecuteResult, ShellOptions, ShellRunOptions, ShellRunResult } from "./types";
+export type {
+	ShellCommandInfo,
+	ShellCommandPolicy,
+	ShellExecuteOptions,
+	ShellExecuteResult,
+	ShellJob,
+	ShellLimits,
+	ShellOptions,
+	ShellParseContext,
+	ShellParsedCommand,
+	ShellParsedHeredoc,
+	ShellParsedPipeline,
+	ShellParsedRedirect,
+	ShellParseResult,
+	ShellPolicyDecision,
+	ShellRunOptions,
+	ShellRunResult,
+	ShellSnapshot,
+	ShellSnapshotFunction,
+	ShellSnapshotVariable,
+	ShellState,
+	ShellSyntaxError,
+	ShellTraceEntry,
+	ShellWaitOptions,
+} from "./types";
 
-export const { Shell } = native;
+export const { Shell, parseShell } = native;
 export type Shell = import("./types").Shell;
 
 /**
@@ -15,12 +39,15 @@ export type Shell = import("./types").Shell;
  *
  * @param options - Execution options including command, cwd, env, timeout
  * @param onChunk - Optional callback for streaming output chunks
+ * @param onStderr - Optional callback for stderr chunks; separates stderr from `onChunk`
  * @returns Promise resolving to execution result with exit code and status
  */
 export async function executeShell(
 	options: ShellExecuteOptions,
 	onChunk?: (chunk: string) => void,
+	onStderr?: (chunk: string) => void,
 ): Promise<ShellExecuteResult> {
 	const wrappedCallback = onChunk ? (err: Error | null, chunk: string) => !err && onChunk(chunk) : undefined;
-	return native.executeShell(options, wrappedCallback);
+	const wrappedStderr = onStderr ? (err: Error | null, chunk: string) => !err && onStderr(chunk) : undefined;
+	return native.executeShell(options, wrappedCallback, wrappedStderr);
 }
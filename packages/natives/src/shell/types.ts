@@ -24,6 +24,33 @@ export interface ShellRunOptions extends Cancellable {
 	cwd?: string;
 	/** Environment variables to apply for this command. */
 	env?: Record<string, string>;
+	/** Data piped to the command's stdin. */
+	stdin?: string | Uint8Array;
+	/** Keep stdin open for `Shell.write()` until `Shell.closeStdin()` is called or the command exits. */
+	streamStdin?: boolean;
+	/** Resource limits for this command. */
+	limits?: ShellLimits;
+	/** Record every simple command run, returned as `trace` in the result. */
+	trace?: boolean;
+	/** Capture the output of background jobs (`cmd &`) for `Shell.jobs()` and `Shell.waitJob()` instead of the command's output. */
+	captureJobOutput?: boolean;
+}
+
+/**
+ * Resource limits for a single command.
+ *
+ * Process limits set the soft limit of every external process the command spawns, leaving its hard limit unchanged
+ * (ignored on Windows).
+ */
+export interface ShellLimits {
+	/** CPU time limit in seconds per process. */
+	cpuSeconds?: number;
+	/** Address space limit in bytes per process. */
+	addressSpaceBytes?: number;
+	/** Open file descriptor limit per process. */
+	openFiles?: number;
+	/** Combined stdout/stderr bytes after which the command is killed. */
+	maxOutputBytes?: number;
 }
 
 /**
@@ -36,6 +63,250 @@ export interface ShellRunResult {
 	cancelled: boolean;
 	/** Whether the command timed out. */
 	timedOut: boolean;
+	/** Bytes read from stdout (includes stderr when streams are merged). */
+	stdoutBytes: number;
+	/** Bytes read from stderr (undefined when streams are merged). */
+	stderrBytes?: number;
+	/** Whether the command was killed for exceeding `limits.maxOutputBytes`. */
+	outputLimitExceeded: boolean;
+	/** Commands run, in start order, when the `trace` option is set. */
+	trace?: ShellTraceEntry[];
+}
+
+/**
+ * A simple command executed during a traced run.
+ */
+export interface ShellTraceEntry {
+	/** Expanded arguments, starting with the command name. */
+	args: string[];
+	/** How the command name resolved. */
+	kind: "builtin" | "function" | "external" | "notFound";
+	/** Resolved executable path for external commands. */
+	path?: string;
+	/** Process ID for external commands. */
+	pid?: number;
+	/** Zero-based position within the command's pipeline. */
+	pipelineIndex: number;
+	/** Number of commands in the pipeline. */
+	pipelineLength: number;
+	/** Start time in milliseconds since the Unix epoch. */
+	startMs: number;
+	/** End time in milliseconds since the Unix epoch (undefined if the command never completed). */
+	endMs?: number;
+	/** Exit code (undefined if the command failed to start or never completed). */
+	exitCode?: number;
+}
+
+/**
+ * State of a persistent shell session between commands.
+ */
+export interface ShellState {
+	/** Current working directory. */
+	cwd: string;
+	/** Exported variables with their scalar values. */
+	env: Record<string, string>;
+	/** Names of defined shell functions, sorted. */
+	functions: string[];
+	/** Defined aliases keyed by name. */
+	aliases: Record<string, string>;
+	/** Directory stack from `pushd`, most recent first (without the current directory). */
+	dirStack: string[];
+	/** Exit statuses of each command in the last pipeline. */
+	lastPipelineStatuses: number[];
+}
+
+/** A variable captured in a {@link ShellSnapshot}. */
+export interface ShellSnapshotVariable {
+	/** Variable name. */
+	name: string;
+	/** Attribute flags as printed by `declare -p` (e.g. `"ax"`). */
+	attributes: string;
+	/** Scalar value; absent for arrays and declared-but-unset variables. */
+	value?: string;
+	/** Array elements keyed by index or key; absent for scalars. */
+	elements?: Record<string, string>;
+}
+
+/** A function captured in a {@link ShellSnapshot}. */
+export interface ShellSnapshotFunction {
+	/** Function name. */
+	name: string;
+	/** Function body, starting at the compound command. */
+	body: string;
+	/** Whether the function is exported to child shells. */
+	exported: boolean;
+}
+
+/**
+ * JSON-serializable state of a shell session.
+ */
+export interface ShellSnapshot {
+	/** Snapshot format version. */
+	version: number;
+	/** Working directory. */
+	cwd: string;
+	/** Shell variables, excluding dynamic and shell-managed ones. */
+	variables: ShellSnapshotVariable[];
+	/** Function definitions. */
+	functions: ShellSnapshotFunction[];
+	/** Aliases keyed by name. */
+	aliases: Record<string, string>;
+	/** `set -o` options keyed by name. */
+	setOptions: Record<string, boolean>;
+	/** `shopt` options keyed by name. */
+	shoptOptions: Record<string, boolean>;
+	/** Directory stack from `pushd`, oldest first. */
+	dirStack: string[];
+}
+
+/**
+ * A background job of a shell session.
+ */
+export interface ShellJob {
+	/** Job ID, as used by `%1` job specs. */
+	id: number;
+	/** Process group ID of the job, when the shell created one. */
+	pgid?: number;
+	/** IDs of the job's running processes. */
+	pids: number[];
+	/** Command line that started the job. */
+	command: string;
+	/** Current job state. */
+	state: "running" | "stopped" | "done" | "unknown";
+	/** Exit code once the job is done. */
+	exitCode?: number;
+	/** Most recent captured output (up to 1 MiB), for jobs started with `captureJobOutput`. */
+	output?: string;
+}
+
+/**
+ * Options for `Shell.waitJob()`.
+ */
+export interface ShellWaitOptions {
+	/** Timeout in milliseconds before the wait rejects; the job keeps running. */
+	timeoutMs?: number;
+	/** Abort signal for cancelling the wait. */
+	signal?: AbortSignal;
+}
+
+/**
+ * Static rules for a shell's command policy.
+ */
+export interface ShellCommandPolicy {
+	/** Commands to block, as a name (`"sudo"`) or argv prefix (`"rm -rf /"`); names also match paths ending in them. */
+	deny?: string[];
+	/** Exit code for commands blocked by `deny` (default 126). */
+	exitCode?: number;
+}
+
+/**
+ * A simple command about to run, passed to the command policy callback.
+ */
+export interface ShellCommandInfo {
+	/** Expanded arguments, starting with the command name. */
+	args: string[];
+	/** How the command name resolved. */
+	kind: "builtin" | "function" | "external" | "notFound";
+	/** Shell working directory. */
+	cwd: string;
+}
+
+/**
+ * Verdict returned by the command policy callback; `undefined` allows the command.
+ *
+ * `deny` skips the command with `exitCode` (default 126) and writes `message` to stderr.
+ * `rewrite` runs `args` instead; the rewritten command is not checked again.
+ */
+export type ShellPolicyDecision =
+	| { action: "allow" }
+	| { action: "deny"; exitCode?: number; message?: string }
+	| { action: "rewrite"; args: string[] };
+
+/**
+ * Enclosing construct of a parsed command or pipeline.
+ */
+export type ShellParseContext = "subshell" | "function" | "commandSubstitution" | "processSubstitution";
+
+/** A syntax error reported by `parseShell()`. */
+export interface ShellSyntaxError {
+	/** Error message. */
+	message: string;
+	/** 1-based line of the error. */
+	line: number;
+	/** 1-based column of the error. */
+	column: number;
+}
+
+/** An I/O redirection attached to a parsed command. */
+export interface ShellParsedRedirect {
+	/** Explicit file descriptor, if any (e.g. `2` in `2>file`). */
+	fd?: number;
+	/** Redirection operator, e.g. `>`, `>>`, `<`, `<<`, `<<<`, `&>`. */
+	operator: string;
+	/** Unexpanded target word, heredoc delimiter or file descriptor. */
+	target: string;
+}
+
+/** A simple command found by `parseShell()`. */
+export interface ShellParsedCommand {
+	/** Unexpanded command name and arguments. */
+	words: string[];
+	/** Variable assignments accompanying the command. */
+	assignments: string[];
+	/** Redirections applied to the command. */
+	redirects: ShellParsedRedirect[];
+	/** Index into `pipelines` of the containing pipeline. */
+	pipeline: number;
+	/** Enclosing constructs, outermost first. */
+	context: ShellParseContext[];
+	/** 1-based line; absent inside command substitutions. */
+	line?: number;
+	/** 1-based column; absent inside command substitutions. */
+	column?: number;
+}
+
+/** A pipeline found by `parseShell()`; single commands form one-element pipelines. */
+export interface ShellParsedPipeline {
+	/** Indices into `commands` of the pipeline's simple commands. */
+	commands: number[];
+	/** Number of pipeline stages, including compound commands. */
+	length: number;
+	/** Whether the pipeline is negated with `!`. */
+	negated: boolean;
+	/** Whether the pipeline runs in the background with `&`. */
+	background: boolean;
+	/** Operator joining this pipeline to the previous one. */
+	operator?: "&&" | "||";
+	/** Enclosing constructs, outermost first. */
+	context: ShellParseContext[];
+}
+
+/** A here-document found by `parseShell()`. */
+export interface ShellParsedHeredoc {
+	/** Delimiter word. */
+	delimiter: string;
+	/** Unexpanded document body. */
+	body: string;
+	/** Whether the body undergoes expansion (unquoted delimiter). */
+	expands: boolean;
+	/** Index into `commands` of the command it feeds, if a simple command. */
+	command?: number;
+}
+
+/**
+ * Result of `parseShell()`: a syntax error, or a summary of the parsed source.
+ */
+export interface ShellParseResult {
+	/** Syntax error; when set, the other fields are empty. */
+	error?: ShellSyntaxError;
+	/** Simple commands in source order. */
+	commands: ShellParsedCommand[];
+	/** Pipelines in source order. */
+	pipelines: ShellParsedPipeline[];
+	/** Here-documents in source order. */
+	heredocs: ShellParsedHeredoc[];
+	/** Names of functions defined. */
+	functions: string[];
 }
 
 /**
@@ -52,6 +323,12 @@ export interface ShellExecuteOptions extends Cancellable {
 	sessionEnv?: Record<string, string>;
 	/** Optional snapshot path to source for bash sessions. */
 	snapshotPath?: string;
+	/** Data piped to the command's stdin. */
+	stdin?: string | Uint8Array;
+	/** Resource limits for this command. */
+	limits?: ShellLimits;
+	/** Record every simple command run, returned as `trace` in the result. */
+	trace?: boolean;
 }
 
 /**
@@ -64,9 +341,57 @@ export interface Shell {
 	 * Run a command in the shell.
 	 * @param options Command execution options.
 	 * @param onChunk Optional callback for streamed output.
+	 * @param onStderr Optional callback for stderr; when set, stderr is no longer sent to `onChunk`.
 	 * @returns Promise resolving to the command result.
 	 */
-	run(options: ShellRunOptions, onChunk?: TsFunc<string>): Promise<ShellRunResult>;
+	run(options: ShellRunOptions, onChunk?: TsFunc<string>, onStderr?: TsFunc<string>): Promise<ShellRunResult>;
+	/**
+	 * Write to the stdin of a command started with `streamStdin`.
+	 * @param data Text or bytes to write.
+	 */
+	write(data: string | Uint8Array): void;
+	/** Close the stdin of the running command, signalling EOF. */
+	closeStdin(): void;
+	/**
+	 * Inspect the session state, waiting for any running command to finish.
+	 * @returns Promise resolving to the current session state.
+	 */
+	state(): Promise<ShellState>;
+	/**
+	 * Capture variables, functions, aliases, options and cwd, waiting for any running command to finish.
+	 * @returns Promise resolving to a snapshot for {@link ShellConstructor.fromSnapshot}.
+	 */
+	saveSnapshot(): Promise<ShellSnapshot>;
+	/**
+	 * Set the policy consulted before every simple command, including those in functions, `eval` and subshells.
+	 * Commands matching `policy.deny` are blocked; others go to `check`. A throwing callback blocks the command.
+	 * @param policy Static deny rules, or `null` for none.
+	 * @param check Optional callback that may allow, deny or rewrite each command.
+	 */
+	setCommandPolicy(
+		policy: ShellCommandPolicy | null,
+		check?: (command: ShellCommandInfo) => ShellPolicyDecision | undefined | Promise<ShellPolicyDecision | undefined>,
+	): void;
+	/**
+	 * List background jobs. Finished jobs are listed once with their exit code and then forgotten.
+	 * While a command is running, lists the jobs as they were when it started, without waiting for it.
+	 * @returns The session's jobs, ordered by ID.
+	 */
+	jobs(): ShellJob[];
+	/**
+	 * Send a signal to a background job's processes, without waiting for a running command.
+	 * Jobs started by the running command can only be signaled once it finishes.
+	 * @param id Job ID.
+	 * @param signal Signal name (`"INT"`, `"SIGKILL"`) or number (`"9"`); defaults to `"TERM"`.
+	 */
+	killJob(id: number, signal?: string): void;
+	/**
+	 * Wait for a background job to finish; rejects if no such job exists or the wait times out or is aborted.
+	 * @param id Job ID.
+	 * @param options Optional timeout and abort signal.
+	 * @returns Promise resolving to the finished job with its exit code.
+	 */
+	waitJob(id: number, options?: ShellWaitOptions): Promise<ShellJob>;
 	/**
 	 * Abort all running commands in this session.
 	 * @param reason Optional reason for the abort.
@@ -81,6 +406,12 @@ export interface ShellConstructor {
 	 * @param options Optional session configuration.
 	 */
 	new (options?: ShellOptions): Shell;
+	/**
+	 * Create a shell session restored from a snapshot.
+	 * @param snapshot Snapshot returned by `Shell.saveSnapshot()`.
+	 * @param options Optional session configuration, applied before the snapshot.
+	 */
+	fromSnapshot(snapshot: ShellSnapshot, options?: ShellOptions): Shell;
 }
 
 declare module "../bindings" {
@@ -90,9 +421,21 @@ declare module "../bindings" {
 		 * Execute a shell command with explicit session metadata.
 		 * @param options Execution options including session identifiers.
 		 * @param onChunk Optional callback for streamed output.
+		 * @param onStderr Optional callback for stderr; when set, stderr is no longer sent to `onChunk`.
 		 * @returns Promise resolving to the command result.
 		 */
-		executeShell(options: ShellExecuteOptions, onChunk?: TsFunc<string>): Promise<ShellExecuteResult>;
+		executeShell(
+			options: ShellExecuteOptions,
+			onChunk?: TsFunc<string>,
+			onStderr?: TsFunc<string>,
+		): Promise<ShellExecuteResult>;
+
+		/**
+		 * Parse a shell command without executing it.
+		 * @param command Shell source to parse.
+		 * @returns Syntax error with position, or a summary of commands, pipelines and heredocs.
+		 */
+		parseShell(command: string): ShellParseResult;
 
 		/** Shell class constructor for creating sessions. */
 		Shell: ShellConstructor;
@@ -2,15 +2,29 @@ import { beforeAll, describe, expect, it } from "bun:test";
 import * as fs from "node:fs/promises";
 import * as os from "node:os";
 import * as path from "node:path";
+import * as zlib from "node:zlib";
 import {
+	executeShell,
 	FileType,
+	FuzzyCandidates,
 	fuzzyFind,
+	fuzzyMatch,
 	type GlobMatch,
+	type GrepOptions,
 	glob,
 	grep,
+	grepJson,
 	htmlToMarkdown,
 	invalidateFsScanCache,
+	parseShell,
+	PtySession,
+	recordFileAccess,
+	replace,
 	sanitizeText,
+	Shell,
+	searchContent,
+	type WatchEvent,
+	watch,
 } from "../src/index";
 
 let testDir: string;
@@ -102,6 +116,362 @@ describe("pi-natives", () => {
 			expect(result.totalMatches).toBe(0);
 			expect(result.filesWithMatches).toBe(0);
 		});
+
+		it("should use ripgrep file types with typeAdd and typeNot", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-types-"));
+			try {
+				await fs.writeFile(path.join(dir, "api.proto"), "// NEEDLE\n");
+				await fs.writeFile(path.join(dir, "App.vue"), "<!-- NEEDLE -->\n");
+				await fs.writeFile(path.join(dir, "main.rs"), "// NEEDLE\n");
+
+				const paths = async (options: Partial<GrepOptions>) =>
+					(await grep({ pattern: "NEEDLE", path: dir, ...options })).matches.map(match => match.path).sort();
+				expect(await paths({ type: "proto" })).toEqual(["api.proto"]);
+				expect(await paths({ typeNot: ["rust", "vue"] })).toEqual(["api.proto"]);
+				expect(await paths({ type: "schema", typeAdd: ["schema:include:protobuf,rust"] })).toEqual([
+					"api.proto",
+					"main.rs",
+				]);
+				expect(await paths({ type: "schema", typeAdd: ["Schema:include:Protobuf,RUST"] })).toEqual([
+					"api.proto",
+					"main.rs",
+				]);
+				expect(await paths({ type: "vue", typeNot: ["RS"] })).toEqual(["App.vue"]);
+				await expect(paths({ typeNot: ["vuex"] })).rejects.toThrow("Unknown file type: vuex");
+
+				const found = await glob({ pattern: "*", path: dir, type: "vue" });
+				expect(found.matches.map(match => match.path)).toEqual(["App.vue"]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should report submatch offsets in UTF-16 units with capture groups", async () => {
+			const result = await grep({ pattern: "(\\w+)\\(\\)", path: testDir, glob: "file1.ts" });
+			const match = result.matches[0];
+			expect(match.submatches).toEqual([{ start: 16, end: 23, text: "hello()", groups: ["hello"] }]);
+
+			const content = searchContent("😀 a=1 b=2", { pattern: "[a-z]=\\d" });
+			const line = content.matches[0].line;
+			expect(content.matches[0].submatches.map(s => line.slice(s.start, s.end))).toEqual(["a=1", "b=2"]);
+		});
+
+		it("should match fixed strings and tag multiple patterns", async () => {
+			const literal = searchContent("call(foo)\n", { pattern: "call(", fixedStrings: true });
+			expect(literal.error).toBeUndefined();
+			expect(literal.matchCount).toBe(1);
+
+			const result = await grep({ patterns: ["TODO", "FIXME"], path: testDir, wordRegexp: true });
+			const tags = result.matches.map(match => [match.path, match.submatches?.[0].pattern]).sort();
+			expect(tags).toEqual([
+				["file1.ts", 0],
+				["file2.ts", 1],
+			]);
+		});
+
+		it("should fall back to the fancy engine for look-around patterns", async () => {
+			const result = await grep({ pattern: 'return (?!"hello")', path: testDir, glob: "*.ts" });
+			expect(result.matches.map(match => match.path)).toEqual(["file2.ts"]);
+
+			const strict = searchContent("foobar\n", { pattern: "foo(?!bar)", engine: "default" });
+			expect(strict.error).toContain("look-around");
+		});
+
+		it("should find fancy-engine matches after invalid UTF-8", async () => {
+			const content = Buffer.concat([
+				Buffer.from("bad \xff line\nxneedle\n", "latin1"),
+				Buffer.from("ok \xfe xneedle yneedle\n", "latin1"),
+			]);
+			const result = searchContent(content, { pattern: "(?<=x)needle", engine: "fancy" });
+			expect(result.error).toBeUndefined();
+			expect(result.matches.map(match => [match.lineNumber, match.submatches?.map(sub => sub.start)])).toEqual([
+				[2, [1]],
+				[3, [6]],
+			]);
+		});
+
+		it("should reject unknown regex engines", async () => {
+			expect(searchContent("x", { pattern: "x", engine: "fancey" as never }).error).toContain(
+				"Unknown regex engine: fancey",
+			);
+			await expect(grep({ pattern: "x", path: testDir, engine: "fancey" as never })).rejects.toThrow(
+				"Unknown regex engine: fancey",
+			);
+		});
+
+		it("should select non-matching lines with invertMatch", async () => {
+			const result = await grep({ pattern: "e", path: testDir, glob: "file1.ts", invertMatch: true });
+			expect(result.matches.map(match => match.line)).toEqual(["}"]);
+		});
+
+		it("should search large files in full and report maxFileBytes truncation", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-large-"));
+			try {
+				const filler = `${"x".repeat(99)}\n`.repeat(60_000);
+				await fs.writeFile(path.join(dir, "large.txt"), `${filler}NEEDLE\n`);
+
+				const full = await grep({ pattern: "NEEDLE", path: dir });
+				expect(full.totalMatches).toBe(1);
+				expect(full.matches[0].lineNumber).toBe(60_001);
+				expect(full.skippedFiles).toBeUndefined();
+
+				const capped = await grep({ pattern: "NEEDLE", path: dir, maxFileBytes: 1024 });
+				expect(capped.totalMatches).toBe(0);
+				expect(capped.skippedFiles).toEqual([expect.objectContaining({ path: "large.txt", reason: "truncated" })]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should report binary files as skipped instead of searched", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-binary-"));
+			try {
+				await fs.writeFile(path.join(dir, "text.txt"), "needle\n");
+				await fs.writeFile(path.join(dir, "blob.bin"), Buffer.from("head\0needle\n"));
+
+				const result = await grep({ pattern: "needle", path: dir });
+				expect(result.matches.map(match => match.path)).toEqual(["text.txt"]);
+				expect(result.filesSearched).toBe(1);
+				expect(result.skippedFiles).toEqual([
+					{ path: "blob.bin", reason: "binary", message: "binary data at byte 4" },
+				]);
+
+				const single = await grep({ pattern: "needle", path: path.join(dir, "blob.bin") });
+				expect(single.filesSearched).toBe(0);
+				expect(single.skippedFiles?.map(file => file.reason)).toEqual(["binary"]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should narrow candidates with the trigram index and pick up edits", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-index-"));
+			const indexDir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-index-store-"));
+			process.env.GREP_INDEX_DIR = indexDir;
+			try {
+				await fs.mkdir(path.join(dir, ".git"));
+				await fs.writeFile(path.join(dir, "a.ts"), "const needle = 1;\n");
+				await fs.writeFile(path.join(dir, "b.ts"), "const other = 2;\n");
+
+				const first = await grep({ pattern: "needle", path: dir, index: true });
+				expect(first.matches.map(match => match.path)).toEqual(["a.ts"]);
+				expect(first.filesSearched).toBe(1);
+
+				await fs.writeFile(path.join(dir, "b.ts"), "const needle = 2;\n");
+				invalidateFsScanCache(path.join(dir, "b.ts"));
+				const second = await grep({ pattern: "needle", path: dir, index: true });
+				expect(second.matches.map(match => match.path).sort()).toEqual(["a.ts", "b.ts"]);
+			} finally {
+				delete process.env.GREP_INDEX_DIR;
+				await fs.rm(dir, { recursive: true, force: true });
+				await fs.rm(indexDir, { recursive: true, force: true });
+			}
+		});
+
+		it("should search transcoded files when the trigram index is on", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-index-"));
+			const indexDir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-index-store-"));
+			process.env.GREP_INDEX_DIR = indexDir;
+			try {
+				await fs.mkdir(path.join(dir, ".git"));
+				const utf16 = Buffer.concat([Buffer.from([0xff, 0xfe]), Buffer.from("const needle = 1;\n", "utf16le")]);
+				await fs.writeFile(path.join(dir, "wide.txt"), utf16);
+				await fs.writeFile(path.join(dir, "latin.txt"), Buffer.from("caf\xe9 au lait\n", "latin1"));
+				await fs.writeFile(path.join(dir, "plain.ts"), "const other = 2;\n");
+
+				const wide = await grep({ pattern: "needle", path: dir, index: true });
+				expect(wide.matches.map(match => [match.path, match.encoding])).toEqual([["wide.txt", "UTF-16LE"]]);
+
+				const latin = await grep({ pattern: "caf\u00e9", path: dir, index: true, encoding: "latin1" });
+				expect(latin.matches.map(match => match.path)).toEqual(["latin.txt"]);
+			} finally {
+				delete process.env.GREP_INDEX_DIR;
+				await fs.rm(dir, { recursive: true, force: true });
+				await fs.rm(indexDir, { recursive: true, force: true });
+			}
+		});
+
+		it("should search compressed files with searchCompressed", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-compressed-"));
+			try {
+				await fs.writeFile(path.join(dir, "app.log.gz"), zlib.gzipSync("ok\nERROR rotated\n"));
+
+				const plain = await grep({ pattern: "ERROR", path: dir });
+				expect(plain.totalMatches).toBe(0);
+
+				const result = await grep({ pattern: "ERROR", path: dir, searchCompressed: true });
+				expect(result.matches.map(match => [match.path, match.lineNumber, match.line])).toEqual([
+					["app.log.gz", 2, "ERROR rotated"],
+				]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should decode UTF-16 and legacy encodings", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-encoding-"));
+			try {
+				await fs.writeFile(path.join(dir, "bom.reg"), Buffer.from('\ufeff"Key"="café"\r\n', "utf16le"));
+				await fs.writeFile(path.join(dir, "legacy.txt"), Buffer.from("crème brûlée\n", "latin1"));
+
+				const sniffed = await grep({ pattern: "café", path: dir });
+				expect(sniffed.matches.map(match => [match.path, match.line, match.encoding])).toEqual([
+					["bom.reg", '"Key"="café"', "UTF-16LE"],
+				]);
+
+				const latin = await grep({ pattern: "brûlée", path: dir, glob: "*.txt", encoding: "latin1" });
+				expect(latin.matches[0].line).toBe("crème brûlée");
+				expect(latin.matches[0].encoding).toBe("windows-1252");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should stream ripgrep JSON messages", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-json-"));
+			try {
+				await fs.writeFile(path.join(dir, "a.txt"), "before\nfoo and foo\nafter\n");
+				await fs.writeFile(path.join(dir, "b.txt"), "nothing here\n");
+
+				const messages = [];
+				for await (const line of grepJson({ pattern: "foo", path: dir, context: 1 })) {
+					messages.push(JSON.parse(line));
+				}
+				expect(messages.map(message => message.type)).toEqual([
+					"begin",
+					"context",
+					"match",
+					"context",
+					"end",
+					"summary",
+				]);
+				expect(messages[2].data).toEqual({
+					path: { text: "a.txt" },
+					lines: { text: "foo and foo\n" },
+					line_number: 2,
+					absolute_offset: 7,
+					submatches: [
+						{ match: { text: "foo" }, start: 0, end: 3 },
+						{ match: { text: "foo" }, start: 8, end: 11 },
+					],
+				});
+				expect(messages[5].data.stats).toMatchObject({ searches: 2, searches_with_match: 1, matches: 2 });
+
+				let received = 0;
+				for await (const _ of grepJson({ pattern: "foo", path: dir })) {
+					received++;
+					break;
+				}
+				expect(received).toBe(1);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should let other searches run while a grepJson consumer lags", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-json-"));
+			try {
+				await Promise.all(
+					Array.from({ length: 600 }, (_, i) => fs.writeFile(path.join(dir, `f${i}.txt`), "needle\n")),
+				);
+				const messages = grepJson({ pattern: "needle", path: dir });
+				await messages.next();
+				// Give the stream time to fill its buffer and wait on the consumer.
+				await Bun.sleep(200);
+
+				const result = await grep({ pattern: "needle", path: dir, timeoutMs: 5000 });
+				expect(result.totalMatches).toBe(600);
+				await messages.return(undefined);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+	});
+
+	describe("replace", () => {
+		it("should preview capture-group replacements without writing", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-replace-"));
+			try {
+				const file = path.join(dir, "names.ts");
+				await fs.writeFile(file, "const fooBar = 1;\nconst fooBaz = 2;\n");
+
+				const result = await replace({
+					pattern: "foo(?P<rest>Ba[rz])",
+					replacement: "qux${rest}",
+					path: dir,
+					dryRun: true,
+				});
+
+				expect(result.totalReplacements).toBe(2);
+				expect(result.files.map(f => f.path)).toEqual(["names.ts"]);
+				expect(result.files[0].diff).toContain("+const quxBar = 1;");
+				expect(await fs.readFile(file, "utf8")).toBe("const fooBar = 1;\nconst fooBaz = 2;\n");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should write replacements to matching files", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-replace-"));
+			try {
+				await fs.writeFile(path.join(dir, "a.ts"), "fooBar(fooBar);\n");
+				await fs.writeFile(path.join(dir, "b.md"), "fooBar\n");
+
+				const result = await replace({ pattern: "foo(Bar)", replacement: "baz$1", path: dir, glob: "*.ts" });
+
+				expect(result.filesChanged).toBe(1);
+				expect(result.totalReplacements).toBe(2);
+				expect(await fs.readFile(path.join(dir, "a.ts"), "utf8")).toBe("bazBar(bazBar);\n");
+				expect(await fs.readFile(path.join(dir, "b.md"), "utf8")).toBe("fooBar\n");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should apply grep options and keep each file's encoding", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-replace-"));
+			try {
+				await fs.writeFile(path.join(dir, "a.ts"), "Foo foo food\n");
+				await fs.writeFile(path.join(dir, "latin.txt"), Buffer.from("caf\xe9 foo\n", "latin1"));
+				const utf16 = Buffer.concat([Buffer.from([0xff, 0xfe]), Buffer.from("foo\n", "utf16le")]);
+				await fs.writeFile(path.join(dir, "wide.txt"), utf16);
+				await fs.writeFile(path.join(dir, "big.txt"), "foo ".repeat(100));
+				await fs.writeFile(path.join(dir, "blob.bin"), Buffer.from("foo\0"));
+
+				const result = await replace({
+					pattern: "foo",
+					replacement: "bar",
+					path: dir,
+					fixedStrings: true,
+					wordRegexp: true,
+					smartCase: true,
+					encoding: "latin1",
+					maxFileBytes: 64,
+				});
+
+				expect(result.files.map(f => [f.path, f.replacements])).toEqual([
+					["a.ts", 2],
+					["latin.txt", 1],
+					["wide.txt", 1],
+				]);
+				expect(result.filesSearched).toBe(3);
+				expect(result.skippedFiles?.map(f => [f.path, f.reason])).toEqual([
+					["big.txt", "tooLarge"],
+					["blob.bin", "binary"],
+				]);
+				expect(await fs.readFile(path.join(dir, "a.ts"), "utf8")).toBe("bar bar food\n");
+				expect(await fs.readFile(path.join(dir, "latin.txt"))).toEqual(Buffer.from("caf\xe9 bar\n", "latin1"));
+				expect(await fs.readFile(path.join(dir, "wide.txt"))).toEqual(
+					Buffer.concat([Buffer.from([0xff, 0xfe]), Buffer.from("bar\n", "utf16le")]),
+				);
+				expect(await fs.readFile(path.join(dir, "big.txt"), "utf8")).toBe("foo ".repeat(100));
+				await expect(replace({ pattern: "foo", replacement: "", path: dir, invertMatch: true } as never)).rejects.toThrow(
+					"does not support invertMatch",
+				);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
 	});
 
 	describe("fuzzyFind", () => {
@@ -116,6 +486,68 @@ describe("pi-natives", () => {
 
 			expect(result.matches.some(match => match.path === "history-search.ts")).toBe(true);
 		});
+
+		it("should rank path-aligned matches first and report positions", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-fuzzy-"));
+			try {
+				for (const file of ["src/a/b/config.ts", "src/con/fig.ts", "scripts/cache/non-fig.ts"]) {
+					await fs.mkdir(path.join(dir, path.dirname(file)), { recursive: true });
+					await fs.writeFile(path.join(dir, file), "");
+				}
+
+				const result = await fuzzyFind({ query: "config", path: dir });
+				expect(result.matches.map(match => match.path)).toEqual([
+					"src/a/b/config.ts",
+					"src/con/fig.ts",
+					"scripts/cache/non-fig.ts",
+				]);
+				const [best] = result.matches;
+				expect(best.positions).toEqual([8, 9, 10, 11, 12, 13]);
+				expect(best.positions.map(index => best.path[index]).join("")).toBe("config");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+	});
+
+	describe("fuzzyMatch", () => {
+		it("should rank strings and keyed candidates with positions", async () => {
+			const result = await fuzzyMatch("gpt", [
+				"grep-tool",
+				{ text: "anthropic/claude", key: "claude" },
+				{ text: "openai/gpt-4o", key: "gpt-4o" },
+			]);
+
+			expect(result.totalMatches).toBe(2);
+			expect(result.matches.map(match => match.text)).toEqual(["openai/gpt-4o", "grep-tool"]);
+			const [best] = result.matches;
+			expect(best.key).toBe("gpt-4o");
+			expect(best.index).toBe(2);
+			expect(best.positions).toEqual([7, 8, 9]);
+		});
+
+		it("should reuse a candidate handle across queries", async () => {
+			const handle = new FuzzyCandidates(["src/app.ts", "src/api/index.ts", "README.md"]);
+			expect(handle.length).toBe(3);
+
+			expect((await handle.match("a", { paths: true })).totalMatches).toBe(3);
+			const narrowed = await fuzzyMatch("api", handle, { paths: true, maxResults: 1 });
+			expect(narrowed.totalMatches).toBe(1);
+			expect(narrowed.matches[0].text).toBe("src/api/index.ts");
+			const all = await handle.match("", { maxResults: 2 });
+			expect(all.matches.map(match => match.index)).toEqual([0, 1]);
+		});
+
+		it("should rank concurrent queries on one handle independently", async () => {
+			const texts = Array.from({ length: 20_000 }, (_, i) => `dir${i % 7}/file${i}.ts`);
+			const handle = new FuzzyCandidates(texts);
+			const queries = ["dir3", "file1999", "dir3/file1", "d"];
+			const options = { paths: true, maxResults: 5 };
+			const shared = await Promise.all(queries.map(query => handle.match(query, options)));
+			const fresh = await Promise.all(queries.map(query => fuzzyMatch(query, texts, options)));
+			expect(shared).toEqual(fresh);
+			expect(await handle.match("dir3/file12", options)).toEqual(await fuzzyMatch("dir3/file12", texts, options));
+		});
 	});
 
 	describe("find", () => {
@@ -139,6 +571,28 @@ describe("pi-natives", () => {
 			expect(result.totalMatches).toBe(4);
 		});
 
+		it("should combine patterns with negation and exclusions", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-glob-patterns-"));
+			try {
+				for (const file of ["src/a.ts", "src/b.tsx", "src/a.test.ts", "gen/x.ts", "gen/keep.ts", "docs/c.ts"]) {
+					await fs.mkdir(path.join(dir, path.dirname(file)), { recursive: true });
+					await fs.writeFile(path.join(dir, file), "");
+				}
+				const ignoreFile = path.join(dir, "extra.ignore");
+				await fs.writeFile(ignoreFile, "docs/\n");
+
+				const result = await glob({
+					patterns: ["*.ts", "*.tsx", "!*.test.ts"],
+					exclude: ["gen/", "!gen/keep.ts"],
+					ignoreFiles: [ignoreFile],
+					path: dir,
+				});
+				expect(result.matches.map(match => match.path).sort()).toEqual(["gen/keep.ts", "src/a.ts", "src/b.tsx"]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
 		it("should invalidate scan cache when invalidateFsScanCache receives a relative path", async () => {
 			await glob({ pattern: "*.ts", path: testDir, cache: true });
 			const newFile = path.join(testDir, "newly-added.ts");
@@ -174,6 +628,134 @@ describe("pi-natives", () => {
 			const second = await glob({ pattern: fileName, path: testDir, hidden: true, gitignore: true, cache: true });
 			expect(second.totalMatches).toBe(1);
 		});
+
+		it("should rank recorded file accesses first with frecency", async () => {
+			const dir = await fs.realpath(await fs.mkdtemp(path.join(os.tmpdir(), "natives-frecency-")));
+			const storeDir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-frecency-store-"));
+			process.env.FRECENCY_DIR = storeDir;
+			try {
+				await fs.mkdir(path.join(dir, ".git"));
+				const old = new Date(Date.now() - 30 * 24 * 60 * 60 * 1000);
+				for (const file of ["alpha.ts", "beta.ts", "gamma.ts"]) {
+					await fs.writeFile(path.join(dir, file), "");
+					await fs.utimes(path.join(dir, file), old, old);
+				}
+				recordFileAccess(path.join(dir, "gamma.ts"));
+				recordFileAccess(path.join(dir, "gamma.ts"));
+				recordFileAccess(path.join(dir, "beta.ts"));
+
+				const globbed = await glob({ pattern: "*.ts", path: dir, frecency: true, maxResults: 2 });
+				expect(globbed.matches.map(match => match.path)).toEqual(["gamma.ts", "beta.ts"]);
+
+				const found = await fuzzyFind({ query: "ts", path: dir, frecency: true });
+				expect(found.matches.map(match => match.path)).toEqual(["gamma.ts", "beta.ts", "alpha.ts"]);
+			} finally {
+				delete process.env.FRECENCY_DIR;
+				await fs.rm(dir, { recursive: true, force: true });
+				await fs.rm(storeDir, { recursive: true, force: true });
+			}
+		});
+
+		it("should report changes from watch", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-watch-"));
+			try {
+				await fs.mkdir(path.join(dir, "old"));
+				await fs.writeFile(path.join(dir, "old", "a.txt"), "a\n");
+				await fs.writeFile(path.join(dir, ".ignore"), "*.log\n");
+
+				const events: WatchEvent[] = [];
+				const watcher = await watch(dir, event => events.push(event));
+				try {
+					await fs.writeFile(path.join(dir, "new.txt"), "new\n");
+					await fs.writeFile(path.join(dir, "skip.log"), "ignored\n");
+					await fs.rm(path.join(dir, "old"), { recursive: true });
+					await Bun.sleep(400);
+				} finally {
+					watcher.close();
+				}
+
+				const changes = events.map(event => `${event.kind} ${event.path}`).sort();
+				expect(changes).toEqual(["create new.txt", "remove old", "remove old/a.txt"]);
+				expect(events.find(event => event.path === "old")?.fileType).toBe(FileType.Dir);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should share a root between watchers with different rules", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-watch-"));
+			try {
+				const visible: WatchEvent[] = [];
+				const all: WatchEvent[] = [];
+				const first = await watch(dir, event => visible.push(event));
+				const second = await watch(dir, event => all.push(event), { hidden: true });
+				try {
+					await fs.writeFile(path.join(dir, "a.txt"), "a\n");
+					await fs.writeFile(path.join(dir, ".hidden"), "h\n");
+					await Bun.sleep(400);
+					first.close();
+					await fs.writeFile(path.join(dir, "b.txt"), "b\n");
+					await Bun.sleep(400);
+				} finally {
+					first.close();
+					second.close();
+				}
+
+				expect(visible.map(event => `${event.kind} ${event.path}`)).toEqual(["create a.txt"]);
+				expect(all.map(event => `${event.kind} ${event.path}`).sort()).toEqual([
+					"create .hidden",
+					"create a.txt",
+					"create b.txt",
+				]);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should patch watched scan cache entries on invalidation", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-watch-"));
+			try {
+				await fs.writeFile(path.join(dir, "first.txt"), "first\n");
+				await glob({ pattern: "*.txt", path: dir, cache: true });
+
+				await fs.writeFile(path.join(dir, "second.txt"), "second\n");
+				invalidateFsScanCache(path.join(dir, "second.txt"));
+				const added = await glob({ pattern: "*.txt", path: dir, cache: true });
+				expect(added.matches.map(match => match.path).sort()).toEqual(["first.txt", "second.txt"]);
+
+				await fs.rm(path.join(dir, "first.txt"));
+				invalidateFsScanCache(path.join(dir, "first.txt"));
+				const removed = await glob({ pattern: "*.txt", path: dir, cache: true });
+				expect(removed.matches.map(match => match.path)).toEqual(["second.txt"]);
+
+				await fs.writeFile(path.join(dir, "third.txt"), "third\n");
+				await Bun.sleep(300);
+				const watched = await glob({ pattern: "*.txt", path: dir, cache: true });
+				expect(watched.matches.map(match => match.path).sort()).toEqual(["second.txt", "third.txt"]);
+			} finally {
+				invalidateFsScanCache(dir);
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should keep watched scan cache up to date", async () => {
+			const dir = await fs.mkdtemp(path.join(os.tmpdir(), "natives-watch-"));
+			try {
+				await fs.writeFile(path.join(dir, "first.txt"), "first\n");
+				const before = await glob({ pattern: "*.txt", path: dir, cache: true });
+				expect(before.matches.map(match => match.path)).toEqual(["first.txt"]);
+
+				await fs.writeFile(path.join(dir, "second.txt"), "second\n");
+				await fs.rm(path.join(dir, "first.txt"));
+				await Bun.sleep(300);
+
+				const after = await glob({ pattern: "*.txt", path: dir, cache: true });
+				expect(after.matches.map(match => match.path)).toEqual(["second.txt"]);
+			} finally {
+				invalidateFsScanCache(dir);
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
 	});
 	describe("htmlToMarkdown", () => {
 		it("should convert basic HTML to markdown", async () => {
@@ -242,4 +824,366 @@ describe("pi-natives", () => {
 			expect(sanitizeText(input)).toBe("hello");
 		});
 	});
+
+	describe("shell", () => {
+		it("should separate stderr and count bytes per stream", async () => {
+			const stdout: string[] = [];
+			const stderr: string[] = [];
+			const result = await executeShell(
+				{ command: "echo a; echo b >&2" },
+				chunk => stdout.push(chunk),
+				chunk => stderr.push(chunk),
+			);
+
+			expect(result.exitCode).toBe(0);
+			expect(stdout.join("")).toBe("a\n");
+			expect(stderr.join("")).toBe("b\n");
+			expect(result.stdoutBytes).toBe(2);
+			expect(result.stderrBytes).toBe(2);
+		});
+
+		it("should merge stderr into onChunk without onStderr", async () => {
+			const output: string[] = [];
+			const result = await executeShell({ command: "echo a; echo b >&2" }, chunk => output.push(chunk));
+
+			expect(output.join("")).toBe("a\nb\n");
+			expect(result.stdoutBytes).toBe(4);
+			expect(result.stderrBytes).toBeUndefined();
+		});
+
+		it.skipIf(process.platform === "win32")("should apply soft process limits to spawned commands only", async () => {
+			const command = "sh -c 'ulimit -Sn; ulimit -Hn; ulimit -St; ulimit -Ht'; ulimit -n";
+			const run = async (limits?: { openFiles: number; cpuSeconds: number }) => {
+				const output: string[] = [];
+				const result = await executeShell({ command, limits }, chunk => output.push(chunk));
+				expect(result.exitCode).toBe(0);
+				return output.join("").trim().split("\n");
+			};
+
+			const [, hardFiles, , hardCpu, shellFiles] = await run();
+			// Hard limits and the shell's own limits stay as they were.
+			expect(await run({ openFiles: 64, cpuSeconds: 5 })).toEqual(["64", hardFiles, "5", hardCpu, shellFiles]);
+		});
+
+		it("should kill commands that exceed maxOutputBytes", async () => {
+			const output: string[] = [];
+			const result = await executeShell({ command: "yes", limits: { maxOutputBytes: 1000 } }, chunk =>
+				output.push(chunk),
+			);
+
+			expect(result.outputLimitExceeded).toBe(true);
+			expect(result.exitCode).toBeUndefined();
+			expect(result.stdoutBytes).toBe(1000);
+			expect(output.join("").length).toBe(1000);
+		});
+
+		it("should pipe buffered stdin to read, pipelines and mapfile", async () => {
+			const shell = new Shell();
+			const run = async (command: string, stdin: string | Uint8Array) => {
+				const output: string[] = [];
+				const result = await shell.run({ command, stdin }, (err, chunk) => !err && output.push(chunk));
+				expect(result.exitCode).toBe(0);
+				return output.join("");
+			};
+
+			expect(await run('read line; echo "got:$line"', "hello\nworld\n")).toBe("got:hello\n");
+			expect(await run("cat | tr a-z A-Z", new TextEncoder().encode("abc"))).toBe("ABC");
+			expect(await run('mapfile -t lines; echo "${#lines[@]} ${lines[1]}"', "x\ny\nz\n")).toBe("3 y\n");
+		});
+
+		it("should stream stdin written while the command runs", async () => {
+			const shell = new Shell();
+			const output: string[] = [];
+			const running = shell.run({ command: "cat", streamStdin: true }, (err, chunk) => !err && output.push(chunk));
+
+			expect(() => shell.run({ command: "cat", streamStdin: true })).toThrow("Shell stdin stream already active");
+			shell.write("one\n");
+			shell.write(new TextEncoder().encode("two\n"));
+			shell.closeStdin();
+			const result = await running;
+
+			expect(result.exitCode).toBe(0);
+			expect(output.join("")).toBe("one\ntwo\n");
+			expect(() => shell.write("late")).toThrow("Shell stdin is not open");
+		});
+
+		it("should close streamed stdin when the command exits first", async () => {
+			const shell = new Shell();
+			const output: string[] = [];
+			const result = await shell.run(
+				{ command: "head -n1", stdin: "first\nsecond\n", streamStdin: true },
+				(err, chunk) => !err && output.push(chunk),
+			);
+
+			expect(result.exitCode).toBe(0);
+			expect(output.join("")).toBe("first\n");
+			expect(() => shell.write("late")).toThrow("Shell stdin is not open");
+			shell.closeStdin();
+		});
+
+		it("should apply the command policy to builtins, functions and paths", async () => {
+			const shell = new Shell();
+			const seen: string[] = [];
+			shell.setCommandPolicy({ deny: ["rm", "git push"], exitCode: 3 }, command => {
+				seen.push(`${command.args[0]}:${command.kind}`);
+				if (command.args[0] === "blocked") return { action: "deny", exitCode: 7, message: "no blocked" };
+				if (command.args[0] === "greeting") return { action: "rewrite", args: ["echo", "rewritten"] };
+				return undefined;
+			});
+			const run = async (command: string) => {
+				const output: string[] = [];
+				await shell.run({ command }, (err, chunk) => !err && output.push(chunk));
+				return output.join("");
+			};
+
+			expect(await run("f() { echo in-f; }; f; /bin/echo path; nosuchcmd 2>/dev/null")).toBe("in-f\npath\n");
+			expect(seen).toEqual(["f:function", "echo:builtin", "/bin/echo:external", "nosuchcmd:notFound"]);
+
+			seen.length = 0;
+			expect(await run("rm -rf /nonexistent; echo $?; /bin/rm x; echo $?; git push; echo $?")).toBe(
+				"rm: blocked by shell policy\n3\n/bin/rm: blocked by shell policy\n3\ngit: blocked by shell policy\n3\n",
+			);
+			// Denied commands never reach the callback.
+			expect(seen).toEqual(["echo:builtin", "echo:builtin", "echo:builtin"]);
+
+			seen.length = 0;
+			expect(await run("blocked; echo $?; greeting")).toBe("no blocked\n7\nrewritten\n");
+			// The rewritten command is not checked again.
+			expect(seen).toEqual(["blocked:notFound", "echo:builtin", "greeting:notFound"]);
+
+			shell.setCommandPolicy(null);
+			expect(await run("rm -f /nonexistent; echo $?")).toBe("0\n");
+		});
+
+		it("should trace commands in start order with exit codes and pipeline positions", async () => {
+			const result = await executeShell(
+				{
+					command: "f() { echo a | tr a b; false; }; f | cat; (exit 4); /bin/echo done; nosuch 2>/dev/null",
+					trace: true,
+				},
+				() => {},
+			);
+			expect(result.exitCode).toBe(127);
+			const trace = result.trace ?? [];
+			const summary = trace.map(entry => [entry.args.join(" "), entry.kind, entry.exitCode]);
+			// Commands run by the function body follow the call itself.
+			expect(summary.filter(([args]) => args !== "cat")).toEqual([
+				["f", "function", 1],
+				["echo a", "builtin", 0],
+				["tr a b", "external", 0],
+				["false", "builtin", 1],
+				["exit 4", "builtin", 4],
+				["/bin/echo done", "external", 0],
+				["nosuch", "notFound", undefined],
+			]);
+			expect(summary.findIndex(([args]) => args === "cat")).toBeGreaterThan(0);
+			expect(summary.findIndex(([args]) => args === "cat")).toBeLessThan(summary.findIndex(([args]) => args === "exit 4"));
+
+			const positions = (args: string) => {
+				const entry = trace.find(entry => entry.args.join(" ") === args);
+				return [entry?.pipelineIndex, entry?.pipelineLength];
+			};
+			expect(positions("f")).toEqual([0, 2]);
+			expect(positions("cat")).toEqual([1, 2]);
+			expect(positions("echo a")).toEqual([0, 2]);
+			expect(positions("tr a b")).toEqual([1, 2]);
+			expect(positions("false")).toEqual([0, 1]);
+
+			for (const entry of trace) {
+				expect(typeof entry.pid).toBe(entry.kind === "external" ? "number" : "undefined");
+				expect(entry.endMs ?? 0).toBeGreaterThanOrEqual(entry.startMs);
+			}
+			expect(trace.find(entry => entry.args[0] === "tr")?.path).toMatch(/\/tr$/);
+		});
+
+		it("should parse commands, pipelines and heredocs without running them", () => {
+			const parsed = parseShell('FOO=1 git commit -m "x y" 2>/dev/null && ! grep -q a <<< "$(cat f | wc -l)" || (cd /; ls)');
+			expect(parsed.error).toBeUndefined();
+			expect(parsed.commands.map(command => command.words)).toEqual([
+				["git", "commit", "-m", '"x y"'],
+				["grep", "-q", "a"],
+				["cat", "f"],
+				["wc", "-l"],
+				["cd", "/"],
+				["ls"],
+			]);
+			expect(parsed.commands[0]).toMatchObject({
+				assignments: ["FOO=1"],
+				redirects: [{ fd: 2, operator: ">", target: "/dev/null" }],
+				line: 1,
+				column: 7,
+			});
+			expect(parsed.commands[2].context).toEqual(["commandSubstitution"]);
+			expect(parsed.commands[2].line).toBeUndefined();
+			expect(parsed.commands[4].context).toEqual(["subshell"]);
+			expect(parsed.pipelines[1]).toMatchObject({ commands: [1], negated: true, operator: "&&" });
+			expect(parsed.pipelines[2]).toMatchObject({ commands: [2, 3], length: 2 });
+
+			const heredoc = parseShell("f() { cat <<EOF\nhi $x\nEOF\n}");
+			expect(heredoc.functions).toEqual(["f"]);
+			expect(heredoc.commands[0].context).toEqual(["function"]);
+			expect(heredoc.heredocs).toEqual([{ delimiter: "EOF", body: "hi $x\n", expands: true, command: 0 }]);
+		});
+
+		it("should report syntax errors for incomplete and invalid input", () => {
+			const cases: [string, string, number][] = [
+				['echo "abc', "unterminated double quote", 10],
+				["if true; then echo", "end of input", 19],
+				["echo $(", "unterminated command substitution", 8],
+				["a |", "end of input", 4],
+				["echo ) foo", "near token `)'", 6],
+			];
+			for (const [source, message, column] of cases) {
+				const parsed = parseShell(source);
+				expect(parsed.error?.message).toContain(message);
+				expect(parsed.error).toMatchObject({ line: 1, column });
+				expect(parsed.commands).toEqual([]);
+				expect(parsed.pipelines).toEqual([]);
+			}
+		});
+
+		it.skipIf(process.platform === "win32")("should time out, abort and kill waits on background jobs", async () => {
+			const shell = new Shell();
+			await shell.run({ command: "sleep 30 & /bin/sleep 30 &" });
+			const jobs = shell.jobs();
+			expect(jobs.map(job => [job.id, job.state])).toEqual([
+				[1, "running"],
+				[2, "running"],
+			]);
+
+			await expect(shell.waitJob(1, { timeoutMs: 50 })).rejects.toThrow("Timeout");
+			const controller = new AbortController();
+			setTimeout(() => controller.abort(), 50);
+			await expect(shell.waitJob(2, { signal: controller.signal })).rejects.toThrow("Aborted");
+
+			// The builtin `sleep` has no process to signal, so its task is aborted.
+			shell.killJob(1);
+			expect((await shell.waitJob(1)).state).toBe("done");
+			shell.killJob(2, "KILL");
+			const killed = await shell.waitJob(2);
+			expect(killed.exitCode).toBe(128 + 9);
+			await expect(shell.waitJob(2)).rejects.toThrow("No such job: 2");
+		});
+
+		it.skipIf(process.platform === "win32")("should list and kill jobs while a command is running", async () => {
+			const shell = new Shell();
+			await shell.run({ command: "/bin/sleep 30 &" });
+			const running = shell.run({ command: "/bin/sleep 1" });
+			await Bun.sleep(100);
+
+			const jobs = shell.jobs();
+			expect(jobs.map(job => [job.id, job.state])).toEqual([[1, "running"]]);
+			shell.killJob(1, "KILL");
+			expect((await running).exitCode).toBe(0);
+			expect((shell.jobs()).map(job => [job.id, job.exitCode])).toEqual([[1, 128 + 9]]);
+		});
+
+		it.skipIf(process.platform === "win32")("should take over a session in a PTY with line editing", async () => {
+			const dir = await fs.realpath(await fs.mkdtemp(path.join(os.tmpdir(), "natives-pty-")));
+			try {
+				await fs.mkdir(path.join(dir, "subdir_unique"));
+				const shell = new Shell();
+				await shell.run({ command: `cd '${dir}'; PS1='$ '; greet() { echo "hello-$1"; }` });
+				const pty = new PtySession();
+				let output = "";
+				const done = pty.startShell(shell, undefined, (err, chunk) => {
+					if (!err) output += chunk;
+				});
+				const type = async (keys: string) => {
+					pty.write(keys);
+					await Bun.sleep(200);
+				};
+
+				await type("greet one\r");
+				await type("echo x\r");
+				// Up twice recalls `greet one`; Left twice then `Z` edits mid-line.
+				await type("\x1b[A\x1b[A\r");
+				await type("echo abc\x1b[D\x1b[DZ\r");
+				await type("cd subd\t\r");
+				await type("pwd\r");
+				// A background job keeps the terminal open after the shell exits.
+				await type("/bin/sleep 30 &\r");
+				await type("exit\r");
+				const result = await done;
+
+				expect(result.exitCode).toBe(0);
+				const lines = output.split("\r\n");
+				expect(lines.filter(line => line.startsWith("hello-"))).toEqual(["hello-one", "hello-one"]);
+				expect(lines).toContain("aZbc");
+				expect(lines).toContain(path.join(dir, "subdir_unique"));
+				const jobs = shell.jobs();
+				expect(jobs.map(job => job.command)).toEqual(["/bin/sleep 30"]);
+				shell.killJob(jobs[0].id, "KILL");
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+
+		it("should report session state between commands", async () => {
+			const shell = new Shell();
+			const dir = await fs.realpath(os.tmpdir());
+			await shell.run({
+				command: `export GREETING=hello; LOCAL_ONLY=1; greet() { echo "$GREETING"; }; alias ll='ls -l'; cd '${dir}'; true | false`,
+			});
+
+			const state = await shell.state();
+			expect(state.env.GREETING).toBe("hello");
+			expect(state.env.LOCAL_ONLY).toBeUndefined();
+			expect(state.functions).toEqual(["greet"]);
+			expect(state.aliases).toEqual({ ll: "ls -l" });
+			expect(state.cwd).toBe(dir);
+			expect(state.lastPipelineStatuses).toEqual([0, 1]);
+
+			await shell.run({ command: "pushd / >/dev/null" });
+			const pushed = await shell.state();
+			expect(pushed.cwd).toBe("/");
+			expect(pushed.dirStack).toEqual([dir]);
+		});
+
+		it("should round-trip session state through a snapshot", async () => {
+			const dir = await fs.realpath(await fs.mkdtemp(path.join(os.tmpdir(), "natives-snapshot-")));
+			try {
+				await fs.mkdir(path.join(dir, "sub"));
+				const shell = new Shell();
+				await shell.run({
+					command: [
+						`cd '${dir}'; pushd sub >/dev/null`,
+						'SCALAR="a b"; declare -a ARR=(one two); ARR[5]=six; declare -A MAP=([k]=v)',
+						"export EXPORTED=1; readonly LOCKED=fixed",
+						'f() { echo "f:$1"; }; export -f f',
+						"alias hi='echo hi'; set -o noglob; shopt -s extglob",
+					].join("\n"),
+				});
+				const snapshot = JSON.parse(JSON.stringify(await shell.saveSnapshot()));
+				// Readonly variables set up before the restore keep their value.
+				const init = path.join(dir, "init.sh");
+				await fs.writeFile(init, "readonly PRESET=original\n");
+				snapshot.variables.push({ name: "PRESET", attributes: "", value: "snapshot" });
+
+				const restored = Shell.fromSnapshot(snapshot, { snapshotPath: init });
+				const output: string[] = [];
+				const result = await restored.run(
+					{
+						command: [
+							'echo "$SCALAR|${ARR[1]}|${ARR[5]}|${MAP[k]}|$EXPORTED|$LOCKED|$PRESET"',
+							"f x; hi",
+							"[[ -o noglob ]] && echo noglob; shopt -q extglob && echo extglob",
+							"pwd",
+						].join("\n"),
+					},
+					(err, chunk) => !err && output.push(chunk),
+				);
+				expect(result.exitCode).toBe(0);
+				expect(output.join("")).toBe(`a b|two|six|v|1|fixed|original\nf:x\nhi\nnoglob\nextglob\n${dir}/sub\n`);
+
+				const state = await restored.state();
+				expect(state.env.EXPORTED).toBe("1");
+				expect(state.dirStack).toEqual([dir]);
+				const assign = await restored.run({ command: "LOCKED=changed" });
+				expect(assign.exitCode).not.toBe(0);
+			} finally {
+				await fs.rm(dir, { recursive: true, force: true });
+			}
+		});
+	});
 });
@@ -16,6 +16,7 @@ function buildAutocompleteFuzzyDiscoveryProfile(
 	hidden: boolean;
 	gitignore: boolean;
 	cache: boolean;
+	frecency: boolean;
 } {
 	return {
 		query,
@@ -24,6 +25,7 @@ function buildAutocompleteFuzzyDiscoveryProfile(
 		hidden: true,
 		gitignore: true,
 		cache: true,
+		frecency: true,
 	};
 }
 